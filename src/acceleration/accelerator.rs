@@ -0,0 +1,202 @@
+#[cfg(test)]
+use crate::acceleration::bvh::Bvh;
+#[cfg(test)]
+use crate::acceleration::grid::Grid;
+#[cfg(test)]
+use crate::acceleration::kd_tree::KdTree;
+use crate::intersection::intersection::Intersection;
+use crate::math::bounds::Bounds;
+#[cfg(test)]
+use crate::math::tuple::Tuple;
+use crate::ray::ray::Ray;
+#[cfg(test)]
+use crate::shapes::parametric::{tessellate, torus};
+use crate::shapes::shape::Shape;
+#[cfg(test)]
+use ray_tracer::approx_eq;
+use std::fmt::Debug;
+#[cfg(test)]
+use std::time::Instant;
+
+/// Spatial index over primitives that are only known by their bounds, so the same
+/// structure serves a scene of shapes as well as the triangles of a mesh.
+/// Primitives with infinite bounds are tested against every ray, those with empty
+/// bounds against none.
+pub trait Accelerator: Debug {
+    /// Offers the primitives the ray may hit before `max_t`, roughly nearest first,
+    /// to `hit`. It gets the index of the primitive and the distance to the closest
+    /// hit so far, and returns the distance to its own hit only if that is closer.
+    /// Whatever lies beyond the closest hit is skipped. Returns the closest distance.
+    fn closest_hit(
+        &self,
+        ray: &Ray,
+        max_t: f64,
+        hit: &mut dyn FnMut(usize, f64) -> Option<f64>,
+    ) -> Option<f64>;
+
+    /// Whether `blocks` holds for any of the primitives the ray may hit before
+    /// `max_t`. Stops at the first one that does, without looking for the closest.
+    fn any_hit(&self, ray: &Ray, max_t: f64, blocks: &mut dyn FnMut(usize) -> bool) -> bool;
+
+    /// Offers every primitive the ray may hit to `visit`, behind its origin as well
+    /// as in front, each once and in no particular order. For shapes that need all
+    /// of their crossings, as containment and CSG do.
+    fn all_hits(&self, ray: &Ray, visit: &mut dyn FnMut(usize));
+
+    /// Closest hit in front of the ray among the shapes the accelerator was built
+    /// over, see `shape_bounds`.
+    fn intersect<'a>(&self, shapes: &'a [Box<dyn Shape>], ray: &Ray) -> Option<Intersection<'a>> {
+        let mut closest = None;
+        self.closest_hit(ray, f64::INFINITY, &mut |index, max_t| {
+            let hit = shapes[index]
+                .intersect(ray)
+                .into_iter()
+                .find(|hit| hit.t >= 0. && hit.t < max_t)?;
+            let t = hit.t;
+            closest = Some(hit);
+            Some(t)
+        });
+        closest
+    }
+
+    /// Whether any of the shapes blocks the ray before `max_t`, as for shadow rays.
+    fn occluded(&self, shapes: &[Box<dyn Shape>], ray: &Ray, max_t: f64) -> bool {
+        self.any_hit(ray, max_t, &mut |index| shapes[index].occludes(ray, max_t))
+    }
+}
+
+/// World space bounds of the shapes, by index, to build an accelerator from.
+pub fn shape_bounds(shapes: &[Box<dyn Shape>]) -> Vec<Bounds> {
+    shapes
+        .iter()
+        .map(|shape| shape.parent_space_bounds())
+        .collect()
+}
+
+#[test]
+fn all_hits_offer_each_primitive_once() {
+    // Small boxes along the ray and beside it, and a long one through all of them.
+    // Those beside it may be offered too, if they share a cell or leaf.
+    let mut bounds: Vec<Bounds> = (-10..=10)
+        .flat_map(|n| {
+            let z = n as f64 * 2.;
+            [0., 3.].map(|x| {
+                Bounds::create(
+                    Tuple::point(x - 0.5, -0.5, z - 0.5),
+                    Tuple::point(x + 0.5, 0.5, z + 0.5),
+                )
+            })
+        })
+        .collect();
+    bounds.push(Bounds::create(
+        Tuple::point(-1., -1., -30.),
+        Tuple::point(1., 1., 30.),
+    ));
+    let expected: Vec<usize> = (0..bounds.len()).filter(|index| index % 2 == 0).collect();
+
+    let ray = Ray::create(Tuple::point(0., 0., 1.), Tuple::vector(0., 0., 1.));
+    let accelerators: [Box<dyn Accelerator>; 4] = [
+        Box::new(Bvh::create(&bounds, 1)),
+        Box::new(Bvh::create(&bounds, 1).flatten()),
+        Box::new(KdTree::create(&bounds, 1)),
+        Box::new(Grid::create(&bounds)),
+    ];
+    for accelerator in &accelerators {
+        let mut offered = vec![];
+        accelerator.all_hits(&ray, &mut |index| offered.push(index));
+        let count = offered.len();
+        offered.sort_unstable();
+        offered.dedup();
+        assert_eq!(count, offered.len());
+        assert!(expected.iter().all(|index| offered.contains(index)));
+    }
+}
+
+/// Run with `cargo test --release -- --ignored --nocapture` to compare building and
+/// traversing the accelerators over a quarter million triangles.
+#[test]
+#[ignore]
+fn benchmark_accelerators() {
+    let mesh = tessellate(&torus(1., 0.25), 512, 256);
+    let bounds: Vec<Bounds> = (0..mesh.triangles().len())
+        .map(|primitive| Bounds::from_points(&mesh.vertices(primitive)))
+        .collect();
+    let size = 400;
+    let rays: Vec<Ray> = (0..size * size)
+        .map(|n| {
+            let (x, y) = (
+                (n % size) as f64 / size as f64,
+                (n / size) as f64 / size as f64,
+            );
+            let origin = Tuple::point(0., 3., -3.);
+            let target = Tuple::point(x * 3. - 1.5, 0., y * 3. - 1.5);
+            Ray::create(origin, (target - origin).normalize())
+        })
+        .collect();
+    println!("{} triangles, {} rays", bounds.len(), rays.len());
+
+    type Build = fn(&[Bounds]) -> Box<dyn Accelerator>;
+    let accelerators: [(&str, Build); 4] = [
+        ("bvh", |bounds| Box::new(Bvh::create(bounds, 4))),
+        ("linear bvh", |bounds| {
+            Box::new(Bvh::create(bounds, 4).flatten())
+        }),
+        ("k-d tree", |bounds| Box::new(KdTree::create(bounds, 2))),
+        ("grid", |bounds| Box::new(Grid::create(bounds))),
+    ];
+
+    let mut results = vec![];
+    for (name, build) in accelerators {
+        let start = Instant::now();
+        let accelerator = build(&bounds);
+        let built = start.elapsed();
+
+        let start = Instant::now();
+        let hits: Vec<Option<f64>> = rays
+            .iter()
+            .map(|ray| {
+                accelerator.closest_hit(ray, f64::INFINITY, &mut |primitive, max_t| {
+                    let t = mesh.intersect_triangle(ray, primitive)?.t;
+                    if t >= 0. && t < max_t {
+                        Some(t)
+                    } else {
+                        None
+                    }
+                })
+            })
+            .collect();
+        let traced = start.elapsed();
+
+        let start = Instant::now();
+        let occluded: Vec<bool> = rays
+            .iter()
+            .map(|ray| {
+                accelerator.any_hit(ray, f64::INFINITY, &mut |primitive| {
+                    mesh.intersect_triangle(ray, primitive)
+                        .is_some_and(|hit| hit.t >= 0.)
+                })
+            })
+            .collect();
+        let shadowed = start.elapsed();
+        println!(
+            "{:>10}: built in {:?}, {:.0} ns per ray, {:.0} ns per shadow ray",
+            name,
+            built,
+            traced.as_nanos() as f64 / rays.len() as f64,
+            shadowed.as_nanos() as f64 / rays.len() as f64
+        );
+        assert!(hits
+            .iter()
+            .zip(&occluded)
+            .all(|(hit, occluded)| hit.is_some() == *occluded));
+        results.push(hits);
+    }
+
+    // Rays along the seams of the mesh can find either of two triangles
+    for hits in &results[1..] {
+        for (hit, expected) in hits.iter().zip(&results[0]) {
+            assert_eq!(expected.is_some(), hit.is_some());
+            assert!(approx_eq(expected.unwrap_or(0.), hit.unwrap_or(0.)));
+        }
+    }
+}
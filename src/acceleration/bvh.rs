@@ -0,0 +1,476 @@
+#[cfg(test)]
+use crate::acceleration::accelerator::shape_bounds;
+use crate::acceleration::accelerator::Accelerator;
+use crate::acceleration::linear_bvh::LinearBvh;
+use crate::math::bounds::Bounds;
+#[cfg(test)]
+use crate::math::transformation::{scaling, translation};
+use crate::math::tuple::Tuple;
+use crate::ray::ray::Ray;
+#[cfg(test)]
+use crate::shapes::cone::Cone;
+#[cfg(test)]
+use crate::shapes::quadric::Quadric;
+#[cfg(test)]
+use crate::shapes::shape::Shape;
+#[cfg(test)]
+use ray_tracer::approx_eq;
+
+/// Candidate split planes per axis, evenly spaced across the primitives' centroids.
+const BINS: usize = 12;
+/// Cost of visiting a node, relative to testing a primitive.
+const TRAVERSAL_COST: f64 = 0.125;
+
+#[derive(Debug, Copy, Clone)]
+struct Primitive {
+    index: usize,
+    bounds: Bounds,
+    centroid: Tuple,
+}
+
+#[derive(Debug)]
+pub(crate) enum Node {
+    /// Holds the `count` primitives from `first` on in the ordered primitives.
+    Leaf {
+        bounds: Bounds,
+        first: usize,
+        count: usize,
+    },
+    /// The first child is the one on the lower side of the split along `axis`.
+    Interior {
+        bounds: Bounds,
+        axis: usize,
+        children: Box<[Node; 2]>,
+    },
+}
+
+impl Node {
+    pub(crate) fn bounds(&self) -> &Bounds {
+        match self {
+            Node::Leaf { bounds, .. } | Node::Interior { bounds, .. } => bounds,
+        }
+    }
+}
+
+/// Bounding volume hierarchy. Nodes are split where the surface area heuristic
+/// expects rays to be cheapest to trace, trying a few planes per axis rather than
+/// one at every primitive. Leaves hold at most `max_leaf_size` primitives, and
+/// fewer where splitting them up further pays off. The size is capped at what a
+/// `LinearBvh` leaf can count.
+#[derive(Debug)]
+pub struct Bvh {
+    pub(crate) root: Option<Node>,
+    /// Indices of the primitives, ordered so that every leaf holds a contiguous run.
+    pub(crate) primitives: Vec<usize>,
+    pub(crate) unbounded: Vec<usize>,
+}
+
+impl Bvh {
+    pub fn create(bounds: &[Bounds], max_leaf_size: usize) -> Bvh {
+        let mut primitives = vec![];
+        let mut unbounded = vec![];
+        for (index, bounds) in bounds.iter().enumerate() {
+            if bounds.is_empty() {
+                continue;
+            }
+            if !bounds.is_finite() {
+                unbounded.push(index);
+                continue;
+            }
+            primitives.push(Primitive {
+                index,
+                bounds: *bounds,
+                centroid: bounds.centroid(),
+            });
+        }
+
+        let root = if primitives.is_empty() {
+            None
+        } else {
+            Some(build(
+                &mut primitives,
+                0,
+                max_leaf_size.clamp(1, u16::MAX as usize),
+            ))
+        };
+        Bvh {
+            root,
+            primitives: primitives.iter().map(|primitive| primitive.index).collect(),
+            unbounded,
+        }
+    }
+
+    /// Same tree laid out in one array, see `LinearBvh`.
+    pub fn flatten(&self) -> LinearBvh {
+        LinearBvh::create(self)
+    }
+
+    /// Visits the nearer child first, so that the farther one is often skipped for
+    /// lying beyond the limit by then.
+    fn walk<F: FnMut(usize, &mut f64) -> bool>(
+        &self,
+        node: &Node,
+        ray: &Ray,
+        from: f64,
+        limit: &mut f64,
+        visit: &mut F,
+    ) -> bool {
+        match node {
+            Node::Leaf { first, count, .. } => self.primitives[*first..first + count]
+                .iter()
+                .any(|&index| visit(index, limit)),
+            Node::Interior { children, .. } => {
+                let mut visits = [
+                    (entry(children[0].bounds(), ray, from, *limit), &children[0]),
+                    (entry(children[1].bounds(), ray, from, *limit), &children[1]),
+                ];
+                if let [(Some(first), _), (Some(second), _)] = visits {
+                    if second < first {
+                        visits.swap(0, 1);
+                    }
+                }
+
+                visits.iter().any(|&(t, child)| {
+                    t.is_some_and(|t| t < *limit) && self.walk(child, ray, from, limit, visit)
+                })
+            }
+        }
+    }
+
+    /// Offers the primitives the ray may hit between `from` and `limit` to `visit`,
+    /// roughly nearest first. It may lower the limit to skip whatever lies beyond,
+    /// and returns true to stop. Returns whether it was stopped.
+    fn traverse<F: FnMut(usize, &mut f64) -> bool>(
+        &self,
+        ray: &Ray,
+        from: f64,
+        limit: &mut f64,
+        visit: &mut F,
+    ) -> bool {
+        if self.unbounded.iter().any(|&index| visit(index, limit)) {
+            return true;
+        }
+
+        match &self.root {
+            Some(root) if entry(root.bounds(), ray, from, *limit).is_some() => {
+                self.walk(root, ray, from, limit, visit)
+            }
+            _ => false,
+        }
+    }
+}
+
+impl Accelerator for Bvh {
+    fn closest_hit(
+        &self,
+        ray: &Ray,
+        max_t: f64,
+        hit: &mut dyn FnMut(usize, f64) -> Option<f64>,
+    ) -> Option<f64> {
+        let mut closest = max_t;
+        let mut found = false;
+        self.traverse(ray, 0., &mut closest, &mut |index, closest: &mut f64| {
+            if let Some(t) = hit(index, *closest) {
+                *closest = t;
+                found = true;
+            }
+            false
+        });
+        if found {
+            Some(closest)
+        } else {
+            None
+        }
+    }
+
+    fn any_hit(&self, ray: &Ray, max_t: f64, blocks: &mut dyn FnMut(usize) -> bool) -> bool {
+        let mut limit = max_t;
+        self.traverse(ray, 0., &mut limit, &mut |index, _: &mut f64| blocks(index))
+    }
+
+    fn all_hits(&self, ray: &Ray, visit: &mut dyn FnMut(usize)) {
+        let mut limit = f64::INFINITY;
+        self.traverse(
+            ray,
+            f64::NEG_INFINITY,
+            &mut limit,
+            &mut |index, _: &mut f64| {
+                visit(index);
+                false
+            },
+        );
+    }
+}
+
+/// Where the ray enters the box, unless the box lies before `from` or beyond the
+/// closest hit.
+fn entry(bounds: &Bounds, ray: &Ray, from: f64, closest: f64) -> Option<f64> {
+    match bounds.intersect(ray) {
+        Some((near, far)) if far >= from && near < closest => Some(near),
+        _ => None,
+    }
+}
+
+/// Builds the subtree over `primitives`, which start at `first` in the final order.
+fn build(primitives: &mut [Primitive], first: usize, max_leaf_size: usize) -> Node {
+    let count = primitives.len();
+    let bounds = primitives
+        .iter()
+        .fold(Bounds::empty(), |bounds, primitive| {
+            bounds.merge(&primitive.bounds)
+        });
+    let leaf = Node::Leaf {
+        bounds,
+        first,
+        count,
+    };
+    if count == 1 {
+        return leaf;
+    }
+
+    let (axis, middle) = match sah_partition(primitives, &bounds) {
+        Some((cost, axis, middle)) if count > max_leaf_size || cost < count as f64 => {
+            (axis, middle)
+        }
+        // Nothing to tell the primitives apart by, but too many of them for a leaf
+        None if count > max_leaf_size => (0, count / 2),
+        _ => return leaf,
+    };
+
+    let (left, right) = primitives.split_at_mut(middle);
+    Node::Interior {
+        bounds,
+        axis,
+        children: Box::new([
+            build(left, first, max_leaf_size),
+            build(right, first + middle, max_leaf_size),
+        ]),
+    }
+}
+
+/// Partitions the primitives at the cheapest of the boundaries between bins along
+/// any axis. By the surface area heuristic a ray through a node goes through a
+/// child with the ratio of their surface areas as chance, so the expected cost is
+/// that of visiting the node plus the children's counts weighted by the ratios.
+/// Returns the cost, relative to testing one primitive, the axis and where the
+/// second half starts.
+fn sah_partition(primitives: &mut [Primitive], bounds: &Bounds) -> Option<(f64, usize, usize)> {
+    let area = bounds.surface_area();
+    if area <= 0. {
+        return None;
+    }
+    let centroids = primitives
+        .iter()
+        .fold(Bounds::empty(), |bounds, primitive| {
+            bounds.add_point(primitive.centroid)
+        });
+    let bin = |primitive: &Primitive, axis: usize| {
+        let extent = centroids.max[axis] - centroids.min[axis];
+        let offset = (primitive.centroid[axis] - centroids.min[axis]) / extent;
+        ((offset * BINS as f64) as usize).min(BINS - 1)
+    };
+
+    // Cost, axis and last bin on the left of the cheapest split
+    let mut best: Option<(f64, usize, usize)> = None;
+    for axis in 0..3 {
+        // The smallest and largest centroids end up in the first and last bins, so
+        // no split leaves a side empty
+        if centroids.max[axis] - centroids.min[axis] <= 0. {
+            continue;
+        }
+
+        let mut bins = [(Bounds::empty(), 0); BINS];
+        for primitive in primitives.iter() {
+            let (bounds, count) = &mut bins[bin(primitive, axis)];
+            *bounds = bounds.merge(&primitive.bounds);
+            *count += 1;
+        }
+
+        let mut right_costs = [0.; BINS];
+        let (mut right, mut right_count) = (Bounds::empty(), 0);
+        for i in (1..BINS).rev() {
+            right = right.merge(&bins[i].0);
+            right_count += bins[i].1;
+            right_costs[i] = right.surface_area() * right_count as f64;
+        }
+
+        let (mut left, mut left_count) = (Bounds::empty(), 0);
+        for i in 0..BINS - 1 {
+            left = left.merge(&bins[i].0);
+            left_count += bins[i].1;
+            let cost = TRAVERSAL_COST
+                + (left.surface_area() * left_count as f64 + right_costs[i + 1]) / area;
+            if best.is_none_or(|(best_cost, _, _)| cost < best_cost) {
+                best = Some((cost, axis, i));
+            }
+        }
+    }
+
+    let (cost, axis, last) = best?;
+    let mut middle = 0;
+    for i in 0..primitives.len() {
+        if bin(&primitives[i], axis) <= last {
+            primitives.swap(i, middle);
+            middle += 1;
+        }
+    }
+    Some((cost, axis, middle))
+}
+
+#[cfg(test)]
+fn sphere_at(x: f64, y: f64, z: f64, radius: f64) -> Box<dyn Shape> {
+    let mut sphere = Quadric::create([1., 1., 1., 0., 0., 0., 0., 0., 0., -1.]);
+    sphere.set_transform(translation(x, y, z) * scaling(radius, radius, radius));
+    Box::new(sphere)
+}
+
+#[cfg(test)]
+fn scattered_spheres() -> Vec<Box<dyn Shape>> {
+    // Scrambled but repeatable offsets
+    let jitter = |n: usize| ((n * 7919) % 101) as f64 / 101. - 0.5;
+    (0..1000)
+        .map(|n| {
+            let (x, y, z) = ((n % 10) as f64, ((n / 10) % 10) as f64, (n / 100) as f64);
+            sphere_at(
+                x * 2. + jitter(n),
+                y * 2. + jitter(n + 1),
+                z * 2. + jitter(n + 2),
+                0.3 + jitter(n + 3).abs(),
+            )
+        })
+        .collect()
+}
+
+#[cfg(test)]
+fn leaf_sizes(node: &Node, sizes: &mut Vec<usize>) {
+    match node {
+        Node::Leaf { count, .. } => sizes.push(*count),
+        Node::Interior { children, .. } => {
+            leaf_sizes(&children[0], sizes);
+            leaf_sizes(&children[1], sizes);
+        }
+    }
+}
+
+#[test]
+fn closest_hit_matches_testing_every_shape() {
+    let shapes = scattered_spheres();
+    let bvh = Bvh::create(&shape_bounds(&shapes), 4);
+
+    for n in 0..200 {
+        let angle = n as f64 * 0.37;
+        let origin = Tuple::point(
+            9. + 15. * angle.cos(),
+            9. + (n % 7) as f64,
+            9. + 15. * angle.sin(),
+        );
+        let target = Tuple::point((n % 19) as f64, (n % 17) as f64, (n % 13) as f64);
+        let ray = Ray::create(origin, (target - origin).normalize());
+
+        let expected = shapes
+            .iter()
+            .filter_map(|shape| shape.intersect(&ray).hit().map(|hit| hit.t))
+            .fold(None, |nearest: Option<f64>, t| {
+                Some(nearest.map_or(t, |n| n.min(t)))
+            });
+        let hit = bvh.intersect(&shapes, &ray);
+
+        assert_eq!(expected.is_some(), hit.is_some());
+        if let (Some(expected), Some(hit)) = (expected, hit) {
+            assert!(approx_eq(expected, hit.t));
+        }
+    }
+}
+
+#[test]
+fn leaves_hold_every_primitive_once() {
+    let shapes = scattered_spheres();
+    let bvh = Bvh::create(&shape_bounds(&shapes), 3);
+
+    let mut sizes = vec![];
+    leaf_sizes(bvh.root.as_ref().unwrap(), &mut sizes);
+    assert!(sizes.iter().all(|size| *size >= 1 && *size <= 3));
+    assert_eq!(1000, sizes.iter().sum::<usize>());
+
+    let mut primitives = bvh.primitives.clone();
+    primitives.sort();
+    assert_eq!((0..1000).collect::<Vec<_>>(), primitives);
+}
+
+#[test]
+fn splits_between_clusters() {
+    let bounds: Vec<Bounds> = (0..20)
+        .map(|n| {
+            let x = if n % 2 == 0 { 0. } else { 100. } + (n / 2) as f64 * 0.1;
+            Bounds::create(Tuple::point(x, 0., 0.), Tuple::point(x + 1., 1., 1.))
+        })
+        .collect();
+    let bvh = Bvh::create(&bounds, 4);
+
+    match bvh.root.as_ref().unwrap() {
+        Node::Interior { children, .. } => {
+            assert!(children[0].bounds().max.x < 50.);
+            assert!(children[1].bounds().min.x > 50.);
+        }
+        Node::Leaf { .. } => panic!("Expected the clusters to be split"),
+    }
+}
+
+#[test]
+fn nearer_hits_prune_farther_nodes() {
+    let shapes: Vec<Box<dyn Shape>> = (0..100)
+        .map(|n| sphere_at(0., 0., n as f64 * 3., 1.))
+        .collect();
+    let bvh = Bvh::create(&shape_bounds(&shapes), 1);
+    let ray = Ray::create(Tuple::point(0., 0., -10.), Tuple::vector(0., 0., 1.));
+
+    let mut tested = 0;
+    let closest = bvh.closest_hit(&ray, f64::INFINITY, &mut |index, max_t| {
+        tested += 1;
+        let hit = shapes[index].intersect(&ray).hit()?.t;
+        if hit < max_t {
+            Some(hit)
+        } else {
+            None
+        }
+    });
+
+    assert_eq!(Some(9.), closest);
+    assert!(tested < 5);
+}
+
+#[test]
+fn any_hit_stops_at_first_blocker() {
+    let shapes: Vec<Box<dyn Shape>> = (0..100)
+        .map(|n| sphere_at(0., 0., n as f64 * 3., 1.))
+        .collect();
+    let bvh = Bvh::create(&shape_bounds(&shapes), 1);
+    let ray = Ray::create(Tuple::point(0., 0., -10.), Tuple::vector(0., 0., 1.));
+
+    let mut tested = 0;
+    assert!(bvh.any_hit(&ray, f64::INFINITY, &mut |index| {
+        tested += 1;
+        shapes[index].occludes(&ray, f64::INFINITY)
+    }));
+    assert_eq!(1, tested);
+    assert!(!bvh.occluded(&shapes, &ray, 9.));
+}
+
+#[test]
+fn unbounded_shapes_are_always_tested() {
+    let mut cone = Cone::create();
+    cone.set_transform(translation(50., 0., 0.));
+    let shapes: Vec<Box<dyn Shape>> = vec![sphere_at(0., 0., 0., 1.), Box::new(cone)];
+    let bvh = Bvh::create(&shape_bounds(&shapes), 4);
+
+    assert_eq!(vec![1], bvh.unbounded);
+    let ray = Ray::create(Tuple::point(50., 10., -20.), Tuple::vector(0., 0., 1.));
+    let hit = bvh.intersect(&shapes, &ray).unwrap();
+    assert!(std::ptr::addr_eq(&*shapes[1], hit.object));
+    assert!(approx_eq(10., hit.t));
+
+    let miss = Ray::create(Tuple::point(0., 5., -20.), Tuple::vector(0., 0., 1.));
+    assert!(Bvh::create(&shape_bounds(&shapes[..1]), 4)
+        .intersect(&shapes, &miss)
+        .is_none());
+}
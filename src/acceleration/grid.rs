@@ -0,0 +1,438 @@
+#[cfg(test)]
+use crate::acceleration::accelerator::shape_bounds;
+use crate::acceleration::accelerator::Accelerator;
+use crate::math::bounds::Bounds;
+#[cfg(test)]
+use crate::math::transformation::{scaling, translation};
+#[cfg(test)]
+use crate::math::tuple::Tuple;
+use crate::ray::ray::Ray;
+#[cfg(test)]
+use crate::shapes::parametric::{tessellate, torus};
+#[cfg(test)]
+use crate::shapes::quadric::Quadric;
+#[cfg(test)]
+use crate::shapes::shape::Shape;
+#[cfg(test)]
+use ray_tracer::approx_eq;
+use std::cell::Cell;
+
+/// Cells per primitive the grid aims for.
+const DENSITY: f64 = 3.;
+/// Most cells along any axis, which keeps huge scenes from using up the memory.
+const MAX_RESOLUTION: usize = 128;
+
+/// Uniform grid of cells over the primitives' bounds, each listing the primitives
+/// that overlap it. Building takes one pass to count the primitives per cell and
+/// another to fill them in, so it grows linearly with the number of primitives and
+/// suits scenes that are rebuilt every frame. Rays step from cell to cell in order
+/// with a 3D DDA, the algorithm of Amanatides and Woo.
+///
+/// The mailboxes are shared by all queries, which makes a grid `!Sync`: threads
+/// tracing in parallel need a grid each. A query made from within the callback of
+/// another one is answered correctly, but may have the outer query offer some of
+/// its primitives a second time.
+#[derive(Debug)]
+pub struct Grid {
+    bounds: Bounds,
+    resolution: [usize; 3],
+    cell_size: [f64; 3],
+    /// Where the primitives of each cell start in `cell_primitives`, with the end
+    /// of the last cell added.
+    cell_starts: Vec<usize>,
+    cell_primitives: Vec<usize>,
+    unbounded: Vec<usize>,
+    /// Number of the ray that last tested each primitive, so that primitives in
+    /// several cells are tested once per ray.
+    mailboxes: Vec<Cell<u64>>,
+    rays: Cell<u64>,
+}
+
+impl Grid {
+    /// About `DENSITY` cells per primitive, in cubes as far as the bounds allow.
+    pub fn create(bounds: &[Bounds]) -> Grid {
+        let mut unbounded = vec![];
+        let mut grid_bounds = Bounds::empty();
+        let mut count = 0;
+        for (index, bounds) in bounds.iter().enumerate() {
+            if bounds.is_empty() {
+                continue;
+            }
+            if !bounds.is_finite() {
+                unbounded.push(index);
+                continue;
+            }
+            grid_bounds = grid_bounds.merge(bounds);
+            count += 1;
+        }
+
+        let mut resolution = [1; 3];
+        let mut cell_size = [0.; 3];
+        if count > 0 {
+            let extent = grid_bounds.max - grid_bounds.min;
+            let longest = extent.x.max(extent.y).max(extent.z);
+            let per_unit = if longest > 0. {
+                (DENSITY * count as f64).cbrt() / longest
+            } else {
+                0.
+            };
+            for axis in 0..3 {
+                resolution[axis] =
+                    ((extent[axis] * per_unit).round() as usize).clamp(1, MAX_RESOLUTION);
+                cell_size[axis] = extent[axis] / resolution[axis] as f64;
+            }
+        }
+
+        let mut grid = Grid {
+            bounds: grid_bounds,
+            resolution,
+            cell_size,
+            cell_starts: vec![],
+            cell_primitives: vec![],
+            unbounded,
+            mailboxes: (0..bounds.len()).map(|_| Cell::new(0)).collect(),
+            rays: Cell::new(0),
+        };
+        grid.fill(bounds);
+        grid
+    }
+
+    fn fill(&mut self, bounds: &[Bounds]) {
+        let cells = self.resolution.iter().product::<usize>();
+        let overlapping: Vec<(usize, [usize; 3], [usize; 3])> = bounds
+            .iter()
+            .enumerate()
+            .filter(|(_, bounds)| !bounds.is_empty() && bounds.is_finite())
+            .map(|(index, bounds)| {
+                let first = [0, 1, 2].map(|axis| self.cell_at(bounds.min[axis], axis));
+                let last = [0, 1, 2].map(|axis| self.cell_at(bounds.max[axis], axis));
+                (index, first, last)
+            })
+            .collect();
+
+        // Count first, so every cell's primitives can go into one array
+        let mut counts = vec![0; cells];
+        for (_, first, last) in overlapping.iter() {
+            self.for_each_cell(first, last, |cell| counts[cell] += 1);
+        }
+        let mut starts = Vec::with_capacity(cells + 1);
+        starts.push(0);
+        for count in counts.iter() {
+            starts.push(starts[starts.len() - 1] + count);
+        }
+
+        let mut next = starts.clone();
+        let mut cell_primitives = vec![0; starts[cells]];
+        for (index, first, last) in overlapping.iter() {
+            self.for_each_cell(first, last, |cell| {
+                cell_primitives[next[cell]] = *index;
+                next[cell] += 1;
+            });
+        }
+
+        self.cell_starts = starts;
+        self.cell_primitives = cell_primitives;
+    }
+
+    fn cell_at(&self, value: f64, axis: usize) -> usize {
+        if self.cell_size[axis] <= 0. {
+            return 0;
+        }
+        let cell = ((value - self.bounds.min[axis]) / self.cell_size[axis]).floor();
+        (cell.max(0.) as usize).min(self.resolution[axis] - 1)
+    }
+
+    fn cell_index(&self, cell: [usize; 3]) -> usize {
+        (cell[2] * self.resolution[1] + cell[1]) * self.resolution[0] + cell[0]
+    }
+
+    fn for_each_cell(&self, first: &[usize; 3], last: &[usize; 3], mut action: impl FnMut(usize)) {
+        for z in first[2]..=last[2] {
+            for y in first[1]..=last[1] {
+                for x in first[0]..=last[0] {
+                    action(self.cell_index([x, y, z]));
+                }
+            }
+        }
+    }
+
+    /// Offers the primitives the ray may hit between `from` and `limit` to `visit`,
+    /// roughly nearest first. It may lower the limit to skip whatever lies beyond,
+    /// and returns true to stop. Returns whether it was stopped.
+    fn traverse<F: FnMut(usize, &mut f64) -> bool>(
+        &self,
+        ray: &Ray,
+        from: f64,
+        limit: &mut f64,
+        visit: &mut F,
+    ) -> bool {
+        if self.unbounded.iter().any(|&index| visit(index, limit)) {
+            return true;
+        }
+
+        let t_enter = match self.bounds.intersect(ray) {
+            Some((t_enter, t_exit)) if t_exit >= from && !self.cell_primitives.is_empty() => {
+                t_enter.max(from)
+            }
+            _ => return false,
+        };
+        let ray_number = self.rays.get() + 1;
+        self.rays.set(ray_number);
+
+        // Per axis: the cell the ray is in, which way it steps, the distance at which
+        // it crosses into the next cell and the distance between crossings
+        let entry = ray.position(t_enter);
+        let mut cell = [0, 1, 2].map(|axis| self.cell_at(entry[axis], axis));
+        let mut step = [0_isize; 3];
+        let mut t_next = [f64::INFINITY; 3];
+        let mut t_delta = [f64::INFINITY; 3];
+        for axis in 0..3 {
+            let (origin, direction) = (ray.origin[axis], ray.direction[axis]);
+            let lower = self.bounds.min[axis] + cell[axis] as f64 * self.cell_size[axis];
+            if direction > 0. {
+                step[axis] = 1;
+                t_next[axis] = (lower + self.cell_size[axis] - origin) / direction;
+                t_delta[axis] = self.cell_size[axis] / direction;
+            } else if direction < 0. {
+                step[axis] = -1;
+                t_next[axis] = (lower - origin) / direction;
+                t_delta[axis] = -self.cell_size[axis] / direction;
+            }
+        }
+
+        loop {
+            let index = self.cell_index(cell);
+            for &primitive in
+                &self.cell_primitives[self.cell_starts[index]..self.cell_starts[index + 1]]
+            {
+                let mailbox = &self.mailboxes[primitive];
+                if mailbox.get() == ray_number {
+                    continue;
+                }
+                mailbox.set(ray_number);
+                if visit(primitive, limit) {
+                    return true;
+                }
+            }
+
+            let axis = if t_next[0] < t_next[1] {
+                if t_next[0] < t_next[2] {
+                    0
+                } else {
+                    2
+                }
+            } else if t_next[1] < t_next[2] {
+                1
+            } else {
+                2
+            };
+            // Hits in this cell are nearer than anything in the ones after it
+            if *limit <= t_next[axis] {
+                break;
+            }
+
+            let next = cell[axis] as isize + step[axis];
+            if next < 0 || next >= self.resolution[axis] as isize {
+                break;
+            }
+            cell[axis] = next as usize;
+            t_next[axis] += t_delta[axis];
+        }
+        false
+    }
+}
+
+impl Accelerator for Grid {
+    fn closest_hit(
+        &self,
+        ray: &Ray,
+        max_t: f64,
+        hit: &mut dyn FnMut(usize, f64) -> Option<f64>,
+    ) -> Option<f64> {
+        let mut closest = max_t;
+        let mut found = false;
+        self.traverse(ray, 0., &mut closest, &mut |index, closest: &mut f64| {
+            if let Some(t) = hit(index, *closest) {
+                *closest = t;
+                found = true;
+            }
+            false
+        });
+        if found {
+            Some(closest)
+        } else {
+            None
+        }
+    }
+
+    fn any_hit(&self, ray: &Ray, max_t: f64, blocks: &mut dyn FnMut(usize) -> bool) -> bool {
+        let mut limit = max_t;
+        self.traverse(ray, 0., &mut limit, &mut |index, _: &mut f64| blocks(index))
+    }
+
+    fn all_hits(&self, ray: &Ray, visit: &mut dyn FnMut(usize)) {
+        let mut limit = f64::INFINITY;
+        self.traverse(
+            ray,
+            f64::NEG_INFINITY,
+            &mut limit,
+            &mut |index, _: &mut f64| {
+                visit(index);
+                false
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+fn sphere_at(x: f64, y: f64, z: f64, radius: f64) -> Box<dyn Shape> {
+    let mut sphere = Quadric::create([1., 1., 1., 0., 0., 0., 0., 0., 0., -1.]);
+    sphere.set_transform(translation(x, y, z) * scaling(radius, radius, radius));
+    Box::new(sphere)
+}
+
+#[test]
+fn resolution_follows_count_and_shape() {
+    let cube: Vec<Bounds> = (0..1000)
+        .map(|n| {
+            let corner = Tuple::point((n % 10) as f64, ((n / 10) % 10) as f64, (n / 100) as f64);
+            Bounds::create(corner, corner + Tuple::vector(1., 1., 1.))
+        })
+        .collect();
+    // Cube root of three cells for each of the thousand
+    assert_eq!([14, 14, 14], Grid::create(&cube).resolution);
+
+    let flat: Vec<Bounds> = (0..1000)
+        .map(|n| {
+            let corner = Tuple::point((n % 40) as f64, 0., (n / 40) as f64 * 0.1);
+            Bounds::create(corner, corner + Tuple::vector(1., 0., 0.1))
+        })
+        .collect();
+    let grid = Grid::create(&flat);
+    assert_eq!(1, grid.resolution[1]);
+    assert!(grid.resolution[0] > 5 * grid.resolution[2]);
+}
+
+#[test]
+fn closest_hit_matches_testing_every_shape() {
+    let jitter = |n: usize| ((n * 7919) % 101) as f64 / 101. - 0.5;
+    let shapes: Vec<Box<dyn Shape>> = (0..500)
+        .map(|n| {
+            let (x, y, z) = ((n % 10) as f64, ((n / 10) % 5) as f64, (n / 50) as f64);
+            sphere_at(
+                x * 2. + jitter(n),
+                y * 3. + jitter(n + 1),
+                z * 2.,
+                0.3 + jitter(n + 2).abs(),
+            )
+        })
+        .collect();
+    let grid = Grid::create(&shape_bounds(&shapes));
+
+    for n in 0..200 {
+        let angle = n as f64 * 0.29;
+        let origin = Tuple::point(
+            9. + 16. * angle.cos(),
+            (n % 9) as f64 * 2.,
+            9. + 16. * angle.sin(),
+        );
+        let target = Tuple::point((n % 19) as f64, (n % 5) as f64 * 3., (n % 13) as f64 * 1.5);
+        let ray = Ray::create(origin, (target - origin).normalize());
+
+        let expected = shapes
+            .iter()
+            .filter_map(|shape| shape.intersect(&ray).hit().map(|hit| hit.t))
+            .fold(None, |nearest: Option<f64>, t| {
+                Some(nearest.map_or(t, |n| n.min(t)))
+            });
+        let hit = grid.intersect(&shapes, &ray).map(|hit| hit.t);
+
+        assert_eq!(expected.is_some(), hit.is_some());
+        if let (Some(expected), Some(hit)) = (expected, hit) {
+            assert!(approx_eq(expected, hit));
+        }
+    }
+}
+
+#[test]
+fn axis_aligned_rays_through_triangles() {
+    let mesh = tessellate(&torus(1., 0.25), 24, 12);
+    let bounds: Vec<Bounds> = (0..mesh.triangles().len())
+        .map(|primitive| Bounds::from_points(&mesh.vertices(primitive)))
+        .collect();
+    let grid = Grid::create(&bounds);
+    let directions = [
+        Tuple::vector(1., 0., 0.),
+        Tuple::vector(0., -1., 0.),
+        Tuple::vector(0., 0., -1.),
+    ];
+
+    for n in 0..150 {
+        let direction = directions[n % 3];
+        let offset = Tuple::vector(
+            (n % 7) as f64 * 0.2 - 0.6,
+            (n % 5) as f64 * 0.1 - 0.2,
+            (n % 11) as f64 * 0.2 - 1.,
+        );
+        let ray = Ray::create(
+            Tuple::point(0., 0., 0.) + offset - direction * 3.,
+            direction,
+        );
+
+        let expected = (0..mesh.triangles().len())
+            .filter_map(|primitive| mesh.intersect_triangle(&ray, primitive))
+            .map(|hit| hit.t)
+            .filter(|t| *t >= 0.)
+            .fold(None, |nearest: Option<f64>, t| {
+                Some(nearest.map_or(t, |n| n.min(t)))
+            });
+        let hit = grid.closest_hit(&ray, f64::INFINITY, &mut |primitive, max_t| {
+            let t = mesh.intersect_triangle(&ray, primitive)?.t;
+            if t >= 0. && t < max_t {
+                Some(t)
+            } else {
+                None
+            }
+        });
+
+        assert_eq!(expected, hit);
+    }
+}
+
+#[test]
+fn primitives_in_many_cells_are_tested_once() {
+    let mut shapes: Vec<Box<dyn Shape>> = (0..100)
+        .map(|n| sphere_at((n % 10) as f64 * 2., 0., (n / 10) as f64 * 2., 0.5))
+        .collect();
+    // Spans most of the grid, and lies behind every small sphere on the ray
+    shapes.push(sphere_at(9., -20., 9., 19.5));
+    let grid = Grid::create(&shape_bounds(&shapes));
+    let ray = Ray::create(
+        Tuple::point(1., 5., -5.),
+        Tuple::vector(0., -1., 1.).normalize(),
+    );
+
+    for _ in 0..2 {
+        let mut tested = vec![0; shapes.len()];
+        grid.closest_hit(&ray, f64::INFINITY, &mut |index, _| {
+            tested[index] += 1;
+            None
+        });
+        assert_eq!(1, tested[100]);
+        assert!(tested.iter().all(|count| *count <= 1));
+    }
+}
+
+#[test]
+fn empty_and_unbounded_grids() {
+    let ray = Ray::create(Tuple::point(0., 0., -5.), Tuple::vector(0., 0., 1.));
+    assert!(Grid::create(&[])
+        .closest_hit(&ray, f64::INFINITY, &mut |_, _| Some(1.))
+        .is_none());
+
+    let unbounded = Grid::create(&[Bounds::infinite(), Bounds::empty()]);
+    assert_eq!(
+        Some(1.),
+        unbounded.closest_hit(&ray, f64::INFINITY, &mut |_, _| Some(1.))
+    );
+}
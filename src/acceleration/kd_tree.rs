@@ -0,0 +1,505 @@
+#[cfg(test)]
+use crate::acceleration::accelerator::shape_bounds;
+use crate::acceleration::accelerator::Accelerator;
+use crate::math::bounds::Bounds;
+#[cfg(test)]
+use crate::math::transformation::translation;
+#[cfg(test)]
+use crate::math::tuple::Tuple;
+use crate::ray::ray::Ray;
+#[cfg(test)]
+use crate::shapes::parametric::{tessellate, torus};
+#[cfg(test)]
+use crate::shapes::quadric::Quadric;
+#[cfg(test)]
+use crate::shapes::shape::Shape;
+#[cfg(test)]
+use ray_tracer::approx_eq;
+
+/// Cost of stepping through a node, relative to testing a primitive.
+const TRAVERSAL_COST: f64 = 1. / 80.;
+/// Discount on splits that cut off empty space, which rays then skip altogether.
+const EMPTY_BONUS: f64 = 0.5;
+/// How many splits that do not pay off may follow each other before giving up.
+const MAX_BAD_REFINES: usize = 3;
+
+#[derive(Debug)]
+enum KdNode {
+    /// Holds the `count` primitives from `first` on in the tree's primitives.
+    Leaf { first: usize, count: usize },
+    /// The child below the split directly follows this node, `above` is the index
+    /// of the other one.
+    Interior {
+        axis: usize,
+        split: f64,
+        above: usize,
+    },
+}
+
+/// Part of the tree still to visit, and the range of `t` the ray spends in it.
+struct Pending {
+    node: usize,
+    t_min: f64,
+    t_max: f64,
+}
+
+/// k-d tree, splitting space rather than the primitives: a primitive that straddles
+/// a split goes into both halves. Splits are placed at the sides of primitives by
+/// the surface area heuristic, favouring those that cut off empty space. Leaves
+/// hold at most `max_leaf_size` primitives unless the splits stop paying off or the
+/// tree gets too deep.
+#[derive(Debug)]
+pub struct KdTree {
+    bounds: Bounds,
+    nodes: Vec<KdNode>,
+    /// Indices of the primitives in each leaf, one run after another.
+    primitives: Vec<usize>,
+    unbounded: Vec<usize>,
+}
+
+impl KdTree {
+    pub fn create(bounds: &[Bounds], max_leaf_size: usize) -> KdTree {
+        let mut primitives = vec![];
+        let mut unbounded = vec![];
+        for (index, bounds) in bounds.iter().enumerate() {
+            if bounds.is_empty() {
+                continue;
+            }
+            if !bounds.is_finite() {
+                unbounded.push(index);
+                continue;
+            }
+            primitives.push(index);
+        }
+
+        let tree_bounds = primitives
+            .iter()
+            .fold(Bounds::empty(), |tree, index| tree.merge(&bounds[*index]));
+        let mut tree = KdTree {
+            bounds: tree_bounds,
+            nodes: vec![],
+            primitives: vec![],
+            unbounded,
+        };
+        if !primitives.is_empty() {
+            let max_depth = (8. + 1.3 * (primitives.len() as f64).log2()).round() as usize;
+            tree.build(
+                bounds,
+                primitives,
+                tree_bounds,
+                max_leaf_size.max(1),
+                max_depth,
+                0,
+            );
+        }
+        tree
+    }
+
+    fn build(
+        &mut self,
+        all: &[Bounds],
+        primitives: Vec<usize>,
+        bounds: Bounds,
+        max_leaf_size: usize,
+        depth: usize,
+        bad_refines: usize,
+    ) {
+        let count = primitives.len();
+        let leaf_cost = count as f64;
+        let split = if count <= max_leaf_size || depth == 0 {
+            None
+        } else {
+            cheapest_split(all, &primitives, &bounds)
+        };
+
+        let (axis, position, bad_refines) = match split {
+            Some((cost, axis, position)) if cost < leaf_cost => (axis, position, bad_refines),
+            Some((cost, axis, position))
+                if bad_refines + 1 < MAX_BAD_REFINES && (cost < 4. * leaf_cost || count > 16) =>
+            {
+                (axis, position, bad_refines + 1)
+            }
+            _ => {
+                self.nodes.push(KdNode::Leaf {
+                    first: self.primitives.len(),
+                    count,
+                });
+                self.primitives.extend(primitives);
+                return;
+            }
+        };
+
+        // Primitives lying in the plane of the split go to both sides
+        let below: Vec<usize> = primitives
+            .iter()
+            .copied()
+            .filter(|index| all[*index].min[axis] < position || all[*index].max[axis] <= position)
+            .collect();
+        let above: Vec<usize> = primitives
+            .into_iter()
+            .filter(|index| all[*index].max[axis] > position || all[*index].min[axis] >= position)
+            .collect();
+        let (mut below_bounds, mut above_bounds) = (bounds, bounds);
+        below_bounds.max[axis] = position;
+        above_bounds.min[axis] = position;
+
+        let node = self.nodes.len();
+        self.nodes.push(KdNode::Interior {
+            axis,
+            split: position,
+            above: 0,
+        });
+        self.build(
+            all,
+            below,
+            below_bounds,
+            max_leaf_size,
+            depth - 1,
+            bad_refines,
+        );
+        let above_index = self.nodes.len();
+        if let KdNode::Interior { above, .. } = &mut self.nodes[node] {
+            *above = above_index;
+        }
+        self.build(
+            all,
+            above,
+            above_bounds,
+            max_leaf_size,
+            depth - 1,
+            bad_refines,
+        );
+    }
+}
+
+/// Cheapest plane through a side of one of the primitives, by the surface area
+/// heuristic: the expected cost of tracing a ray through the node is that of the
+/// traversal step plus the counts on either side weighted by the chance of the
+/// ray going through that side, the ratio of its surface area to the node's.
+/// Returns the cost, relative to testing one primitive, the axis and the position.
+fn cheapest_split(
+    all: &[Bounds],
+    primitives: &[usize],
+    bounds: &Bounds,
+) -> Option<(f64, usize, f64)> {
+    let area = bounds.surface_area();
+    if area <= 0. {
+        return None;
+    }
+    let size = bounds.max - bounds.min;
+
+    let mut best: Option<(f64, usize, f64)> = None;
+    for axis in 0..3 {
+        let mut starts: Vec<f64> = primitives
+            .iter()
+            .map(|index| all[*index].min[axis])
+            .collect();
+        let mut ends: Vec<f64> = primitives
+            .iter()
+            .map(|index| all[*index].max[axis])
+            .collect();
+        starts.sort_by(f64::total_cmp);
+        ends.sort_by(f64::total_cmp);
+
+        for &position in starts.iter().chain(ends.iter()) {
+            if position <= bounds.min[axis] || position >= bounds.max[axis] {
+                continue;
+            }
+
+            let below = starts.partition_point(|start| *start < position);
+            let above = ends.len() - ends.partition_point(|end| *end <= position);
+            // Areas of the two halves, which share the sides across the axis
+            let (other1, other2) = (size[(axis + 1) % 3], size[(axis + 2) % 3]);
+            let side_area = |length: f64| 2. * (other1 * other2 + length * (other1 + other2));
+            let below_area = side_area(position - bounds.min[axis]);
+            let above_area = side_area(bounds.max[axis] - position);
+
+            let bonus = if below == 0 || above == 0 {
+                EMPTY_BONUS
+            } else {
+                0.
+            };
+            let cost = TRAVERSAL_COST
+                + (1. - bonus) * (below_area * below as f64 + above_area * above as f64) / area;
+            if best.is_none_or(|(best_cost, _, _)| cost < best_cost) {
+                best = Some((cost, axis, position));
+            }
+        }
+    }
+    best
+}
+
+impl KdTree {
+    /// Offers the primitives the ray may hit between `from` and `limit` to `visit`,
+    /// roughly nearest first. It may lower the limit to skip whatever lies beyond,
+    /// and returns true to stop. Returns whether it was stopped.
+    fn traverse<F: FnMut(usize, &mut f64) -> bool>(
+        &self,
+        ray: &Ray,
+        from: f64,
+        limit: &mut f64,
+        visit: &mut F,
+    ) -> bool {
+        if self.unbounded.iter().any(|&index| visit(index, limit)) {
+            return true;
+        }
+
+        let (t_min, t_max) = match self.bounds.intersect(ray) {
+            Some((t_min, t_max)) if !self.nodes.is_empty() && t_max >= from => {
+                (t_min.max(from), t_max)
+            }
+            _ => return false,
+        };
+
+        // Nodes are visited front to back, so the search is over as soon as the
+        // limit lies before the next one
+        let mut stack = Vec::with_capacity(64);
+        let mut next = Some(Pending {
+            node: 0,
+            t_min,
+            t_max,
+        });
+        while let Some(Pending {
+            mut node,
+            t_min,
+            mut t_max,
+        }) = next
+        {
+            if *limit < t_min {
+                break;
+            }
+
+            loop {
+                match self.nodes[node] {
+                    KdNode::Interior { axis, split, above } => {
+                        // The side the ray is on before it crosses the split, and after
+                        let (origin, direction) = (ray.origin[axis], ray.direction[axis]);
+                        let below_first = direction > 0. || (direction == 0. && origin < split);
+                        let (first, second) = if below_first {
+                            (node + 1, above)
+                        } else {
+                            (above, node + 1)
+                        };
+
+                        let t_split = (split - origin) / direction;
+                        if direction == 0. && origin == split {
+                            // Runs along the split, where both sides have primitives
+                            stack.push(Pending {
+                                node: second,
+                                t_min,
+                                t_max,
+                            });
+                            node = first;
+                        } else if direction == 0. || t_split > t_max {
+                            node = first;
+                        } else if t_split <= t_min {
+                            node = second;
+                        } else {
+                            stack.push(Pending {
+                                node: second,
+                                t_min: t_split,
+                                t_max,
+                            });
+                            node = first;
+                            t_max = t_split;
+                        }
+                    }
+                    KdNode::Leaf { first, count } => {
+                        if self.primitives[first..first + count]
+                            .iter()
+                            .any(|&index| visit(index, limit))
+                        {
+                            return true;
+                        }
+                        break;
+                    }
+                }
+            }
+            next = stack.pop();
+        }
+
+        false
+    }
+}
+
+impl Accelerator for KdTree {
+    fn closest_hit(
+        &self,
+        ray: &Ray,
+        max_t: f64,
+        hit: &mut dyn FnMut(usize, f64) -> Option<f64>,
+    ) -> Option<f64> {
+        let mut closest = max_t;
+        let mut found = false;
+        self.traverse(ray, 0., &mut closest, &mut |index, closest: &mut f64| {
+            if let Some(t) = hit(index, *closest) {
+                *closest = t;
+                found = true;
+            }
+            false
+        });
+        if found {
+            Some(closest)
+        } else {
+            None
+        }
+    }
+
+    fn any_hit(&self, ray: &Ray, max_t: f64, blocks: &mut dyn FnMut(usize) -> bool) -> bool {
+        let mut limit = max_t;
+        self.traverse(ray, 0., &mut limit, &mut |index, _: &mut f64| blocks(index))
+    }
+
+    /// Primitives can sit in several leaves, so they are gathered up first to offer
+    /// each one once.
+    fn all_hits(&self, ray: &Ray, visit: &mut dyn FnMut(usize)) {
+        let mut indices = vec![];
+        let mut limit = f64::INFINITY;
+        self.traverse(
+            ray,
+            f64::NEG_INFINITY,
+            &mut limit,
+            &mut |index, _: &mut f64| {
+                indices.push(index);
+                false
+            },
+        );
+        indices.sort_unstable();
+        indices.dedup();
+        indices.into_iter().for_each(visit);
+    }
+}
+
+#[cfg(test)]
+fn scattered_spheres() -> Vec<Box<dyn Shape>> {
+    let jitter = |n: usize| ((n * 7919) % 101) as f64 / 101. - 0.5;
+    (0..500)
+        .map(|n| {
+            let mut sphere = Quadric::create([1., 1., 1., 0., 0., 0., 0., 0., 0., -1.]);
+            let (x, y, z) = ((n % 10) as f64, ((n / 10) % 5) as f64, (n / 50) as f64);
+            sphere.set_transform(translation(
+                x * 2. + jitter(n),
+                y * 2. + jitter(n + 1),
+                z * 2. + jitter(n + 2),
+            ));
+            Box::new(sphere) as Box<dyn Shape>
+        })
+        .collect()
+}
+
+#[test]
+fn closest_hit_matches_testing_every_shape() {
+    let shapes = scattered_spheres();
+    let tree = KdTree::create(&shape_bounds(&shapes), 2);
+
+    for n in 0..200 {
+        let angle = n as f64 * 0.41;
+        let origin = Tuple::point(
+            9. + 15. * angle.cos(),
+            (n % 9) as f64,
+            9. + 15. * angle.sin(),
+        );
+        let target = Tuple::point((n % 19) as f64, (n % 5) as f64 * 2., (n % 13) as f64 * 1.5);
+        let ray = Ray::create(origin, (target - origin).normalize());
+
+        let expected = shapes
+            .iter()
+            .filter_map(|shape| shape.intersect(&ray).hit().map(|hit| hit.t))
+            .fold(None, |nearest: Option<f64>, t| {
+                Some(nearest.map_or(t, |n| n.min(t)))
+            });
+        let hit = tree.intersect(&shapes, &ray).map(|hit| hit.t);
+
+        assert_eq!(expected.is_some(), hit.is_some());
+        if let (Some(expected), Some(hit)) = (expected, hit) {
+            assert!(approx_eq(expected, hit));
+        }
+    }
+}
+
+#[test]
+fn axis_aligned_rays_through_triangles() {
+    // Flat triangles and rays along the axes put primitives and origins right on
+    // the splitting planes
+    let mesh = tessellate(&torus(1., 0.25), 24, 12);
+    let bounds: Vec<Bounds> = (0..mesh.triangles().len())
+        .map(|primitive| Bounds::from_points(&mesh.vertices(primitive)))
+        .collect();
+    let tree = KdTree::create(&bounds, 1);
+    let directions = [
+        Tuple::vector(1., 0., 0.),
+        Tuple::vector(0., -1., 0.),
+        Tuple::vector(0., 0., -1.),
+    ];
+
+    for n in 0..150 {
+        let direction = directions[n % 3];
+        let offset = Tuple::vector(
+            (n % 7) as f64 * 0.2 - 0.6,
+            (n % 5) as f64 * 0.1 - 0.2,
+            (n % 11) as f64 * 0.2 - 1.,
+        );
+        let origin = Tuple::point(0., 0., 0.) + offset - direction * 3.;
+        let ray = Ray::create(origin, direction);
+
+        let expected = (0..mesh.triangles().len())
+            .filter_map(|primitive| mesh.intersect_triangle(&ray, primitive))
+            .map(|hit| hit.t)
+            .filter(|t| *t >= 0.)
+            .fold(None, |nearest: Option<f64>, t| {
+                Some(nearest.map_or(t, |n| n.min(t)))
+            });
+        let hit = tree.closest_hit(&ray, f64::INFINITY, &mut |primitive, max_t| {
+            let t = mesh.intersect_triangle(&ray, primitive)?.t;
+            if t >= 0. && t < max_t {
+                Some(t)
+            } else {
+                None
+            }
+        });
+
+        assert_eq!(expected, hit);
+    }
+}
+
+#[test]
+fn splits_off_empty_space() {
+    let bounds: Vec<Bounds> = (0..20)
+        .map(|n| {
+            let x = if n < 10 { n as f64 } else { 90. + n as f64 };
+            Bounds::create(Tuple::point(x, 0., 0.), Tuple::point(x + 1., 1., 1.))
+        })
+        .collect();
+    let tree = KdTree::create(&bounds, 4);
+
+    match tree.nodes[0] {
+        KdNode::Interior { axis, split, .. } => {
+            assert_eq!(0, axis);
+            assert!((10. ..=100.).contains(&split));
+        }
+        KdNode::Leaf { .. } => panic!("Expected the clusters to be split"),
+    }
+}
+
+#[test]
+fn overlapping_primitives_end_up_in_one_leaf() {
+    let bounds = vec![Bounds::create(Tuple::point(0., 0., 0.), Tuple::point(1., 1., 1.)); 50];
+    let tree = KdTree::create(&bounds, 4);
+
+    assert_eq!(1, tree.nodes.len());
+    assert_eq!(50, tree.primitives.len());
+}
+
+#[test]
+fn empty_and_unbounded_trees() {
+    let ray = Ray::create(Tuple::point(0., 0., -5.), Tuple::vector(0., 0., 1.));
+    assert!(KdTree::create(&[], 4)
+        .closest_hit(&ray, f64::INFINITY, &mut |_, _| Some(1.))
+        .is_none());
+
+    let unbounded = KdTree::create(&[Bounds::infinite(), Bounds::empty()], 4);
+    assert_eq!(
+        Some(1.),
+        unbounded.closest_hit(&ray, f64::INFINITY, &mut |_, _| Some(1.))
+    );
+}
@@ -0,0 +1,320 @@
+#[cfg(test)]
+use crate::acceleration::accelerator::shape_bounds;
+use crate::acceleration::accelerator::Accelerator;
+use crate::acceleration::bvh::{Bvh, Node};
+use crate::math::bounds::Bounds;
+#[cfg(test)]
+use crate::math::transformation::translation;
+#[cfg(test)]
+use crate::math::tuple::Tuple;
+use crate::ray::ray::Ray;
+#[cfg(test)]
+use crate::shapes::quadric::Quadric;
+#[cfg(test)]
+use crate::shapes::shape::Shape;
+#[cfg(test)]
+use ray_tracer::approx_eq;
+use std::convert::TryFrom;
+
+/// Node packed into 32 bytes, so that two share a cache line. The bounds are kept
+/// in single precision, rounded outwards. An interior node is followed by its first
+/// child and points at the second one with `offset`, a leaf points at its first
+/// primitive instead.
+#[derive(Debug, Copy, Clone)]
+#[repr(C)]
+struct LinearNode {
+    min: [f32; 3],
+    max: [f32; 3],
+    offset: u32,
+    /// Number of primitives in a leaf, zero for interior nodes.
+    count: u16,
+    axis: u8,
+    _padding: u8,
+}
+
+impl LinearNode {
+    fn create(bounds: &Bounds, offset: usize, count: usize, axis: usize) -> LinearNode {
+        let down = |value: f64| {
+            let rounded = value as f32;
+            if rounded as f64 > value {
+                rounded.next_down()
+            } else {
+                rounded
+            }
+        };
+        let up = |value: f64| {
+            let rounded = value as f32;
+            if (rounded as f64) < value {
+                rounded.next_up()
+            } else {
+                rounded
+            }
+        };
+
+        LinearNode {
+            min: [down(bounds.min.x), down(bounds.min.y), down(bounds.min.z)],
+            max: [up(bounds.max.x), up(bounds.max.y), up(bounds.max.z)],
+            offset: u32::try_from(offset).expect("Too many nodes for a flattened BVH!"),
+            count: u16::try_from(count).expect("Too many primitives in a BVH leaf!"),
+            axis: axis as u8,
+            _padding: 0,
+        }
+    }
+
+    /// Whether the ray passes through the box between `from` and `closest`.
+    fn is_entered(&self, origin: &[f64; 3], inverse: &[f64; 3], from: f64, closest: f64) -> bool {
+        let (mut near, mut far) = (from, closest);
+        for axis in 0..3 {
+            let low = self.min[axis] as f64 - origin[axis];
+            let high = self.max[axis] as f64 - origin[axis];
+            if inverse[axis].is_infinite() {
+                // Parallel to the sides, so either always between them or never
+                if low > 0. || high < 0. {
+                    return false;
+                }
+                continue;
+            }
+
+            let (t0, t1) = (low * inverse[axis], high * inverse[axis]);
+            near = t0.min(t1).max(near);
+            far = t0.max(t1).min(far);
+        }
+        near <= far
+    }
+}
+
+/// BVH flattened into a single array of nodes in depth first order, for rays to
+/// walk without recursion or following pointers. Build a `Bvh` and flatten it.
+#[derive(Debug)]
+pub struct LinearBvh {
+    nodes: Vec<LinearNode>,
+    primitives: Vec<usize>,
+    unbounded: Vec<usize>,
+}
+
+impl LinearBvh {
+    pub fn create(bvh: &Bvh) -> LinearBvh {
+        let mut nodes = vec![];
+        if let Some(root) = &bvh.root {
+            flatten(root, &mut nodes);
+        }
+
+        LinearBvh {
+            nodes,
+            primitives: bvh.primitives.clone(),
+            unbounded: bvh.unbounded.clone(),
+        }
+    }
+}
+
+fn flatten(node: &Node, nodes: &mut Vec<LinearNode>) {
+    match node {
+        Node::Leaf {
+            bounds,
+            first,
+            count,
+        } => nodes.push(LinearNode::create(bounds, *first, *count, 0)),
+        Node::Interior {
+            bounds,
+            axis,
+            children,
+        } => {
+            let index = nodes.len();
+            nodes.push(LinearNode::create(bounds, 0, 0, *axis));
+            flatten(&children[0], nodes);
+            nodes[index].offset = nodes.len() as u32;
+            flatten(&children[1], nodes);
+        }
+    }
+}
+
+impl LinearBvh {
+    /// Offers the primitives the ray may hit between `from` and `limit` to `visit`,
+    /// roughly nearest first. It may lower the limit to skip whatever lies beyond,
+    /// and returns true to stop. Returns whether it was stopped.
+    fn traverse<F: FnMut(usize, &mut f64) -> bool>(
+        &self,
+        ray: &Ray,
+        from: f64,
+        limit: &mut f64,
+        visit: &mut F,
+    ) -> bool {
+        if self.unbounded.iter().any(|&index| visit(index, limit)) {
+            return true;
+        }
+        if self.nodes.is_empty() {
+            return false;
+        }
+
+        let (o, d) = (ray.origin, ray.direction);
+        let origin = [o.x, o.y, o.z];
+        let inverse = [1. / d.x, 1. / d.y, 1. / d.z];
+        let mut stack = Vec::with_capacity(64);
+        let mut current = 0;
+        loop {
+            let node = &self.nodes[current];
+            if node.is_entered(&origin, &inverse, from, *limit) {
+                if node.count > 0 {
+                    let first = node.offset as usize;
+                    if self.primitives[first..first + node.count as usize]
+                        .iter()
+                        .any(|&index| visit(index, limit))
+                    {
+                        return true;
+                    }
+                } else {
+                    // Nearer child first, judged by which way the ray crosses the
+                    // split, and the other one for later
+                    let (first, second) = (current + 1, node.offset as usize);
+                    if inverse[node.axis as usize] < 0. {
+                        stack.push(first);
+                        current = second;
+                    } else {
+                        stack.push(second);
+                        current = first;
+                    }
+                    continue;
+                }
+            }
+
+            match stack.pop() {
+                Some(next) => current = next,
+                None => return false,
+            }
+        }
+    }
+}
+
+impl Accelerator for LinearBvh {
+    fn closest_hit(
+        &self,
+        ray: &Ray,
+        max_t: f64,
+        hit: &mut dyn FnMut(usize, f64) -> Option<f64>,
+    ) -> Option<f64> {
+        let mut closest = max_t;
+        let mut found = false;
+        self.traverse(ray, 0., &mut closest, &mut |index, closest: &mut f64| {
+            if let Some(t) = hit(index, *closest) {
+                *closest = t;
+                found = true;
+            }
+            false
+        });
+        if found {
+            Some(closest)
+        } else {
+            None
+        }
+    }
+
+    fn any_hit(&self, ray: &Ray, max_t: f64, blocks: &mut dyn FnMut(usize) -> bool) -> bool {
+        let mut limit = max_t;
+        self.traverse(ray, 0., &mut limit, &mut |index, _: &mut f64| blocks(index))
+    }
+
+    fn all_hits(&self, ray: &Ray, visit: &mut dyn FnMut(usize)) {
+        let mut limit = f64::INFINITY;
+        self.traverse(
+            ray,
+            f64::NEG_INFINITY,
+            &mut limit,
+            &mut |index, _: &mut f64| {
+                visit(index);
+                false
+            },
+        );
+    }
+}
+
+#[cfg(test)]
+fn sphere_grid(count: usize) -> Vec<Box<dyn Shape>> {
+    (0..count)
+        .map(|n| {
+            let mut sphere = Quadric::create([1., 1., 1., 0., 0., 0., 0., 0., 0., -1.]);
+            let (x, z) = ((n % 20) as f64 * 2.5, (n / 20) as f64 * 2.5);
+            sphere.set_transform(translation(x, (n % 7) as f64 * 0.3, z));
+            Box::new(sphere) as Box<dyn Shape>
+        })
+        .collect()
+}
+
+#[test]
+fn nodes_are_32_bytes() {
+    assert_eq!(32, std::mem::size_of::<LinearNode>());
+}
+
+#[test]
+fn bounds_are_rounded_outwards() {
+    let bounds = Bounds::create(Tuple::point(0.1, -0.1, 1e10), Tuple::point(0.3, 0.7, 3e10));
+    let node = LinearNode::create(&bounds, 0, 1, 0);
+
+    for axis in 0..3 {
+        assert!(node.min[axis] as f64 <= bounds.min[axis]);
+        assert!(node.max[axis] as f64 >= bounds.max[axis]);
+    }
+}
+
+#[test]
+fn flattened_tree_finds_same_hits() {
+    let shapes = sphere_grid(400);
+    let bvh = Bvh::create(&shape_bounds(&shapes), 2);
+    let linear = bvh.flatten();
+
+    for n in 0..300 {
+        let origin = Tuple::point((n % 23) as f64 * 2., 8., -5. + (n % 11) as f64);
+        let target = Tuple::point((n % 17) as f64 * 3., 0., (n % 19) as f64 * 2.7);
+        let ray = Ray::create(origin, (target - origin).normalize());
+
+        let expected = bvh.intersect(&shapes, &ray);
+        let hit = linear.intersect(&shapes, &ray);
+        assert_eq!(expected.is_some(), hit.is_some());
+        if let (Some(expected), Some(hit)) = (expected, hit) {
+            assert!(approx_eq(expected.t, hit.t));
+            assert!(std::ptr::addr_eq(expected.object, hit.object));
+        }
+    }
+}
+
+#[test]
+fn second_child_follows_first_subtree() {
+    let shapes = sphere_grid(50);
+    let bvh = Bvh::create(&shape_bounds(&shapes), 1);
+    let linear = bvh.flatten();
+
+    assert_eq!(2 * 50 - 1, linear.nodes.len());
+    let root = &linear.nodes[0];
+    assert_eq!(0, root.count);
+    // The first subtree has one leaf more than it has interior nodes
+    let first_subtree = &linear.nodes[1..root.offset as usize];
+    let leaves = first_subtree.iter().filter(|node| node.count > 0).count();
+    assert_eq!(first_subtree.len(), 2 * leaves - 1);
+}
+
+#[test]
+fn leaf_size_capped_to_fit() {
+    // Boxes in the same spot can't be told apart, so only the leaf size splits them
+    let bounds = vec![Bounds::create(Tuple::point(0., 0., 0.), Tuple::point(1., 1., 1.)); 70_000];
+    let linear = Bvh::create(&bounds, usize::MAX).flatten();
+
+    assert!(linear
+        .nodes
+        .iter()
+        .all(|node| node.count > 0 || node.offset > 0));
+    assert_eq!(3, linear.nodes.len());
+}
+
+#[test]
+fn empty_and_unbounded_trees() {
+    let ray = Ray::create(Tuple::point(0., 0., -5.), Tuple::vector(0., 0., 1.));
+    assert!(Bvh::create(&[], 4)
+        .flatten()
+        .closest_hit(&ray, f64::INFINITY, &mut |_, _| Some(1.))
+        .is_none());
+
+    let unbounded = Bvh::create(&[Bounds::infinite()], 4).flatten();
+    assert_eq!(
+        Some(1.),
+        unbounded.closest_hit(&ray, f64::INFINITY, &mut |_, _| Some(1.))
+    );
+}
@@ -0,0 +1,5 @@
+pub mod accelerator;
+pub mod bvh;
+pub mod grid;
+pub mod kd_tree;
+pub mod linear_bvh;
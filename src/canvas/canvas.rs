@@ -1,3 +1,4 @@
+use std::fmt;
 use std::iter::FromIterator;
 
 use crate::color::color::Color;
@@ -27,11 +28,11 @@ impl Canvas {
     }
 }
 
-impl ToString for Canvas {
-    fn to_string(&self) -> String {
+impl fmt::Display for Canvas {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let mut header = String::new();
 
-        header.push_str(&*String::from_iter([
+        header.push_str(&String::from_iter([
             "P3",
             "\n",
             &(self.width).to_string(),
@@ -45,19 +46,19 @@ impl ToString for Canvas {
         let mut pixels = 0;
         for x in &self.pixels {
             for y in x {
-                header.push_str(&*y.to_string());
+                header.push_str(&y.to_string());
                 pixels += 1;
 
                 if pixels == 5 {
-                    header.push_str("\n");
+                    header.push('\n');
                     pixels = 0;
                 } else {
-                    header.push_str(" ");
+                    header.push(' ');
                 }
             }
         }
 
-        header
+        write!(f, "{}", header)
     }
 }
 
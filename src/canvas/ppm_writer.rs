@@ -7,7 +7,7 @@ use std::io::{Error, Write};
 use std::path::Path;
 
 #[allow(dead_code, unused_must_use)]
-pub fn write_ppm(canvas: Canvas) -> Result<Box<String>, Error> {
+pub fn write_ppm(canvas: Canvas) -> Result<String, Error> {
     let mut rand_string: String = thread_rng()
         .sample_iter(&Alphanumeric)
         .take(10)
@@ -25,7 +25,7 @@ pub fn write_ppm(canvas: Canvas) -> Result<Box<String>, Error> {
     }
 
     let output = File::create(target_path)?;
-    write!(&output, "{}", canvas.to_string())?;
+    write!(&output, "{}", canvas)?;
 
-    Ok(Box::new(target_path.to_str().unwrap().to_string()))
+    Ok(target_path.to_str().unwrap().to_string())
 }
@@ -1,6 +1,7 @@
+use std::fmt;
 use std::ops;
 
-#[derive(Debug, Clone, Copy)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub struct Color {
     pub red: f64,
     pub green: f64,
@@ -21,16 +22,16 @@ impl Color {
     }
 }
 
-impl ToString for Color {
-    fn to_string(&self) -> String {
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         let mut color_as_string = String::new();
-        color_as_string.push_str(&*String::from(float_to_int(self.red).to_string()));
-        color_as_string.push_str(" ");
-        color_as_string.push_str(&*String::from(float_to_int(self.green).to_string()));
-        color_as_string.push_str(" ");
-        color_as_string.push_str(&*String::from(float_to_int(self.blue).to_string()));
+        color_as_string.push_str(&float_to_int(self.red).to_string());
+        color_as_string.push(' ');
+        color_as_string.push_str(&float_to_int(self.green).to_string());
+        color_as_string.push(' ');
+        color_as_string.push_str(&float_to_int(self.blue).to_string());
 
-        color_as_string
+        write!(f, "{}", color_as_string)
     }
 }
 
@@ -45,7 +46,7 @@ pub fn float_to_int(color: f64) -> u32 {
         return 255;
     }
 
-    return color_as_int as u32;
+    color_as_int as u32
 }
 
 impl ops::Sub<Color> for Color {
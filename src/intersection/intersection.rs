@@ -0,0 +1,160 @@
+use crate::material::material::Material;
+#[cfg(test)]
+use crate::shapes::cone::Cone;
+use crate::shapes::shape::Shape;
+use std::ops;
+
+#[derive(Debug, Clone)]
+pub struct Intersection<'a> {
+    pub t: f64,
+    pub object: &'a dyn Shape,
+    pub u: f64,
+    pub v: f64,
+    /// Which triangle of a mesh was hit, zero for shapes that are a single primitive.
+    pub primitive: usize,
+    /// Time of the ray that found the hit, needed to place moving shapes.
+    pub time: f64,
+    /// Groups the object was reached through, innermost first. Recorded per hit
+    /// rather than on the shape itself so the same shape can sit in several groups.
+    pub parents: Vec<&'a dyn Shape>,
+}
+
+impl<'a> Intersection<'a> {
+    pub fn create(t: f64, object: &'a dyn Shape) -> Intersection<'a> {
+        Intersection::create_with_uv(t, object, 0., 0.)
+    }
+
+    /// Intersection that also records where on the surface it happened, e.g. the
+    /// barycentric coordinates on a triangle.
+    pub fn create_with_uv(t: f64, object: &'a dyn Shape, u: f64, v: f64) -> Intersection<'a> {
+        Intersection {
+            t,
+            object,
+            u,
+            v,
+            primitive: 0,
+            time: 0.,
+            parents: vec![],
+        }
+    }
+
+    /// Material to shade the hit with. An override on any of the parents wins over
+    /// the object's own material, the outermost one first.
+    pub fn material(&self) -> &'a Material {
+        self.parents
+            .iter()
+            .rev()
+            .find_map(|parent| parent.material_override())
+            .unwrap_or_else(|| self.object.material())
+    }
+}
+
+impl PartialEq for Intersection<'_> {
+    fn eq(&self, other: &Self) -> bool {
+        self.t == other.t && std::ptr::addr_eq(self.object, other.object)
+    }
+}
+
+#[derive(Debug)]
+pub struct Intersections<'a> {
+    list: Vec<Intersection<'a>>,
+}
+
+impl<'a> Intersections<'a> {
+    pub fn create(mut list: Vec<Intersection<'a>>) -> Intersections<'a> {
+        list.sort_by(|a, b| a.t.total_cmp(&b.t));
+        Intersections { list }
+    }
+
+    pub fn hit(&self) -> Option<&Intersection<'a>> {
+        self.list.iter().find(|i| i.t >= 0.)
+    }
+
+    pub fn len(&self) -> usize {
+        self.list.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.list.is_empty()
+    }
+
+    pub fn iter(&self) -> std::slice::Iter<'_, Intersection<'a>> {
+        self.list.iter()
+    }
+}
+
+impl<'a> IntoIterator for Intersections<'a> {
+    type Item = Intersection<'a>;
+    type IntoIter = std::vec::IntoIter<Intersection<'a>>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.list.into_iter()
+    }
+}
+
+impl<'a> ops::Index<usize> for Intersections<'a> {
+    type Output = Intersection<'a>;
+    fn index(&self, index: usize) -> &Self::Output {
+        &self.list[index]
+    }
+}
+
+#[test]
+fn intersections_are_sorted() {
+    let shape = Cone::create();
+    let xs = Intersections::create(vec![
+        Intersection::create(2., &shape),
+        Intersection::create(-1., &shape),
+        Intersection::create(1., &shape),
+    ]);
+
+    assert_eq!(3, xs.len());
+    assert!(!xs.is_empty());
+    assert_eq!(-1., xs[0].t);
+    assert_eq!(1., xs[1].t);
+    assert_eq!(2., xs[2].t);
+    assert!(xs.iter().map(|i| i.t).eq([-1., 1., 2.]));
+}
+
+#[test]
+fn hit_when_all_intersections_have_positive_t() {
+    let shape = Cone::create();
+    let i1 = Intersection::create(1., &shape);
+    let i2 = Intersection::create(2., &shape);
+    let xs = Intersections::create(vec![i2.clone(), i1.clone()]);
+
+    assert_eq!(Some(&i1), xs.hit());
+}
+
+#[test]
+fn hit_when_some_intersections_have_negative_t() {
+    let shape = Cone::create();
+    let i1 = Intersection::create(-1., &shape);
+    let i2 = Intersection::create(1., &shape);
+    let xs = Intersections::create(vec![i2.clone(), i1.clone()]);
+
+    assert_eq!(Some(&i2), xs.hit());
+}
+
+#[test]
+fn hit_when_all_intersections_have_negative_t() {
+    let shape = Cone::create();
+    let xs = Intersections::create(vec![
+        Intersection::create(-2., &shape),
+        Intersection::create(-1., &shape),
+    ]);
+
+    assert_eq!(None, xs.hit());
+}
+
+#[test]
+fn hit_is_lowest_nonnegative_intersection() {
+    let shape = Cone::create();
+    let i1 = Intersection::create(5., &shape);
+    let i2 = Intersection::create(7., &shape);
+    let i3 = Intersection::create(-3., &shape);
+    let i4 = Intersection::create(2., &shape);
+    let xs = Intersections::create(vec![i1, i2, i3, i4.clone()]);
+
+    assert_eq!(Some(&i4), xs.hit());
+}
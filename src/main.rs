@@ -1,8 +1,25 @@
+#[cfg(test)]
 #[macro_use]
 extern crate approx;
 
+// Nothing is rendered from main yet, so outside of the tests the modules are unused
+#[cfg_attr(not(test), allow(dead_code))]
+mod acceleration;
+#[cfg_attr(not(test), allow(dead_code))]
 mod canvas;
+#[cfg_attr(not(test), allow(dead_code))]
 mod color;
+#[cfg_attr(not(test), allow(dead_code))]
+mod intersection;
+#[cfg_attr(not(test), allow(dead_code))]
+mod material;
+#[cfg_attr(not(test), allow(dead_code))]
 mod math;
+#[cfg_attr(not(test), allow(dead_code))]
+mod ray;
+#[cfg_attr(not(test), allow(dead_code))]
+mod shapes;
+#[cfg_attr(not(test), allow(dead_code))]
+mod world;
 
 fn main() {}
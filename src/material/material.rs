@@ -0,0 +1,35 @@
+use crate::color::color::Color;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Material {
+    pub color: Color,
+    pub ambient: f64,
+    pub diffuse: f64,
+    pub specular: f64,
+    pub shininess: f64,
+}
+
+impl Material {
+    pub fn create() -> Material {
+        Material {
+            color: Color::create(1., 1., 1.),
+            ambient: 0.1,
+            diffuse: 0.9,
+            specular: 0.9,
+            shininess: 200.,
+        }
+    }
+}
+
+#[test]
+fn default_material() {
+    let material = Material::create();
+
+    assert_eq!(1., material.color.red);
+    assert_eq!(1., material.color.green);
+    assert_eq!(1., material.color.blue);
+    assert_eq!(0.1, material.ambient);
+    assert_eq!(0.9, material.diffuse);
+    assert_eq!(0.9, material.specular);
+    assert_eq!(200., material.shininess);
+}
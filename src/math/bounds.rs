@@ -0,0 +1,276 @@
+use crate::math::matrix::Matrix;
+use crate::math::tuple::Tuple;
+use crate::ray::ray::Ray;
+use ray_tracer::EPSILON;
+
+/// Axis aligned bounding box.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Bounds {
+    pub min: Tuple,
+    pub max: Tuple,
+}
+
+impl Bounds {
+    pub fn create(min: Tuple, max: Tuple) -> Bounds {
+        Bounds { min, max }
+    }
+
+    /// Box containing nothing, which grows to fit whatever is added to it.
+    pub fn empty() -> Bounds {
+        Bounds {
+            min: Tuple::point(f64::INFINITY, f64::INFINITY, f64::INFINITY),
+            max: Tuple::point(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY),
+        }
+    }
+
+    /// Box around everything, for shapes that reach out to infinity.
+    pub fn infinite() -> Bounds {
+        Bounds {
+            min: Tuple::point(f64::NEG_INFINITY, f64::NEG_INFINITY, f64::NEG_INFINITY),
+            max: Tuple::point(f64::INFINITY, f64::INFINITY, f64::INFINITY),
+        }
+    }
+
+    pub fn from_points<'a>(points: impl IntoIterator<Item = &'a Tuple>) -> Bounds {
+        points
+            .into_iter()
+            .fold(Bounds::empty(), |bounds, point| bounds.add_point(*point))
+    }
+
+    pub fn add_point(&self, point: Tuple) -> Bounds {
+        Bounds {
+            min: Tuple::point(
+                self.min.x.min(point.x),
+                self.min.y.min(point.y),
+                self.min.z.min(point.z),
+            ),
+            max: Tuple::point(
+                self.max.x.max(point.x),
+                self.max.y.max(point.y),
+                self.max.z.max(point.z),
+            ),
+        }
+    }
+
+    pub fn merge(&self, other: &Bounds) -> Bounds {
+        if other.is_empty() {
+            return *self;
+        }
+        self.add_point(other.min).add_point(other.max)
+    }
+
+    /// Box around the space both boxes share, empty if they are apart.
+    pub fn overlap(&self, other: &Bounds) -> Bounds {
+        Bounds {
+            min: Tuple::point(
+                self.min.x.max(other.min.x),
+                self.min.y.max(other.min.y),
+                self.min.z.max(other.min.z),
+            ),
+            max: Tuple::point(
+                self.max.x.min(other.max.x),
+                self.max.y.min(other.max.y),
+                self.max.z.min(other.max.z),
+            ),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.min.x > self.max.x || self.min.y > self.max.y || self.min.z > self.max.z
+    }
+
+    pub fn is_finite(&self) -> bool {
+        (0..3).all(|axis| self.min[axis].is_finite() && self.max[axis].is_finite())
+    }
+
+    pub fn centroid(&self) -> Tuple {
+        self.min + (self.max - self.min) * 0.5
+    }
+
+    pub fn surface_area(&self) -> f64 {
+        if self.is_empty() {
+            return 0.;
+        }
+        let size = self.max - self.min;
+        2. * (size.x * size.y + size.x * size.z + size.y * size.z)
+    }
+
+    /// Box around all eight corners once transformed, which fits the transformed
+    /// contents conservatively. Boxes reaching out to infinity in any direction
+    /// may point anywhere afterwards, so they stay infinite.
+    pub fn transform(&self, matrix: Matrix) -> Bounds {
+        if self.is_empty() {
+            return *self;
+        }
+        if !self.is_finite() {
+            return Bounds::infinite();
+        }
+
+        let (min, max) = (self.min, self.max);
+        let corners = [
+            Tuple::point(min.x, min.y, min.z),
+            Tuple::point(min.x, min.y, max.z),
+            Tuple::point(min.x, max.y, min.z),
+            Tuple::point(min.x, max.y, max.z),
+            Tuple::point(max.x, min.y, min.z),
+            Tuple::point(max.x, min.y, max.z),
+            Tuple::point(max.x, max.y, min.z),
+            Tuple::point(max.x, max.y, max.z),
+        ];
+        corners.iter().fold(Bounds::empty(), |bounds, corner| {
+            bounds.add_point(matrix * *corner)
+        })
+    }
+
+    pub fn contains(&self, point: Tuple) -> bool {
+        (self.min.x..=self.max.x).contains(&point.x)
+            && (self.min.y..=self.max.y).contains(&point.y)
+            && (self.min.z..=self.max.z).contains(&point.z)
+    }
+
+    /// Range of `t` in which the ray is inside the box, if it enters it at all. The
+    /// range can start or even end behind the ray origin.
+    pub fn intersect(&self, ray: &Ray) -> Option<(f64, f64)> {
+        let axes = [
+            (ray.origin.x, ray.direction.x, self.min.x, self.max.x),
+            (ray.origin.y, ray.direction.y, self.min.y, self.max.y),
+            (ray.origin.z, ray.direction.z, self.min.z, self.max.z),
+        ];
+
+        let mut t_min = f64::NEG_INFINITY;
+        let mut t_max = f64::INFINITY;
+        for (origin, direction, low, high) in axes.iter() {
+            if direction.abs() < EPSILON {
+                if origin < low || origin > high {
+                    return None;
+                }
+                continue;
+            }
+
+            let t0 = (low - origin) / direction;
+            let t1 = (high - origin) / direction;
+            t_min = t_min.max(t0.min(t1));
+            t_max = t_max.min(t0.max(t1));
+        }
+
+        if t_min > t_max {
+            return None;
+        }
+        Some((t_min, t_max))
+    }
+}
+
+#[cfg(test)]
+use crate::math::transformation::rotation_y;
+
+#[test]
+fn bounds_grow_to_fit_points() {
+    let bounds = Bounds::from_points(&[Tuple::point(-5., 2., 0.), Tuple::point(7., 0., -3.)]);
+
+    assert_eq!(Tuple::point(-5., 0., -3.), bounds.min);
+    assert_eq!(Tuple::point(7., 2., 0.), bounds.max);
+    assert!(Bounds::empty().is_empty());
+    assert!(!bounds.is_empty());
+}
+
+#[test]
+fn merging_bounds() {
+    let a = Bounds::create(Tuple::point(-5., -2., 0.), Tuple::point(7., 4., 4.));
+    let b = Bounds::create(Tuple::point(8., -7., -2.), Tuple::point(14., 2., 8.));
+
+    assert_eq!(
+        Bounds::create(Tuple::point(-5., -7., -2.), Tuple::point(14., 4., 8.)),
+        a.merge(&b)
+    );
+}
+
+#[test]
+fn overlapping_bounds() {
+    let a = Bounds::create(Tuple::point(-5., -2., 0.), Tuple::point(7., 4., 4.));
+    let b = Bounds::create(Tuple::point(3., -7., -2.), Tuple::point(14., 2., 8.));
+
+    assert_eq!(
+        Bounds::create(Tuple::point(3., -2., 0.), Tuple::point(7., 2., 4.)),
+        a.overlap(&b)
+    );
+    assert_eq!(a, Bounds::infinite().overlap(&a));
+
+    let apart = Bounds::create(Tuple::point(8., 0., 0.), Tuple::point(9., 1., 1.));
+    assert!(a.overlap(&apart).is_empty());
+}
+
+#[test]
+fn transforming_bounds() {
+    let bounds = Bounds::create(Tuple::point(-1., -1., -1.), Tuple::point(1., 1., 1.));
+    let rotated = bounds.transform(rotation_y(std::f64::consts::PI / 4.));
+    let half_diagonal = f64::sqrt(2.);
+
+    assert_eq!(
+        Tuple::point(-half_diagonal, -1., -half_diagonal),
+        rotated.min
+    );
+    assert_eq!(Tuple::point(half_diagonal, 1., half_diagonal), rotated.max);
+}
+
+#[test]
+fn ray_against_bounds() {
+    let bounds = Bounds::create(Tuple::point(5., -2., 0.), Tuple::point(11., 4., 7.));
+    let examples = [
+        (
+            Tuple::point(15., 1., 2.),
+            Tuple::vector(-1., 0., 0.),
+            Some((4., 10.)),
+        ),
+        (
+            Tuple::point(-5., -1., 4.),
+            Tuple::vector(1., 0., 0.),
+            Some((10., 16.)),
+        ),
+        (
+            Tuple::point(7., 0., 5.),
+            Tuple::vector(0., 0., 1.),
+            Some((-5., 2.)),
+        ),
+        (Tuple::point(9., -1., -8.), Tuple::vector(2., 4., 6.), None),
+        (Tuple::point(15., 5., 2.), Tuple::vector(-1., 0., 0.), None),
+    ];
+
+    for (origin, direction, expected) in examples.iter() {
+        let ray = Ray::create(*origin, *direction);
+        assert_eq!(*expected, bounds.intersect(&ray));
+    }
+}
+
+#[test]
+fn bounds_contain_points() {
+    let bounds = Bounds::create(Tuple::point(-1., 0., 0.), Tuple::point(1., 2., 3.));
+
+    assert!(bounds.contains(Tuple::point(0., 1., 1.)));
+    assert!(bounds.contains(Tuple::point(1., 2., 3.)));
+    assert!(!bounds.contains(Tuple::point(0., 2.5, 1.)));
+    assert!(!Bounds::empty().contains(Tuple::point(0., 0., 0.)));
+}
+
+#[test]
+fn measuring_bounds() {
+    let bounds = Bounds::create(Tuple::point(-1., 0., 0.), Tuple::point(1., 2., 3.));
+
+    assert_eq!(Tuple::point(0., 1., 1.5), bounds.centroid());
+    assert_eq!(2. * (2. * 2. + 2. * 3. + 2. * 3.), bounds.surface_area());
+    assert_eq!(0., Bounds::empty().surface_area());
+    assert!(bounds.is_finite());
+    assert!(!Bounds::infinite().is_finite());
+}
+
+#[test]
+fn transforming_infinite_bounds() {
+    let open = Bounds::create(
+        Tuple::point(-1., f64::NEG_INFINITY, -1.),
+        Tuple::point(1., f64::INFINITY, 1.),
+    );
+
+    let rotated = open.transform(rotation_y(std::f64::consts::PI / 4.));
+
+    assert_eq!(f64::NEG_INFINITY, rotated.min.x);
+    assert_eq!(f64::INFINITY, rotated.max.z);
+}
@@ -151,6 +151,66 @@ impl Matrix {
         }
         matrix
     }
+
+    pub fn determinant(&self) -> f64 {
+        if self.size == 2 {
+            return self[0][0] * self[1][1] - self[0][1] * self[1][0];
+        }
+
+        (0..self.size)
+            .map(|col| self[0][col] * self.cofactor(0, col))
+            .sum()
+    }
+
+    pub fn submatrix(&self, row: usize, col: usize) -> Self {
+        let size = self.size - 1;
+        let mut matrix = Matrix {
+            inner: [Row {
+                inner: [0., 0., 0., 0.],
+                size,
+            }; 4],
+            size,
+        };
+
+        for (target_row, source_row) in (0..self.size).filter(|&r| r != row).enumerate() {
+            for (target_col, source_col) in (0..self.size).filter(|&c| c != col).enumerate() {
+                matrix.set(target_row, target_col, self[source_row][source_col]);
+            }
+        }
+        matrix
+    }
+
+    pub fn minor(&self, row: usize, col: usize) -> f64 {
+        self.submatrix(row, col).determinant()
+    }
+
+    pub fn cofactor(&self, row: usize, col: usize) -> f64 {
+        let minor = self.minor(row, col);
+        if (row + col) % 2 == 1 {
+            return -minor;
+        }
+        minor
+    }
+
+    pub fn is_invertible(&self) -> bool {
+        self.determinant() != 0.
+    }
+
+    pub fn inverse(&self) -> Self {
+        let determinant = self.determinant();
+        if determinant == 0. {
+            panic!("Matrix is not invertible!")
+        }
+
+        let mut matrix = self.empty();
+        let size = self.size;
+        for row in 0..size {
+            for col in 0..size {
+                matrix.set(col, row, self.cofactor(row, col) / determinant);
+            }
+        }
+        matrix
+    }
 }
 
 impl ops::Index<usize> for Matrix {
@@ -362,3 +422,106 @@ fn transpose_a_matrix() {
 fn transpose_identity_matrix() {
     assert_eq!(IDENTITY_MATRIX, IDENTITY_MATRIX.transpose());
 }
+
+#[test]
+fn determinant_of_2x2_matrix() {
+    let m = Matrix::create2([1., 5.], [-3., 2.]);
+
+    assert_eq!(17., m.determinant());
+}
+
+#[test]
+fn submatrix_of_3x3_and_4x4_matrix() {
+    let m = Matrix::create3([1., 5., 0.], [-3., 2., 7.], [0., 6., -3.]);
+    assert_eq!(Matrix::create2([-3., 2.], [0., 6.]), m.submatrix(0, 2));
+
+    let m = Matrix::create(
+        [-6., 1., 1., 6.],
+        [-8., 5., 8., 6.],
+        [-1., 0., 8., 2.],
+        [-7., 1., -1., 1.],
+    );
+    let expected = Matrix::create3([-6., 1., 6.], [-8., 8., 6.], [-7., -1., 1.]);
+    assert_eq!(expected, m.submatrix(2, 1));
+}
+
+#[test]
+fn minor_and_cofactor_of_3x3_matrix() {
+    let m = Matrix::create3([3., 5., 0.], [2., -1., -7.], [6., -1., 5.]);
+
+    assert_eq!(-12., m.minor(0, 0));
+    assert_eq!(-12., m.cofactor(0, 0));
+    assert_eq!(25., m.minor(1, 0));
+    assert_eq!(-25., m.cofactor(1, 0));
+}
+
+#[test]
+fn determinant_of_4x4_matrix() {
+    let m = Matrix::create(
+        [-2., -8., 3., 5.],
+        [-3., 1., 7., 3.],
+        [1., 2., -9., 6.],
+        [-6., 7., 7., -9.],
+    );
+
+    assert_eq!(690., m.cofactor(0, 0));
+    assert_eq!(447., m.cofactor(0, 1));
+    assert_eq!(210., m.cofactor(0, 2));
+    assert_eq!(51., m.cofactor(0, 3));
+    assert_eq!(-4071., m.determinant());
+}
+
+#[test]
+fn invertibility_of_matrix() {
+    let a = Matrix::create(
+        [6., 4., 4., 4.],
+        [5., 5., 7., 6.],
+        [4., -9., 3., -7.],
+        [9., 1., 7., -6.],
+    );
+    assert!(a.is_invertible());
+
+    let b = Matrix::create(
+        [-4., 2., -2., -3.],
+        [9., 6., 2., 6.],
+        [0., -5., 1., -5.],
+        [0., 0., 0., 0.],
+    );
+    assert!(!b.is_invertible());
+}
+
+#[test]
+fn inverse_of_matrix() {
+    let a = Matrix::create(
+        [8., -5., 9., 2.],
+        [7., 5., 6., 1.],
+        [-6., 0., 9., 6.],
+        [-3., 0., -9., -4.],
+    );
+    let expected = Matrix::create(
+        [-0.15385, -0.15385, -0.28205, -0.53846],
+        [-0.07692, 0.12308, 0.02564, 0.03077],
+        [0.35897, 0.35897, 0.43590, 0.92308],
+        [-0.69231, -0.69231, -0.76923, -1.92308],
+    );
+
+    assert_eq!(expected, a.inverse());
+}
+
+#[test]
+fn multiply_product_by_its_inverse() {
+    let a = Matrix::create(
+        [3., -9., 7., 3.],
+        [3., -8., 2., -9.],
+        [-4., 4., 4., 1.],
+        [-6., 5., -1., 1.],
+    );
+    let b = Matrix::create(
+        [8., 2., 2., 2.],
+        [3., -1., 7., 0.],
+        [7., 0., 5., 4.],
+        [6., -2., 0., 5.],
+    );
+
+    assert_eq!(a, a * b * b.inverse());
+}
@@ -1,2 +1,6 @@
+pub mod bounds;
 pub mod matrix;
+pub mod polynomial;
+pub mod quaternion;
+pub mod transformation;
 pub mod tuple;
@@ -0,0 +1,243 @@
+#[cfg(test)]
+use ray_tracer::approx_eq;
+use ray_tracer::EPSILON;
+
+const NEWTON_ITERATIONS: usize = 8;
+
+/// Real roots of `a·x² + b·x + c`, sorted ascending. Falls back to the linear case
+/// when `a` is zero.
+pub fn solve_quadratic(a: f64, b: f64, c: f64) -> Vec<f64> {
+    if a == 0. {
+        if b == 0. {
+            return vec![];
+        }
+        return vec![-c / b];
+    }
+
+    let mut discriminant = b * b - 4. * a * c;
+    if discriminant < 0. {
+        // Roots that should touch can come out slightly complex from rounding alone.
+        if discriminant < -EPSILON * (b * b).max((4. * a * c).abs()).max(1.) {
+            return vec![];
+        }
+        discriminant = 0.;
+    }
+
+    // Avoids the cancellation of `-b ± sqrt(d)` when both terms are close.
+    let q = -0.5 * (b + b.signum() * discriminant.sqrt());
+    let mut roots = if q == 0. {
+        vec![0., 0.]
+    } else {
+        vec![q / a, c / q]
+    };
+    roots.sort_by(|x, y| x.total_cmp(y));
+    roots
+}
+
+/// Real roots of `a·x³ + b·x² + c·x + d`, sorted ascending.
+pub fn solve_cubic(a: f64, b: f64, c: f64, d: f64) -> Vec<f64> {
+    if a == 0. {
+        return solve_quadratic(b, c, d);
+    }
+
+    let (b, c, d) = (b / a, c / a, d / a);
+    let q = (b * b - 3. * c) / 9.;
+    let r = (2. * b * b * b - 9. * b * c + 27. * d) / 54.;
+    let offset = b / 3.;
+
+    let delta = r * r - q * q * q;
+
+    let mut roots = if delta < 0. {
+        // Rounding can push the ratio just past ±1, where acos has no value
+        let theta = (r / (q * q * q).sqrt()).clamp(-1., 1.).acos();
+        let scale = -2. * q.sqrt();
+        vec![
+            scale * (theta / 3.).cos() - offset,
+            scale * ((theta + 2. * std::f64::consts::PI) / 3.).cos() - offset,
+            scale * ((theta - 2. * std::f64::consts::PI) / 3.).cos() - offset,
+        ]
+    } else {
+        let a = -r.signum() * (r.abs() + delta.sqrt()).cbrt();
+        let b = if a == 0. { 0. } else { q / a };
+        if delta <= EPSILON * EPSILON * (r * r) {
+            // On the boundary the other two roots coincide.
+            let double = -(a + b) / 2. - offset;
+            vec![a + b - offset, double, double]
+        } else {
+            vec![a + b - offset]
+        }
+    };
+
+    let coefficients = [1., b, c, d];
+    roots
+        .iter_mut()
+        .for_each(|root| *root = polish(&coefficients, *root));
+    roots.sort_by(|x, y| x.total_cmp(y));
+    roots
+}
+
+/// Real roots of `a·x⁴ + b·x³ + c·x² + d·x + e`, sorted ascending.
+///
+/// Uses Ferrari's method on the depressed quartic and refines every root with a few
+/// Newton steps against the original polynomial, which recovers the precision
+/// Ferrari loses when the roots are far apart or nearly repeated.
+pub fn solve_quartic(a: f64, b: f64, c: f64, d: f64, e: f64) -> Vec<f64> {
+    if a == 0. {
+        return solve_cubic(b, c, d, e);
+    }
+
+    let (b, c, d, e) = (b / a, c / a, d / a, e / a);
+
+    // Substituting x = y - b/4 gives y⁴ + p·y² + q·y + r.
+    let offset = b / 4.;
+    let p = c - 6. * offset * offset;
+    let q = d - 2. * c * offset + 8. * offset * offset * offset;
+    let r = e - d * offset + c * offset * offset - 3. * offset * offset * offset * offset;
+
+    let mut roots = vec![];
+    if q.abs() < EPSILON * EPSILON {
+        // Biquadratic, solve for y² directly.
+        for square in solve_quadratic(1., p, r) {
+            if square >= 0. {
+                roots.push(square.sqrt());
+                roots.push(-square.sqrt());
+            } else if square > -EPSILON {
+                // A double root at zero, counted twice like any other
+                roots.push(0.);
+                roots.push(0.);
+            }
+        }
+    } else {
+        // Resolvent cubic 8m³ + 8p·m² + (2p² - 8r)·m - q² always has a positive root.
+        let m = solve_cubic(8., 8. * p, 2. * p * p - 8. * r, -q * q)
+            .into_iter()
+            .fold(f64::NEG_INFINITY, f64::max);
+        if m <= 0. {
+            return vec![];
+        }
+
+        let s = (2. * m).sqrt();
+        roots.extend(solve_quadratic(1., -s, p / 2. + m + q / (2. * s)));
+        roots.extend(solve_quadratic(1., s, p / 2. + m - q / (2. * s)));
+    }
+
+    let coefficients = [1., b, c, d, e];
+    let mut roots: Vec<f64> = roots
+        .into_iter()
+        .map(|root| polish(&coefficients, root - offset))
+        .collect();
+    roots.sort_by(|x, y| x.total_cmp(y));
+    roots
+}
+
+/// Evaluates a polynomial, coefficients ordered from the highest degree down,
+/// returning its value and derivative.
+fn evaluate(coefficients: &[f64], x: f64) -> (f64, f64) {
+    coefficients
+        .iter()
+        .fold((0., 0.), |(value, derivative), coefficient| {
+            (value * x + coefficient, derivative * x + value)
+        })
+}
+
+fn polish(coefficients: &[f64], mut root: f64) -> f64 {
+    for _ in 0..NEWTON_ITERATIONS {
+        let (value, derivative) = evaluate(coefficients, root);
+        if derivative == 0. {
+            break;
+        }
+
+        let step = value / derivative;
+        let polished = root - step;
+        // Stop once Newton starts diverging, which happens next to repeated roots.
+        if evaluate(coefficients, polished).0.abs() > value.abs() {
+            break;
+        }
+        root = polished;
+        if step.abs() <= f64::EPSILON * root.abs() {
+            break;
+        }
+    }
+    root
+}
+
+#[cfg(test)]
+fn assert_roots(expected: &[f64], actual: Vec<f64>) {
+    assert_eq!(expected.len(), actual.len(), "{:?}", actual);
+    for (expected, actual) in expected.iter().zip(actual.iter()) {
+        assert!(approx_eq(*expected, *actual), "{:?}", actual);
+    }
+}
+
+#[test]
+fn quadratic_roots() {
+    assert_roots(&[-3., 2.], solve_quadratic(1., 1., -6.));
+    assert_roots(&[1., 1.], solve_quadratic(1., -2., 1.));
+    assert_roots(&[], solve_quadratic(1., 0., 1.));
+    assert_roots(&[-2.], solve_quadratic(0., 2., 4.));
+}
+
+#[test]
+fn quadratic_roots_without_cancellation() {
+    let roots = solve_quadratic(1., -1e8, 1.);
+
+    assert!((roots[0] - 1e-8).abs() < 1e-20);
+    assert!(approx_eq(1e8, roots[1]));
+}
+
+#[test]
+fn cubic_roots() {
+    assert_roots(&[1., 2., 3.], solve_cubic(1., -6., 11., -6.));
+    assert_roots(&[2.], solve_cubic(1., -2., 1., -2.));
+    assert_roots(&[-2., 1., 1.], solve_cubic(2., 0., -6., 4.));
+}
+
+#[test]
+fn quartic_with_four_distinct_roots() {
+    assert_roots(&[1., 2., 3., 4.], solve_quartic(1., -10., 35., -50., 24.));
+    assert_roots(
+        &[-4., -0.5, 0.25, 3.],
+        solve_quartic(8., 10., -95., -25., 12.),
+    );
+}
+
+#[test]
+fn quartic_with_repeated_roots() {
+    assert_roots(&[-2., -2., 1., 1.], solve_quartic(1., 2., -3., -4., 4.));
+}
+
+#[test]
+fn biquadratic_quartic() {
+    assert_roots(&[-2., -1., 1., 2.], solve_quartic(1., 0., -5., 0., 4.));
+}
+
+#[test]
+fn biquadratic_with_double_root_at_zero() {
+    assert_roots(&[-2., 0., 0., 2.], solve_quartic(1., 0., -4., 0., 0.));
+    // y² comes out just below zero, which still touches the axis twice
+    assert_roots(&[0., 0.], solve_quartic(1., 0., 1., 0., 1e-12));
+}
+
+#[test]
+fn quartic_without_real_roots() {
+    assert_roots(&[], solve_quartic(1., 0., 0., 0., 1.));
+    assert_roots(&[], solve_quartic(1., -4., 8., -8., 5.));
+}
+
+#[test]
+fn quartic_with_two_real_roots() {
+    // (x² + 1)(x - 1)(x - 3)
+    assert_roots(&[1., 3.], solve_quartic(1., -4., 4., -4., 3.));
+}
+
+#[test]
+fn quartic_with_widely_spread_roots() {
+    // (x - 0.001)(x - 1)(x - 100)(x - 1000)
+    let (r1, r2, r3, r4) = (0.001, 1., 100., 1000.);
+    let b = -(r1 + r2 + r3 + r4);
+    let c = r1 * r2 + r1 * r3 + r1 * r4 + r2 * r3 + r2 * r4 + r3 * r4;
+    let d = -(r1 * r2 * r3 + r1 * r2 * r4 + r1 * r3 * r4 + r2 * r3 * r4);
+    let e = r1 * r2 * r3 * r4;
+
+    assert_roots(&[r1, r2, r3, r4], solve_quartic(1., b, c, d, e));
+}
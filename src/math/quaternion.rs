@@ -0,0 +1,160 @@
+use crate::math::matrix::{Matrix, IDENTITY_MATRIX};
+#[cfg(test)]
+use crate::math::transformation::{rotation_x, rotation_y};
+#[cfg(test)]
+use crate::math::tuple::Tuple;
+#[cfg(test)]
+use std::f64::consts::{FRAC_PI_2, FRAC_PI_4};
+
+/// Unit quaternion describing a rotation, used to interpolate between orientations.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Quaternion {
+    pub w: f64,
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+}
+
+impl Quaternion {
+    pub fn create(w: f64, x: f64, y: f64, z: f64) -> Quaternion {
+        Quaternion { w, x, y, z }
+    }
+
+    /// Rotation held in the upper 3x3 part of a matrix, which must be orthonormal.
+    pub fn from_matrix(m: &Matrix) -> Quaternion {
+        let trace = m[0][0] + m[1][1] + m[2][2];
+        let q = if trace > 0. {
+            let s = (trace + 1.).sqrt() * 2.;
+            Quaternion::create(
+                s / 4.,
+                (m[2][1] - m[1][2]) / s,
+                (m[0][2] - m[2][0]) / s,
+                (m[1][0] - m[0][1]) / s,
+            )
+        } else if m[0][0] > m[1][1] && m[0][0] > m[2][2] {
+            let s = (1. + m[0][0] - m[1][1] - m[2][2]).sqrt() * 2.;
+            Quaternion::create(
+                (m[2][1] - m[1][2]) / s,
+                s / 4.,
+                (m[0][1] + m[1][0]) / s,
+                (m[0][2] + m[2][0]) / s,
+            )
+        } else if m[1][1] > m[2][2] {
+            let s = (1. + m[1][1] - m[0][0] - m[2][2]).sqrt() * 2.;
+            Quaternion::create(
+                (m[0][2] - m[2][0]) / s,
+                (m[0][1] + m[1][0]) / s,
+                s / 4.,
+                (m[1][2] + m[2][1]) / s,
+            )
+        } else {
+            let s = (1. + m[2][2] - m[0][0] - m[1][1]).sqrt() * 2.;
+            Quaternion::create(
+                (m[1][0] - m[0][1]) / s,
+                (m[0][2] + m[2][0]) / s,
+                (m[1][2] + m[2][1]) / s,
+                s / 4.,
+            )
+        };
+        q.normalize()
+    }
+
+    pub fn to_matrix(self) -> Matrix {
+        let Quaternion { w, x, y, z } = self;
+        let mut m = IDENTITY_MATRIX;
+        m.set(0, 0, 1. - 2. * (y * y + z * z));
+        m.set(0, 1, 2. * (x * y - w * z));
+        m.set(0, 2, 2. * (x * z + w * y));
+        m.set(1, 0, 2. * (x * y + w * z));
+        m.set(1, 1, 1. - 2. * (x * x + z * z));
+        m.set(1, 2, 2. * (y * z - w * x));
+        m.set(2, 0, 2. * (x * z - w * y));
+        m.set(2, 1, 2. * (y * z + w * x));
+        m.set(2, 2, 1. - 2. * (x * x + y * y));
+        m
+    }
+
+    /// Opposite rotation, which for a unit quaternion is also its inverse.
+    pub fn conjugate(&self) -> Quaternion {
+        Quaternion::create(self.w, -self.x, -self.y, -self.z)
+    }
+
+    pub fn dot(&self, that: Quaternion) -> f64 {
+        self.w * that.w + self.x * that.x + self.y * that.y + self.z * that.z
+    }
+
+    pub fn normalize(&self) -> Quaternion {
+        let length = self.dot(*self).sqrt();
+        Quaternion::create(
+            self.w / length,
+            self.x / length,
+            self.y / length,
+            self.z / length,
+        )
+    }
+
+    /// Spherical interpolation, turning the short way round at constant speed.
+    pub fn slerp(&self, that: Quaternion, amount: f64) -> Quaternion {
+        let mut cos = self.dot(that);
+        let mut that = that;
+        if cos < 0. {
+            cos = -cos;
+            that = Quaternion::create(-that.w, -that.x, -that.y, -that.z);
+        }
+
+        let (from, to) = if cos > 0.9995 {
+            // Nearly parallel, where the sine below vanishes
+            (1. - amount, amount)
+        } else {
+            let angle = cos.acos();
+            (
+                ((1. - amount) * angle).sin() / angle.sin(),
+                (amount * angle).sin() / angle.sin(),
+            )
+        };
+
+        Quaternion::create(
+            self.w * from + that.w * to,
+            self.x * from + that.x * to,
+            self.y * from + that.y * to,
+            self.z * from + that.z * to,
+        )
+        .normalize()
+    }
+}
+
+#[test]
+fn matrix_round_trip() {
+    let rotation = rotation_x(0.3) * rotation_y(-1.2);
+    let quaternion = Quaternion::from_matrix(&rotation);
+
+    assert!((quaternion.dot(quaternion) - 1.).abs() < 1e-9);
+    assert_eq!(rotation, quaternion.to_matrix());
+
+    // Half turns go through the other branches
+    let half_turn = rotation_y(std::f64::consts::PI);
+    assert_eq!(half_turn, Quaternion::from_matrix(&half_turn).to_matrix());
+}
+
+#[test]
+fn conjugate_turns_back() {
+    let rotation = Quaternion::from_matrix(&(rotation_x(0.7) * rotation_y(0.4)));
+
+    assert_eq!(
+        IDENTITY_MATRIX,
+        rotation.to_matrix() * rotation.conjugate().to_matrix()
+    );
+}
+
+#[test]
+fn slerp_halfway() {
+    let start = Quaternion::from_matrix(&IDENTITY_MATRIX);
+    let end = Quaternion::from_matrix(&rotation_y(FRAC_PI_2));
+    let middle = start.slerp(end, 0.5).to_matrix();
+
+    assert_eq!(rotation_y(FRAC_PI_4), middle);
+    assert_eq!(
+        Tuple::point(f64::sqrt(2.) / 2., 0., -f64::sqrt(2.) / 2.),
+        middle * Tuple::point(1., 0., 0.)
+    );
+}
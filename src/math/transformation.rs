@@ -0,0 +1,140 @@
+use crate::math::matrix::Matrix;
+#[cfg(test)]
+use crate::math::tuple::Tuple;
+#[cfg(test)]
+use std::f64::consts::PI;
+
+pub fn translation(x: f64, y: f64, z: f64) -> Matrix {
+    Matrix::create(
+        [1., 0., 0., x],
+        [0., 1., 0., y],
+        [0., 0., 1., z],
+        [0., 0., 0., 1.],
+    )
+}
+
+pub fn scaling(x: f64, y: f64, z: f64) -> Matrix {
+    Matrix::create(
+        [x, 0., 0., 0.],
+        [0., y, 0., 0.],
+        [0., 0., z, 0.],
+        [0., 0., 0., 1.],
+    )
+}
+
+pub fn rotation_x(radians: f64) -> Matrix {
+    Matrix::create(
+        [1., 0., 0., 0.],
+        [0., radians.cos(), -radians.sin(), 0.],
+        [0., radians.sin(), radians.cos(), 0.],
+        [0., 0., 0., 1.],
+    )
+}
+
+pub fn rotation_y(radians: f64) -> Matrix {
+    Matrix::create(
+        [radians.cos(), 0., radians.sin(), 0.],
+        [0., 1., 0., 0.],
+        [-radians.sin(), 0., radians.cos(), 0.],
+        [0., 0., 0., 1.],
+    )
+}
+
+pub fn rotation_z(radians: f64) -> Matrix {
+    Matrix::create(
+        [radians.cos(), -radians.sin(), 0., 0.],
+        [radians.sin(), radians.cos(), 0., 0.],
+        [0., 0., 1., 0.],
+        [0., 0., 0., 1.],
+    )
+}
+
+pub fn shearing(xy: f64, xz: f64, yx: f64, yz: f64, zx: f64, zy: f64) -> Matrix {
+    Matrix::create(
+        [1., xy, xz, 0.],
+        [yx, 1., yz, 0.],
+        [zx, zy, 1., 0.],
+        [0., 0., 0., 1.],
+    )
+}
+
+#[test]
+fn translate_point_and_vector() {
+    let transform = translation(5., -3., 2.);
+
+    assert_eq!(
+        Tuple::point(2., 1., 7.),
+        transform * Tuple::point(-3., 4., 5.)
+    );
+    assert_eq!(
+        Tuple::point(-8., 7., 3.),
+        transform.inverse() * Tuple::point(-3., 4., 5.)
+    );
+
+    let v = Tuple::vector(-3., 4., 5.);
+    assert_eq!(v, transform * v);
+}
+
+#[test]
+fn scale_point_and_vector() {
+    let transform = scaling(2., 3., 4.);
+
+    assert_eq!(
+        Tuple::point(-8., 18., 32.),
+        transform * Tuple::point(-4., 6., 8.)
+    );
+    assert_eq!(
+        Tuple::vector(-8., 18., 32.),
+        transform * Tuple::vector(-4., 6., 8.)
+    );
+    assert_eq!(
+        Tuple::vector(-2., 2., 2.),
+        transform.inverse() * Tuple::vector(-4., 6., 8.)
+    );
+}
+
+#[test]
+fn rotate_point_around_axes() {
+    let half_quarter = PI / 4.;
+    let full_quarter = PI / 2.;
+    let sqrt2_2 = f64::sqrt(2.) / 2.;
+
+    let p = Tuple::point(0., 1., 0.);
+    assert_eq!(
+        Tuple::point(0., sqrt2_2, sqrt2_2),
+        rotation_x(half_quarter) * p
+    );
+    assert_eq!(Tuple::point(0., 0., 1.), rotation_x(full_quarter) * p);
+
+    let p = Tuple::point(0., 0., 1.);
+    assert_eq!(
+        Tuple::point(sqrt2_2, 0., sqrt2_2),
+        rotation_y(half_quarter) * p
+    );
+    assert_eq!(Tuple::point(1., 0., 0.), rotation_y(full_quarter) * p);
+
+    let p = Tuple::point(0., 1., 0.);
+    assert_eq!(
+        Tuple::point(-sqrt2_2, sqrt2_2, 0.),
+        rotation_z(half_quarter) * p
+    );
+    assert_eq!(Tuple::point(-1., 0., 0.), rotation_z(full_quarter) * p);
+}
+
+#[test]
+fn shear_point() {
+    let p = Tuple::point(2., 3., 4.);
+
+    assert_eq!(
+        Tuple::point(5., 3., 4.),
+        shearing(1., 0., 0., 0., 0., 0.) * p
+    );
+    assert_eq!(
+        Tuple::point(2., 5., 4.),
+        shearing(0., 0., 1., 0., 0., 0.) * p
+    );
+    assert_eq!(
+        Tuple::point(2., 3., 7.),
+        shearing(0., 0., 0., 0., 0., 1.) * p
+    );
+}
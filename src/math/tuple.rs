@@ -1,11 +1,14 @@
+#![allow(clippy::bool_assert_comparison)]
+
+use ray_tracer::approx_eq;
 use std::ops;
 
 #[derive(Debug, Copy, Clone)]
 pub struct Tuple {
-    x: f64,
-    y: f64,
-    z: f64,
-    w: f64,
+    pub x: f64,
+    pub y: f64,
+    pub z: f64,
+    pub w: f64,
 }
 
 impl Tuple {
@@ -55,21 +58,40 @@ impl Tuple {
     }
 }
 
+/// Equal within the crate's `EPSILON`, like matrices, so that computed points and
+/// vectors compare equal despite rounding. Points never equal vectors.
 impl PartialEq for Tuple {
     fn eq(&self, that: &Self) -> bool {
-        if f64::abs(self.x - that.x) > f64::EPSILON {
-            return false;
-        }
+        approx_eq(self.x, that.x)
+            && approx_eq(self.y, that.y)
+            && approx_eq(self.z, that.z)
+            && approx_eq(self.w, that.w)
+    }
+}
 
-        if f64::abs(self.y - that.y) > f64::EPSILON {
-            return false;
+/// Coordinates by axis, `x` being 0 and `w` 3.
+impl ops::Index<usize> for Tuple {
+    type Output = f64;
+    fn index(&self, axis: usize) -> &Self::Output {
+        match axis {
+            0 => &self.x,
+            1 => &self.y,
+            2 => &self.z,
+            3 => &self.w,
+            _ => panic!("Index out of bounds!"),
         }
+    }
+}
 
-        if f64::abs(self.z - that.z) > f64::EPSILON {
-            return false;
+impl ops::IndexMut<usize> for Tuple {
+    fn index_mut(&mut self, axis: usize) -> &mut Self::Output {
+        match axis {
+            0 => &mut self.x,
+            1 => &mut self.y,
+            2 => &mut self.z,
+            3 => &mut self.w,
+            _ => panic!("Index out of bounds!"),
         }
-
-        return true;
     }
 }
 
@@ -117,10 +139,10 @@ impl ops::Neg for Tuple {
 
     fn neg(self) -> Self::Output {
         Tuple {
-            x: -1.0 * self.x,
-            y: -1.0 * self.y,
-            z: -1.0 * self.z,
-            w: -1.0 * self.w,
+            x: -self.x,
+            y: -self.y,
+            z: -self.z,
+            w: -self.w,
         }
     }
 }
@@ -185,6 +207,15 @@ fn tuple_comparison() {
     assert_ne!(vec1, vec2);
 }
 
+#[test]
+fn tuple_comparison_within_epsilon() {
+    let vector = Tuple::vector(0.1 + 0.2, 1.0 / 3.0, 2.0 / 7.0);
+
+    assert_eq!(Tuple::vector(0.3, 0.333333, 0.285714), vector);
+    assert_ne!(Tuple::vector(0.3, 0.3333, 0.2857), vector);
+    assert_ne!(Tuple::point(0.3, 0.333333, 0.285714), vector);
+}
+
 #[test]
 fn tuple_addition() {
     let tuple_1 = Tuple::point(3.0, -2.0, 5.0);
@@ -330,3 +361,17 @@ fn vector_cross() {
     assert_eq!(-2.0, cross.y);
     assert_eq!(1.0, cross.z);
 }
+
+#[test]
+fn indexing_by_axis() {
+    let point = Tuple::point(4., -4., 3.);
+
+    assert_eq!(4., point[0]);
+    assert_eq!(-4., point[1]);
+    assert_eq!(3., point[2]);
+    assert_eq!(1., point[3]);
+
+    let mut moved = point;
+    moved[1] = 2.;
+    assert_eq!(Tuple::point(4., 2., 3.), moved);
+}
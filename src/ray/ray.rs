@@ -0,0 +1,80 @@
+use crate::math::matrix::Matrix;
+#[cfg(test)]
+use crate::math::transformation::{scaling, translation};
+use crate::math::tuple::Tuple;
+
+#[derive(Debug, Copy, Clone)]
+pub struct Ray {
+    pub origin: Tuple,
+    pub direction: Tuple,
+    /// When the ray was cast, for shapes that move.
+    pub time: f64,
+}
+
+impl Ray {
+    pub fn create(origin: Tuple, direction: Tuple) -> Ray {
+        Ray::create_at(origin, direction, 0.)
+    }
+
+    pub fn create_at(origin: Tuple, direction: Tuple, time: f64) -> Ray {
+        Ray {
+            origin,
+            direction,
+            time,
+        }
+    }
+
+    pub fn position(&self, t: f64) -> Tuple {
+        self.origin + self.direction * t
+    }
+
+    pub fn transform(&self, matrix: Matrix) -> Ray {
+        Ray {
+            origin: matrix * self.origin,
+            direction: matrix * self.direction,
+            time: self.time,
+        }
+    }
+}
+
+#[test]
+fn ray_creation() {
+    let origin = Tuple::point(1., 2., 3.);
+    let direction = Tuple::vector(4., 5., 6.);
+    let ray = Ray::create(origin, direction);
+
+    assert_eq!(origin, ray.origin);
+    assert_eq!(direction, ray.direction);
+}
+
+#[test]
+fn ray_position() {
+    let ray = Ray::create(Tuple::point(2., 3., 4.), Tuple::vector(1., 0., 0.));
+
+    assert_eq!(Tuple::point(2., 3., 4.), ray.position(0.));
+    assert_eq!(Tuple::point(3., 3., 4.), ray.position(1.));
+    assert_eq!(Tuple::point(1., 3., 4.), ray.position(-1.));
+    assert_eq!(Tuple::point(4.5, 3., 4.), ray.position(2.5));
+}
+
+#[test]
+fn ray_translate_and_scale() {
+    let ray = Ray::create(Tuple::point(1., 2., 3.), Tuple::vector(0., 1., 0.));
+
+    let translated = ray.transform(translation(3., 4., 5.));
+    assert_eq!(Tuple::point(4., 6., 8.), translated.origin);
+    assert_eq!(Tuple::vector(0., 1., 0.), translated.direction);
+
+    let scaled = ray.transform(scaling(2., 3., 4.));
+    assert_eq!(Tuple::point(2., 6., 12.), scaled.origin);
+    assert_eq!(Tuple::vector(0., 3., 0.), scaled.direction);
+}
+
+#[test]
+fn ray_keeps_time_when_transformed() {
+    let ray = Ray::create_at(Tuple::point(1., 2., 3.), Tuple::vector(0., 1., 0.), 0.25);
+    let moved = ray.transform(translation(3., 4., 5.));
+
+    assert_eq!(0.25, moved.time);
+    assert_eq!(0., Ray::create(ray.origin, ray.direction).time);
+}
@@ -0,0 +1,416 @@
+use crate::intersection::intersection::Intersection;
+use crate::material::material::Material;
+use crate::math::bounds::Bounds;
+use crate::math::matrix::{Matrix, IDENTITY_MATRIX};
+use crate::math::quaternion::Quaternion;
+#[cfg(test)]
+use crate::math::transformation::{rotation_y, rotation_z};
+use crate::math::transformation::{scaling, translation};
+use crate::math::tuple::Tuple;
+use crate::ray::ray::Ray;
+#[cfg(test)]
+use crate::shapes::quadric::Quadric;
+#[cfg(test)]
+use crate::shapes::rectangle::Rectangle;
+use crate::shapes::shape::Shape;
+#[cfg(test)]
+use ray_tracer::approx_eq;
+use std::f64::consts::PI;
+
+/// Keyframe split into parts that interpolate well: a translation, a rotation and
+/// whatever stretching is left.
+#[derive(Debug)]
+struct Keyframe {
+    time: f64,
+    translation: Tuple,
+    rotation: Quaternion,
+    stretch: Matrix,
+}
+
+fn blend(from: &Matrix, to: &Matrix, amount: f64) -> Matrix {
+    let mut result = IDENTITY_MATRIX;
+    for row in 0..4 {
+        for col in 0..4 {
+            result.set(
+                row,
+                col,
+                from[row][col] + (to[row][col] - from[row][col]) * amount,
+            );
+        }
+    }
+    result
+}
+
+impl Keyframe {
+    fn create(time: f64, transform: Matrix) -> Keyframe {
+        // Rays are traced through the inverse motion, which a flattening transform lacks
+        assert!(
+            transform.is_invertible(),
+            "Keyframes need an invertible transform!"
+        );
+        let translation = Tuple::vector(transform[0][3], transform[1][3], transform[2][3]);
+        let mut linear = transform;
+        for row in 0..3 {
+            linear.set(row, 3, 0.);
+        }
+
+        // Polar decomposition: averaging with the inverse transpose converges on
+        // the closest rotation
+        let mut rotation = linear;
+        for _ in 0..100 {
+            let next = blend(&rotation, &rotation.inverse().transpose(), 0.5);
+            let change = (0..3)
+                .flat_map(|row| (0..3).map(move |col| (row, col)))
+                .map(|(row, col)| (next[row][col] - rotation[row][col]).abs())
+                .fold(0., f64::max);
+            rotation = next;
+            if change < 1e-12 {
+                break;
+            }
+        }
+        // Mirroring is left to the stretch, quaternions only hold proper rotations
+        if rotation.determinant() < 0. {
+            rotation = rotation * scaling(-1., -1., -1.);
+        }
+
+        Keyframe {
+            time,
+            translation,
+            rotation: Quaternion::from_matrix(&rotation),
+            stretch: rotation.inverse() * linear,
+        }
+    }
+
+    fn parts(&self) -> Parts {
+        (self.translation, self.rotation, self.stretch)
+    }
+
+    fn interpolate(&self, next: &Keyframe, time: f64) -> Parts {
+        let amount = (time - self.time) / (next.time - self.time);
+        (
+            self.translation + (next.translation - self.translation) * amount,
+            self.rotation.slerp(next.rotation, amount),
+            blend(&self.stretch, &next.stretch, amount),
+        )
+    }
+}
+
+/// Translation, rotation and stretch, applied last to first.
+type Parts = (Tuple, Quaternion, Matrix);
+
+fn compose((t, rotation, stretch): Parts) -> Matrix {
+    translation(t.x, t.y, t.z) * rotation.to_matrix() * stretch
+}
+
+/// Inverse of `compose`, undoing each part in turn rather than inverting the whole.
+fn compose_inverse((t, rotation, stretch): Parts) -> Matrix {
+    invert_linear(&stretch) * rotation.conjugate().to_matrix() * translation(-t.x, -t.y, -t.z)
+}
+
+/// Inverse of a matrix that only has an upper 3x3 part, through its adjugate.
+fn invert_linear(m: &Matrix) -> Matrix {
+    // Cycling the indices takes care of the cofactor signs
+    let cofactor = |row: usize, col: usize| {
+        let (r1, r2) = ((row + 1) % 3, (row + 2) % 3);
+        let (c1, c2) = ((col + 1) % 3, (col + 2) % 3);
+        m[r1][c1] * m[r2][c2] - m[r1][c2] * m[r2][c1]
+    };
+    let determinant: f64 = (0..3).map(|col| m[0][col] * cofactor(0, col)).sum();
+
+    let mut result = IDENTITY_MATRIX;
+    for row in 0..3 {
+        for col in 0..3 {
+            result.set(col, row, cofactor(row, col) / determinant);
+        }
+    }
+    result
+}
+
+/// Moves a shape over time by keyframed transforms, applied in between the shape's
+/// own transform and this one's. Rays pick the motion up through their time. Before
+/// the first keyframe and after the last the shape holds still.
+#[derive(Debug)]
+pub struct Animated {
+    child: Box<dyn Shape>,
+    keyframes: Vec<Keyframe>,
+    transform: Matrix,
+    inverse: Matrix,
+    material: Material,
+}
+
+impl Animated {
+    pub fn create(child: Box<dyn Shape>) -> Animated {
+        Animated {
+            child,
+            keyframes: vec![],
+            transform: IDENTITY_MATRIX,
+            inverse: IDENTITY_MATRIX,
+            material: Material::create(),
+        }
+    }
+
+    /// Moves from `start` at time 0 to `end` at time 1, as over a camera's shutter.
+    pub fn create_between(child: Box<dyn Shape>, start: Matrix, end: Matrix) -> Animated {
+        let mut animated = Animated::create(child);
+        animated.add_keyframe(0., start);
+        animated.add_keyframe(1., end);
+        animated
+    }
+
+    pub fn child(&self) -> &dyn Shape {
+        self.child.as_ref()
+    }
+
+    pub fn add_keyframe(&mut self, time: f64, transform: Matrix) {
+        let index = self
+            .keyframes
+            .partition_point(|keyframe| keyframe.time < time);
+        self.keyframes
+            .insert(index, Keyframe::create(time, transform));
+    }
+
+    /// Keyframed motion at a point in time. Translations are interpolated linearly,
+    /// rotations along the shortest arc and the remaining scaling linearly again.
+    pub fn motion_at(&self, time: f64) -> Matrix {
+        self.parts_at(time).map_or(IDENTITY_MATRIX, compose)
+    }
+
+    /// Inverse of `motion_at`.
+    pub fn motion_inverse_at(&self, time: f64) -> Matrix {
+        self.parts_at(time).map_or(IDENTITY_MATRIX, compose_inverse)
+    }
+
+    fn parts_at(&self, time: f64) -> Option<Parts> {
+        let next = self
+            .keyframes
+            .partition_point(|keyframe| keyframe.time <= time);
+        match next {
+            _ if self.keyframes.is_empty() => None,
+            0 => Some(self.keyframes[0].parts()),
+            _ if next == self.keyframes.len() => Some(self.keyframes[next - 1].parts()),
+            _ => Some(self.keyframes[next - 1].interpolate(&self.keyframes[next], time)),
+        }
+    }
+}
+
+impl Shape for Animated {
+    fn transform(&self) -> &Matrix {
+        &self.transform
+    }
+
+    fn inverse_transform(&self) -> &Matrix {
+        &self.inverse
+    }
+
+    fn set_transform(&mut self, transform: Matrix) {
+        self.transform = transform;
+        self.inverse = transform.inverse();
+    }
+
+    fn inverse_transform_at(&self, time: f64) -> Matrix {
+        self.motion_inverse_at(time) * self.inverse
+    }
+
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn set_material(&mut self, material: Material) {
+        self.material = material;
+    }
+
+    fn local_intersect(&self, ray: &Ray) -> Vec<Intersection<'_>> {
+        self.child
+            .intersect(ray)
+            .into_iter()
+            .map(|mut intersection| {
+                intersection.parents.push(self);
+                intersection
+            })
+            .collect()
+    }
+
+    fn local_normal_at(&self, _point: Tuple, _hit: &Intersection) -> Tuple {
+        panic!("Animations have no surface, the normal is taken from the child that was hit!")
+    }
+
+    fn local_contains(&self, point: Tuple) -> bool {
+        self.child.contains(point)
+    }
+
+    fn local_occludes(&self, ray: &Ray, max_t: f64) -> bool {
+        self.child.occludes(ray, max_t)
+    }
+
+    /// Box around the child all along its motion. The motion is sampled between
+    /// keyframes, and the box grown by how far a turn can bulge out in between.
+    fn local_bounds(&self) -> Bounds {
+        let child = self.child.parent_space_bounds();
+        if self.keyframes.is_empty() {
+            return child;
+        }
+
+        const STEPS: usize = 16;
+        let mut times = vec![self.keyframes[0].time];
+        for pair in self.keyframes.windows(2) {
+            let (start, end) = (pair[0].time, pair[1].time);
+            times
+                .extend((1..=STEPS).map(|step| start + (end - start) * step as f64 / STEPS as f64));
+        }
+
+        let mut bounds = Bounds::empty();
+        let mut reach: f64 = 0.;
+        for time in times {
+            let motion = self.motion_at(time);
+            let moved = child.transform(motion);
+            // Farthest the child gets from the center it turns around
+            let center = Tuple::point(motion[0][3], motion[1][3], motion[2][3]);
+            let far = Tuple::vector(
+                (moved.min.x - center.x)
+                    .abs()
+                    .max((moved.max.x - center.x).abs()),
+                (moved.min.y - center.y)
+                    .abs()
+                    .max((moved.max.y - center.y).abs()),
+                (moved.min.z - center.z)
+                    .abs()
+                    .max((moved.max.z - center.z).abs()),
+            );
+            reach = reach.max(far.magnitude());
+            bounds = bounds.merge(&moved);
+        }
+
+        // Turns are at most half a circle between keyframes
+        let bulge = reach * (1. - (PI / (2. * STEPS as f64)).cos());
+        let margin = Tuple::vector(bulge, bulge, bulge);
+        Bounds::create(bounds.min - margin, bounds.max + margin)
+    }
+}
+
+#[cfg(test)]
+fn sphere() -> Box<dyn Shape> {
+    Box::new(Quadric::create([1., 1., 1., 0., 0., 0., 0., 0., 0., -1.]))
+}
+
+#[test]
+fn ray_time_selects_position() {
+    let animated = Animated::create_between(sphere(), IDENTITY_MATRIX, translation(4., 0., 0.));
+    let at = |time| Ray::create_at(Tuple::point(2., 0., -5.), Tuple::vector(0., 0., 1.), time);
+
+    assert!(animated.intersect(&at(0.)).is_empty());
+    let xs = animated.intersect(&at(0.5));
+    assert_eq!(2, xs.len());
+    assert!(approx_eq(4., xs[0].t));
+    assert_eq!(0.5, xs[0].time);
+    assert!(animated.intersect(&at(1.)).is_empty());
+    // Holds still outside the keyframes
+    assert!(animated.intersect(&at(-3.)).is_empty());
+}
+
+#[test]
+fn interpolating_rotation_and_scale() {
+    let animated = Animated::create_between(
+        sphere(),
+        IDENTITY_MATRIX,
+        rotation_y(std::f64::consts::FRAC_PI_2) * scaling(3., 3., 3.),
+    );
+
+    // Blending the matrices would shrink the shape half way through the turn
+    assert_eq!(
+        rotation_y(std::f64::consts::FRAC_PI_4) * scaling(2., 2., 2.),
+        animated.motion_at(0.5)
+    );
+    assert_eq!(IDENTITY_MATRIX, animated.motion_at(0.));
+}
+
+#[test]
+fn keyframes_in_any_order() {
+    let mut animated = Animated::create(sphere());
+    animated.add_keyframe(2., translation(0., 4., 0.));
+    animated.add_keyframe(0., IDENTITY_MATRIX);
+    animated.add_keyframe(1., translation(2., 0., 0.));
+
+    assert_eq!(translation(1., 0., 0.), animated.motion_at(0.5));
+    assert_eq!(translation(1., 2., 0.), animated.motion_at(1.5));
+    assert_eq!(translation(0., 4., 0.), animated.motion_at(5.));
+}
+
+#[test]
+fn normal_follows_motion() {
+    let mut animated = Animated::create_between(
+        Box::new(Rectangle::create(4., 4.)),
+        IDENTITY_MATRIX,
+        rotation_z(std::f64::consts::FRAC_PI_2),
+    );
+    animated.set_transform(translation(0., 1., 0.));
+
+    let ray = Ray::create_at(Tuple::point(0.1, 5., 0.), Tuple::vector(0., -1., 0.), 0.5);
+    let xs = animated.intersect(&ray);
+    let hit = &xs[0];
+    let half = f64::sqrt(2.) / 2.;
+
+    assert!(approx_eq(5. - 1.1, hit.t));
+    assert!(std::ptr::addr_eq(animated.child(), hit.object));
+    assert_eq!(
+        Tuple::vector(-half, half, 0.),
+        hit.object.normal_at(ray.position(hit.t), hit)
+    );
+}
+
+#[test]
+fn mirrored_keyframe() {
+    let mut animated = Animated::create(sphere());
+    animated.add_keyframe(0., scaling(-1., 2., 1.));
+
+    assert_eq!(scaling(-1., 2., 1.), animated.motion_at(0.));
+}
+
+#[test]
+fn containment_at_start_of_motion() {
+    let animated = Animated::create_between(sphere(), IDENTITY_MATRIX, translation(4., 0., 0.));
+
+    assert!(animated.contains(Tuple::point(0.5, 0., 0.)));
+    assert!(!animated.contains(Tuple::point(4., 0., 0.)));
+}
+
+#[test]
+fn bounds_cover_whole_motion() {
+    let mut sphere = Quadric::create([1., 1., 1., 0., 0., 0., 0., 0., 0., -1.]);
+    sphere.set_transform(translation(3., 0., 0.));
+    let animated = Animated::create_between(
+        Box::new(sphere),
+        IDENTITY_MATRIX,
+        rotation_y(std::f64::consts::PI),
+    );
+    let bounds = animated.local_bounds();
+
+    // Swings round from +x to -x through -z
+    assert!(bounds.min.x <= -4. && bounds.max.x >= 4.);
+    assert!(bounds.min.z <= -4. && bounds.max.z < 1.1);
+    assert!(bounds.contains(animated.motion_at(0.3) * Tuple::point(3., 0., -1.)));
+}
+
+#[test]
+fn inverse_undoes_motion() {
+    let mut animated = Animated::create(sphere());
+    animated.set_transform(translation(0., 1., 0.));
+    animated.add_keyframe(
+        0.,
+        translation(1., 2., 3.) * rotation_y(0.5) * scaling(1., 2., 3.),
+    );
+    animated.add_keyframe(
+        1.,
+        rotation_z(-2.) * translation(-1., 0., 4.) * scaling(-0.5, 1., 1.),
+    );
+
+    for &time in &[-1., 0., 0.2, 0.5, 1., 2.] {
+        let forward = *animated.transform() * animated.motion_at(time);
+        assert_eq!(forward.inverse(), animated.inverse_transform_at(time));
+    }
+}
+
+#[test]
+#[should_panic(expected = "Keyframes need an invertible transform!")]
+fn singular_keyframes_are_rejected() {
+    Animated::create_between(sphere(), IDENTITY_MATRIX, scaling(0., 0., 0.));
+}
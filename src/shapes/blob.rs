@@ -0,0 +1,286 @@
+use crate::intersection::intersection::Intersection;
+use crate::material::material::Material;
+use crate::math::bounds::Bounds;
+use crate::math::matrix::{Matrix, IDENTITY_MATRIX};
+use crate::math::polynomial::solve_quartic;
+use crate::math::tuple::Tuple;
+use crate::ray::ray::Ray;
+use crate::shapes::shape::Shape;
+#[cfg(test)]
+use ray_tracer::approx_eq;
+
+/// Spherical field source of a blob. Its contribution `weight·(1 - d²/radius²)²`
+/// falls off smoothly to zero at `radius` and stays zero beyond it. A negative weight
+/// carves into the other sources.
+#[derive(Debug, Copy, Clone)]
+pub struct BlobSource {
+    pub center: Tuple,
+    pub radius: f64,
+    pub weight: f64,
+}
+
+impl BlobSource {
+    /// Coefficients of the contribution along the ray, highest degree first, and the
+    /// range of `t` in which the ray is inside the source's sphere of influence.
+    fn along(&self, ray: &Ray) -> Option<([f64; 5], f64, f64)> {
+        let offset = ray.origin - self.center;
+        let d = ray.direction;
+        let r2 = self.radius * self.radius;
+
+        let a = d.x * d.x + d.y * d.y + d.z * d.z;
+        let b = 2. * (d.x * offset.x + d.y * offset.y + d.z * offset.z);
+        let c = offset.x * offset.x + offset.y * offset.y + offset.z * offset.z;
+
+        let discriminant = b * b - 4. * a * (c - r2);
+        if discriminant <= 0. {
+            return None;
+        }
+        let t0 = (-b - discriminant.sqrt()) / (2. * a);
+        let t1 = (-b + discriminant.sqrt()) / (2. * a);
+
+        // 1 - d²/r² as a quadratic in t, then squared and weighted.
+        let (g2, g1, g0) = (-a / r2, -b / r2, 1. - c / r2);
+        let w = self.weight;
+        Some((
+            [
+                w * g2 * g2,
+                w * 2. * g2 * g1,
+                w * (g1 * g1 + 2. * g2 * g0),
+                w * 2. * g1 * g0,
+                w * g0 * g0,
+            ],
+            t0,
+            t1,
+        ))
+    }
+}
+
+/// Metaballs: the surface where the summed field of all sources equals `threshold`.
+#[derive(Debug)]
+pub struct Blob {
+    sources: Vec<BlobSource>,
+    pub threshold: f64,
+    transform: Matrix,
+    inverse: Matrix,
+    material: Material,
+}
+
+impl Blob {
+    pub fn create(threshold: f64) -> Blob {
+        Blob {
+            sources: vec![],
+            threshold,
+            transform: IDENTITY_MATRIX,
+            inverse: IDENTITY_MATRIX,
+            material: Material::create(),
+        }
+    }
+
+    pub fn add_source(&mut self, center: Tuple, radius: f64, weight: f64) {
+        self.sources.push(BlobSource {
+            center,
+            radius,
+            weight,
+        });
+    }
+
+    pub fn sources(&self) -> &[BlobSource] {
+        &self.sources
+    }
+
+    pub fn field_at(&self, point: Tuple) -> f64 {
+        self.sources
+            .iter()
+            .map(|source| {
+                let offset = point - source.center;
+                let falloff = 1. - offset.dot(offset) / (source.radius * source.radius);
+                if falloff > 0. {
+                    source.weight * falloff * falloff
+                } else {
+                    0.
+                }
+            })
+            .sum()
+    }
+}
+
+impl Shape for Blob {
+    fn transform(&self) -> &Matrix {
+        &self.transform
+    }
+
+    fn inverse_transform(&self) -> &Matrix {
+        &self.inverse
+    }
+
+    fn set_transform(&mut self, transform: Matrix) {
+        self.transform = transform;
+        self.inverse = transform.inverse();
+    }
+
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn set_material(&mut self, material: Material) {
+        self.material = material;
+    }
+
+    /// Sources the ray misses are culled by their bounding sphere. The ray is then cut
+    /// at every point where a source starts or stops contributing; within each piece
+    /// the field is a single quartic in `t`, solved exactly.
+    fn local_intersect(&self, ray: &Ray) -> Vec<Intersection<'_>> {
+        let active: Vec<([f64; 5], f64, f64)> = self
+            .sources
+            .iter()
+            .filter_map(|source| source.along(ray))
+            .collect();
+
+        let mut cuts: Vec<f64> = active
+            .iter()
+            .flat_map(|(_, t0, t1)| vec![*t0, *t1])
+            .collect();
+        cuts.sort_by(|a, b| a.total_cmp(b));
+
+        let mut xs = vec![];
+        for segment in cuts.windows(2) {
+            let (start, end) = (segment[0], segment[1]);
+            let middle = (start + end) / 2.;
+
+            let mut coefficients = [0., 0., 0., 0., -self.threshold];
+            for (contribution, t0, t1) in active.iter() {
+                if *t0 <= middle && middle <= *t1 {
+                    for (sum, term) in coefficients.iter_mut().zip(contribution.iter()) {
+                        *sum += term;
+                    }
+                }
+            }
+
+            let [a, b, c, d, e] = coefficients;
+            for t in solve_quartic(a, b, c, d, e) {
+                if start <= t && t < end {
+                    xs.push(Intersection::create(t, self));
+                }
+            }
+        }
+        xs
+    }
+
+    /// Opposite of the field gradient, as the field falls off towards the outside.
+    fn local_normal_at(&self, point: Tuple, _hit: &Intersection) -> Tuple {
+        self.sources
+            .iter()
+            .fold(Tuple::vector(0., 0., 0.), |normal, source| {
+                let offset = point - source.center;
+                let r2 = source.radius * source.radius;
+                let falloff = 1. - offset.dot(offset) / r2;
+                if falloff > 0. {
+                    normal + offset * (source.weight * falloff / r2)
+                } else {
+                    normal
+                }
+            })
+    }
+
+    fn local_contains(&self, point: Tuple) -> bool {
+        self.field_at(point) > self.threshold
+    }
+
+    fn local_bounds(&self) -> Bounds {
+        self.sources.iter().fold(Bounds::empty(), |bounds, source| {
+            let reach = Tuple::vector(source.radius, source.radius, source.radius);
+            bounds
+                .add_point(source.center - reach)
+                .add_point(source.center + reach)
+        })
+    }
+}
+
+#[test]
+fn single_source_is_sphere() {
+    let mut blob = Blob::create(0.25);
+    blob.add_source(Tuple::point(0., 0., 0.), 1., 1.);
+    let ray = Ray::create(Tuple::point(0., 0., -5.), Tuple::vector(0., 0., 1.));
+    let xs = blob.local_intersect(&ray);
+
+    // (1 - d²)² = 0.25 at d² = 0.5
+    let radius = f64::sqrt(0.5);
+    assert_eq!(2, xs.len());
+    assert!(approx_eq(5. - radius, xs[0].t));
+    assert!(approx_eq(5. + radius, xs[1].t));
+}
+
+#[test]
+fn ray_outside_every_source_misses() {
+    let mut blob = Blob::create(0.25);
+    blob.add_source(Tuple::point(0., 0., 0.), 1., 1.);
+    blob.add_source(Tuple::point(3., 0., 0.), 1., 1.);
+    let ray = Ray::create(Tuple::point(0., 2., -5.), Tuple::vector(0., 0., 1.));
+
+    assert!(blob.local_intersect(&ray).is_empty());
+}
+
+#[test]
+fn close_sources_blend_together() {
+    let mut blob = Blob::create(0.5);
+    blob.add_source(Tuple::point(-0.6, 0., 0.), 1., 1.);
+    blob.add_source(Tuple::point(0.6, 0., 0.), 1., 1.);
+
+    // Halfway between the centers each source alone is below the threshold
+    assert!(blob.sources()[0].weight * (1. - 0.36_f64).powi(2) < blob.threshold);
+    assert!(blob.field_at(Tuple::point(0., 0., 0.)) > blob.threshold);
+
+    let ray = Ray::create(Tuple::point(0., 0., -5.), Tuple::vector(0., 0., 1.));
+    let xs = blob.local_intersect(&ray);
+    assert_eq!(2, xs.len());
+    for intersection in xs.iter() {
+        assert!(approx_eq(
+            blob.threshold,
+            blob.field_at(ray.position(intersection.t))
+        ));
+    }
+}
+
+#[test]
+fn distant_sources_stay_apart() {
+    let mut blob = Blob::create(0.25);
+    blob.add_source(Tuple::point(-2., 0., 0.), 1., 1.);
+    blob.add_source(Tuple::point(2., 0., 0.), 1., 1.);
+    let ray = Ray::create(Tuple::point(-5., 0., 0.), Tuple::vector(1., 0., 0.));
+
+    assert_eq!(4, blob.local_intersect(&ray).len());
+}
+
+#[test]
+fn negative_source_carves_hole() {
+    let mut blob = Blob::create(0.25);
+    blob.add_source(Tuple::point(0., 0., 0.), 2., 1.);
+    blob.add_source(Tuple::point(0., 0., 0.), 0.5, -2.);
+    let ray = Ray::create(Tuple::point(0., 0., -5.), Tuple::vector(0., 0., 1.));
+    let xs = blob.local_intersect(&ray);
+
+    assert_eq!(4, xs.len());
+    assert!(blob.field_at(ray.position(5.)) < blob.threshold);
+}
+
+#[test]
+fn normal_from_field_gradient() {
+    let mut blob = Blob::create(0.25);
+    blob.add_source(Tuple::point(0., 0., 0.), 1., 1.);
+    let hit = Intersection::create(0., &blob);
+    let third = f64::sqrt(3.) / 3. * f64::sqrt(0.5);
+
+    assert_eq!(
+        Tuple::vector(1., 1., 1.).normalize(),
+        blob.normal_at(Tuple::point(third, third, third), &hit)
+    );
+}
+
+#[test]
+fn point_inside_blob() {
+    let mut blob = Blob::create(0.5);
+    blob.add_source(Tuple::point(0., 0., 0.), 1., 1.);
+
+    assert!(blob.contains(Tuple::point(0.2, 0., 0.)));
+    assert!(!blob.contains(Tuple::point(0.9, 0., 0.)));
+}
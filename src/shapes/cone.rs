@@ -0,0 +1,324 @@
+use crate::intersection::intersection::Intersection;
+use crate::material::material::Material;
+use crate::math::bounds::Bounds;
+use crate::math::matrix::{Matrix, IDENTITY_MATRIX};
+#[cfg(test)]
+use crate::math::transformation::translation;
+use crate::math::tuple::Tuple;
+use crate::ray::ray::Ray;
+use crate::shapes::shape::Shape;
+#[cfg(test)]
+use ray_tracer::approx_eq;
+use ray_tracer::EPSILON;
+
+/// Double-napped cone around the y axis, truncated at `minimum` and `maximum`.
+/// The radius at any height equals |y|, so a closed cone gets caps of that radius.
+#[derive(Debug)]
+pub struct Cone {
+    pub minimum: f64,
+    pub maximum: f64,
+    pub closed: bool,
+    transform: Matrix,
+    inverse: Matrix,
+    material: Material,
+}
+
+impl Cone {
+    pub fn create() -> Cone {
+        Cone {
+            minimum: f64::NEG_INFINITY,
+            maximum: f64::INFINITY,
+            closed: false,
+            transform: IDENTITY_MATRIX,
+            inverse: IDENTITY_MATRIX,
+            material: Material::create(),
+        }
+    }
+
+    fn check_cap(ray: &Ray, t: f64, radius: f64) -> bool {
+        let x = ray.origin.x + t * ray.direction.x;
+        let z = ray.origin.z + t * ray.direction.z;
+
+        x * x + z * z <= radius * radius
+    }
+
+    fn intersect_caps<'a>(&'a self, ray: &Ray, xs: &mut Vec<Intersection<'a>>) {
+        if !self.closed || ray.direction.y.abs() < EPSILON {
+            return;
+        }
+
+        let t = (self.minimum - ray.origin.y) / ray.direction.y;
+        if Cone::check_cap(ray, t, self.minimum.abs()) {
+            xs.push(Intersection::create(t, self));
+        }
+
+        let t = (self.maximum - ray.origin.y) / ray.direction.y;
+        if Cone::check_cap(ray, t, self.maximum.abs()) {
+            xs.push(Intersection::create(t, self));
+        }
+    }
+
+    fn push_if_in_bounds<'a>(&'a self, ray: &Ray, t: f64, xs: &mut Vec<Intersection<'a>>) {
+        let y = ray.origin.y + t * ray.direction.y;
+        if self.minimum < y && y < self.maximum {
+            xs.push(Intersection::create(t, self));
+        }
+    }
+}
+
+impl Shape for Cone {
+    fn transform(&self) -> &Matrix {
+        &self.transform
+    }
+
+    fn inverse_transform(&self) -> &Matrix {
+        &self.inverse
+    }
+
+    fn set_transform(&mut self, transform: Matrix) {
+        self.transform = transform;
+        self.inverse = transform.inverse();
+    }
+
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn set_material(&mut self, material: Material) {
+        self.material = material;
+    }
+
+    fn local_intersect(&self, ray: &Ray) -> Vec<Intersection<'_>> {
+        let (o, d) = (ray.origin, ray.direction);
+        let a = d.x * d.x - d.y * d.y + d.z * d.z;
+        let b = 2. * o.x * d.x - 2. * o.y * d.y + 2. * o.z * d.z;
+        let c = o.x * o.x - o.y * o.y + o.z * o.z;
+
+        let mut xs = vec![];
+
+        if a.abs() < EPSILON {
+            // The ray is parallel to one of the halves, so it crosses the other one only once.
+            if b.abs() >= EPSILON {
+                self.push_if_in_bounds(ray, -c / b, &mut xs);
+            }
+        } else {
+            let discriminant = b * b - 4. * a * c;
+            if discriminant >= 0. {
+                let t0 = (-b - discriminant.sqrt()) / (2. * a);
+                let t1 = (-b + discriminant.sqrt()) / (2. * a);
+
+                self.push_if_in_bounds(ray, t0.min(t1), &mut xs);
+                self.push_if_in_bounds(ray, t0.max(t1), &mut xs);
+            }
+        }
+
+        self.intersect_caps(ray, &mut xs);
+        xs
+    }
+
+    fn local_normal_at(&self, point: Tuple, _hit: &Intersection) -> Tuple {
+        let dist = point.x * point.x + point.z * point.z;
+
+        if dist < self.maximum * self.maximum && point.y >= self.maximum - EPSILON {
+            return Tuple::vector(0., 1., 0.);
+        }
+
+        if dist < self.minimum * self.minimum && point.y <= self.minimum + EPSILON {
+            return Tuple::vector(0., -1., 0.);
+        }
+
+        // Only the apex itself lacks a tangent plane; any point off the axis, however
+        // close, still yields a valid direction once normalized.
+        if dist == 0. {
+            return Tuple::vector(0., if point.y > 0. { -1. } else { 1. }, 0.);
+        }
+
+        let y = if point.y > 0. {
+            -dist.sqrt()
+        } else {
+            dist.sqrt()
+        };
+        Tuple::vector(point.x, y, point.z)
+    }
+
+    fn local_contains(&self, point: Tuple) -> bool {
+        // A cone cut off without caps is open, so it has no inside
+        if !self.closed && (self.minimum.is_finite() || self.maximum.is_finite()) {
+            return false;
+        }
+        point.x * point.x + point.z * point.z < point.y * point.y
+            && self.minimum < point.y
+            && point.y < self.maximum
+    }
+
+    fn local_bounds(&self) -> Bounds {
+        let radius = self.minimum.abs().max(self.maximum.abs());
+        Bounds::create(
+            Tuple::point(-radius, self.minimum, -radius),
+            Tuple::point(radius, self.maximum, radius),
+        )
+    }
+}
+
+#[test]
+fn intersect_cone_with_ray() {
+    let shape = Cone::create();
+    let examples = [
+        (Tuple::point(0., 0., -5.), Tuple::vector(0., 0., 1.), 5., 5.),
+        (
+            Tuple::point(0., 0., -5.),
+            Tuple::vector(1., 1., 1.),
+            8.66025,
+            8.66025,
+        ),
+        (
+            Tuple::point(1., 1., -5.),
+            Tuple::vector(-0.5, -1., 1.),
+            4.55006,
+            49.44994,
+        ),
+    ];
+
+    for (origin, direction, t0, t1) in examples.iter() {
+        let ray = Ray::create(*origin, direction.normalize());
+        let xs = shape.local_intersect(&ray);
+
+        assert_eq!(2, xs.len());
+        assert!(approx_eq(*t0, xs[0].t));
+        assert!(approx_eq(*t1, xs[1].t));
+    }
+}
+
+#[test]
+fn intersect_cone_with_ray_parallel_to_one_half() {
+    let shape = Cone::create();
+    let ray = Ray::create(
+        Tuple::point(0., 0., -1.),
+        Tuple::vector(0., 1., 1.).normalize(),
+    );
+    let xs = shape.local_intersect(&ray);
+
+    assert_eq!(1, xs.len());
+    assert!(approx_eq(f64::sqrt(2.) / 2., xs[0].t));
+}
+
+#[test]
+fn intersect_cone_end_caps() {
+    let mut shape = Cone::create();
+    shape.minimum = -0.5;
+    shape.maximum = 0.5;
+    shape.closed = true;
+
+    let examples = [
+        (Tuple::point(0., 0., -5.), Tuple::vector(0., 1., 0.), 0),
+        (Tuple::point(0., 0., -0.25), Tuple::vector(0., 1., 1.), 2),
+        (Tuple::point(0., 0., -0.25), Tuple::vector(0., 1., 0.), 4),
+    ];
+
+    for (origin, direction, count) in examples.iter() {
+        let ray = Ray::create(*origin, direction.normalize());
+        assert_eq!(*count, shape.local_intersect(&ray).len());
+    }
+}
+
+#[test]
+fn intersect_truncated_open_cone() {
+    let mut shape = Cone::create();
+    shape.minimum = 1.;
+    shape.maximum = 2.;
+
+    let ray = Ray::create(Tuple::point(0., 0., -5.), Tuple::vector(0., 0., 1.));
+    assert_eq!(0, shape.local_intersect(&ray).len());
+
+    let ray = Ray::create(Tuple::point(0., 1.5, -5.), Tuple::vector(0., 0., 1.));
+    let xs = shape.local_intersect(&ray);
+    assert_eq!(2, xs.len());
+    assert!(approx_eq(3.5, xs[0].t));
+    assert!(approx_eq(6.5, xs[1].t));
+}
+
+#[test]
+fn intersect_transformed_cone() {
+    let mut shape = Cone::create();
+    shape.set_transform(translation(0., 1., 0.));
+    assert_eq!(&translation(0., 1., 0.), shape.transform());
+
+    let ray = Ray::create(Tuple::point(0., 0., -5.), Tuple::vector(0., 0., 1.));
+    let xs = shape.intersect(&ray);
+    assert_eq!(2, xs.len());
+    assert!(approx_eq(4., xs[0].t));
+    assert!(approx_eq(6., xs[1].t));
+}
+
+#[test]
+fn normal_vector_on_cone() {
+    let shape = Cone::create();
+    let hit = Intersection::create(0., &shape);
+
+    assert_eq!(
+        Tuple::vector(1., -f64::sqrt(2.), 1.),
+        shape.local_normal_at(Tuple::point(1., 1., 1.), &hit)
+    );
+    assert_eq!(
+        Tuple::vector(-1., 1., 0.),
+        shape.local_normal_at(Tuple::point(-1., -1., 0.), &hit)
+    );
+}
+
+#[test]
+fn normal_vector_near_cone_apex() {
+    let shape = Cone::create();
+    let hit = Intersection::create(0., &shape);
+
+    let normal = shape.normal_at(Tuple::point(1e-7, 1e-7, 0.), &hit);
+    assert_eq!(Tuple::vector(1., -1., 0.).normalize(), normal);
+
+    let normal = shape.normal_at(Tuple::point(0., 0., 0.), &hit);
+    assert_eq!(Tuple::vector(0., 1., 0.), normal);
+}
+
+#[test]
+fn normal_vector_on_cone_end_caps() {
+    let mut shape = Cone::create();
+    shape.minimum = -1.;
+    shape.maximum = 2.;
+    shape.closed = true;
+    let hit = Intersection::create(0., &shape);
+
+    assert_eq!(
+        Tuple::vector(0., -1., 0.),
+        shape.local_normal_at(Tuple::point(0.5, -1., 0.), &hit)
+    );
+    assert_eq!(
+        Tuple::vector(0., 1., 0.),
+        shape.local_normal_at(Tuple::point(1., 2., 0.5), &hit)
+    );
+}
+
+#[test]
+fn point_inside_cone() {
+    let mut cone = Cone::create();
+
+    assert!(cone.contains(Tuple::point(0.5, 1., 0.)));
+    assert!(cone.contains(Tuple::point(0., -2., 1.)));
+    assert!(!cone.contains(Tuple::point(1.5, 1., 0.)));
+
+    cone.minimum = -1.;
+    cone.maximum = 1.;
+    assert!(!cone.contains(Tuple::point(0.5, 0.8, 0.)));
+    cone.closed = true;
+    assert!(cone.contains(Tuple::point(0.5, 0.8, 0.)));
+    assert!(!cone.contains(Tuple::point(0.5, 1.5, 0.)));
+}
+
+#[test]
+fn bounds_of_cone() {
+    let mut cone = Cone::create();
+    assert!(!cone.local_bounds().is_finite());
+
+    cone.minimum = -5.;
+    cone.maximum = 3.;
+    let bounds = cone.local_bounds();
+    assert_eq!(Tuple::point(-5., -5., -5.), bounds.min);
+    assert_eq!(Tuple::point(5., 3., 5.), bounds.max);
+}
@@ -0,0 +1,344 @@
+use crate::intersection::intersection::{Intersection, Intersections};
+use crate::material::material::Material;
+use crate::math::bounds::Bounds;
+use crate::math::matrix::{Matrix, IDENTITY_MATRIX};
+#[cfg(test)]
+use crate::math::transformation::translation;
+use crate::math::tuple::Tuple;
+use crate::ray::ray::Ray;
+#[cfg(test)]
+use crate::shapes::cone::Cone;
+#[cfg(test)]
+use crate::shapes::group::Group;
+#[cfg(test)]
+use crate::shapes::quadric::Quadric;
+use crate::shapes::shape::Shape;
+#[cfg(test)]
+use ray_tracer::approx_eq;
+
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub enum CsgOperation {
+    Union,
+    Intersection,
+    Difference,
+}
+
+impl CsgOperation {
+    /// Whether a hit on one operand survives, given which operand was hit and
+    /// whether the ray is currently inside the left and right operands.
+    pub fn intersection_allowed(&self, left_hit: bool, in_left: bool, in_right: bool) -> bool {
+        match self {
+            CsgOperation::Union => (left_hit && !in_right) || (!left_hit && !in_left),
+            CsgOperation::Intersection => (left_hit && in_right) || (!left_hit && in_left),
+            CsgOperation::Difference => (left_hit && !in_right) || (!left_hit && in_left),
+        }
+    }
+}
+
+/// Constructive solid geometry: two shapes combined by an operation. Either
+/// operand can itself be a group or another CSG.
+#[derive(Debug)]
+pub struct Csg {
+    pub operation: CsgOperation,
+    pub left: Box<dyn Shape>,
+    pub right: Box<dyn Shape>,
+    transform: Matrix,
+    inverse: Matrix,
+    material: Material,
+}
+
+impl Csg {
+    pub fn create(operation: CsgOperation, left: Box<dyn Shape>, right: Box<dyn Shape>) -> Csg {
+        Csg {
+            operation,
+            left,
+            right,
+            transform: IDENTITY_MATRIX,
+            inverse: IDENTITY_MATRIX,
+            material: Material::create(),
+        }
+    }
+
+    /// Whether the intersection belongs to the left operand, either directly or
+    /// through one of the groups and CSGs nested inside it.
+    fn hits_left(&self, intersection: &Intersection) -> bool {
+        let left: &dyn Shape = &*self.left;
+        std::ptr::addr_eq(left, intersection.object)
+            || intersection
+                .parents
+                .iter()
+                .any(|parent| std::ptr::addr_eq(left, *parent))
+    }
+
+    pub fn filter_intersections<'a>(&self, xs: Intersections<'a>) -> Intersections<'a> {
+        let mut in_left = false;
+        let mut in_right = false;
+        let mut result = vec![];
+
+        for intersection in xs {
+            let left_hit = self.hits_left(&intersection);
+
+            if self
+                .operation
+                .intersection_allowed(left_hit, in_left, in_right)
+            {
+                result.push(intersection);
+            }
+
+            if left_hit {
+                in_left = !in_left;
+            } else {
+                in_right = !in_right;
+            }
+        }
+
+        Intersections::create(result)
+    }
+}
+
+impl Shape for Csg {
+    fn transform(&self) -> &Matrix {
+        &self.transform
+    }
+
+    fn inverse_transform(&self) -> &Matrix {
+        &self.inverse
+    }
+
+    fn set_transform(&mut self, transform: Matrix) {
+        self.transform = transform;
+        self.inverse = transform.inverse();
+    }
+
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn set_material(&mut self, material: Material) {
+        self.material = material;
+    }
+
+    fn local_intersect(&self, ray: &Ray) -> Vec<Intersection<'_>> {
+        let mut xs: Vec<Intersection> = self.left.intersect(ray).into_iter().collect();
+        xs.extend(self.right.intersect(ray));
+
+        self.filter_intersections(Intersections::create(xs))
+            .into_iter()
+            .map(|mut intersection| {
+                intersection.parents.push(self);
+                intersection
+            })
+            .collect()
+    }
+
+    fn local_normal_at(&self, _point: Tuple, _hit: &Intersection) -> Tuple {
+        panic!("CSG shapes have no surface, the normal is taken from the child that was hit!")
+    }
+
+    fn local_contains(&self, point: Tuple) -> bool {
+        let (in_left, in_right) = (self.left.contains(point), self.right.contains(point));
+        match self.operation {
+            CsgOperation::Union => in_left || in_right,
+            CsgOperation::Intersection => in_left && in_right,
+            CsgOperation::Difference => in_left && !in_right,
+        }
+    }
+
+    /// Unions cover both operands and intersections only the overlap of the two.
+    /// Differences never reach outside of the left operand.
+    fn local_bounds(&self) -> Bounds {
+        let left = self.left.parent_space_bounds();
+        match self.operation {
+            CsgOperation::Union => left.merge(&self.right.parent_space_bounds()),
+            CsgOperation::Intersection => left.overlap(&self.right.parent_space_bounds()),
+            CsgOperation::Difference => left,
+        }
+    }
+}
+
+#[cfg(test)]
+fn frustum() -> Cone {
+    let mut cone = Cone::create();
+    cone.minimum = 1.;
+    cone.maximum = 2.;
+    cone.closed = true;
+    cone
+}
+
+#[test]
+fn evaluating_rule_for_csg_operations() {
+    let cases = [
+        (true, true, true),
+        (true, true, false),
+        (true, false, true),
+        (true, false, false),
+        (false, true, true),
+        (false, true, false),
+        (false, false, true),
+        (false, false, false),
+    ];
+    let examples = [
+        (
+            CsgOperation::Union,
+            [false, true, false, true, false, false, true, true],
+        ),
+        (
+            CsgOperation::Intersection,
+            [true, false, true, false, true, true, false, false],
+        ),
+        (
+            CsgOperation::Difference,
+            [false, true, false, true, true, true, false, false],
+        ),
+    ];
+
+    for (operation, expected) in examples.iter() {
+        for ((left_hit, in_left, in_right), result) in cases.iter().zip(expected.iter()) {
+            assert_eq!(
+                *result,
+                operation.intersection_allowed(*left_hit, *in_left, *in_right)
+            );
+        }
+    }
+}
+
+#[test]
+fn filtering_list_of_intersections() {
+    let examples = [
+        (CsgOperation::Union, 0, 3),
+        (CsgOperation::Intersection, 1, 2),
+        (CsgOperation::Difference, 0, 1),
+    ];
+
+    for (operation, x0, x1) in examples.iter() {
+        let csg = Csg::create(
+            *operation,
+            Box::new(Cone::create()),
+            Box::new(Cone::create()),
+        );
+        let xs = Intersections::create(vec![
+            Intersection::create(1., &*csg.left),
+            Intersection::create(2., &*csg.right),
+            Intersection::create(3., &*csg.left),
+            Intersection::create(4., &*csg.right),
+        ]);
+
+        let result = csg.filter_intersections(xs);
+
+        assert_eq!(2, result.len());
+        assert_eq!((*x0 + 1) as f64, result[0].t);
+        assert_eq!((*x1 + 1) as f64, result[1].t);
+    }
+}
+
+#[test]
+fn ray_misses_csg_object() {
+    let csg = Csg::create(
+        CsgOperation::Union,
+        Box::new(frustum()),
+        Box::new(frustum()),
+    );
+    let ray = Ray::create(Tuple::point(0., 5., -5.), Tuple::vector(0., 0., 1.));
+
+    assert!(csg.local_intersect(&ray).is_empty());
+}
+
+#[test]
+fn ray_hits_csg_object() {
+    let mut right = frustum();
+    right.set_transform(translation(0., 0., 1.));
+    let csg = Csg::create(CsgOperation::Union, Box::new(frustum()), Box::new(right));
+    let ray = Ray::create(Tuple::point(0., 1.5, -5.), Tuple::vector(0., 0., 1.));
+
+    let xs = csg.local_intersect(&ray);
+
+    assert_eq!(2, xs.len());
+    assert!(approx_eq(3.5, xs[0].t));
+    assert!(std::ptr::addr_eq(&*csg.left, xs[0].object));
+    assert!(approx_eq(7.5, xs[1].t));
+    assert!(std::ptr::addr_eq(&*csg.right, xs[1].object));
+}
+
+#[test]
+fn difference_drills_hole_through_group_operand() {
+    let mut left = Group::create();
+    left.add_child(Box::new(frustum()));
+    let mut drill = frustum();
+    drill.set_transform(translation(0., 0., 1.));
+    let csg = Csg::create(CsgOperation::Difference, Box::new(left), Box::new(drill));
+    let ray = Ray::create(Tuple::point(0., 1.5, -5.), Tuple::vector(0., 0., 1.));
+
+    let xs = csg.intersect(&ray);
+
+    assert_eq!(2, xs.len());
+    assert!(approx_eq(3.5, xs[0].t));
+    assert!(approx_eq(4.5, xs[1].t));
+    assert_eq!(2, xs[0].parents.len());
+    assert_eq!(
+        Tuple::vector(0., -1., -1.).normalize(),
+        xs[1].object.normal_at(ray.position(xs[1].t), &xs[1])
+    );
+}
+
+#[test]
+fn nested_csg_operand() {
+    let mut right = frustum();
+    right.set_transform(translation(0., 0., 1.));
+    let inner = Csg::create(
+        CsgOperation::Intersection,
+        Box::new(frustum()),
+        Box::new(right),
+    );
+    let csg = Csg::create(
+        CsgOperation::Union,
+        Box::new(inner),
+        Box::new(Cone::create()),
+    );
+    let ray = Ray::create(Tuple::point(0., 1.5, -5.), Tuple::vector(0., 0., 1.));
+
+    let xs = csg.intersect(&ray);
+
+    assert_eq!(2, xs.len());
+    assert!(approx_eq(3.5, xs[0].t));
+    assert!(approx_eq(6.5, xs[1].t));
+}
+
+#[test]
+fn point_inside_csg() {
+    let sphere = |x| {
+        let mut sphere = Quadric::create([1., 1., 1., 0., 0., 0., 0., 0., 0., -1.]);
+        sphere.set_transform(translation(x, 0., 0.));
+        Box::new(sphere)
+    };
+    let (only_left, both, only_right) = (
+        Tuple::point(-0.5, 0., 0.),
+        Tuple::point(0.5, 0., 0.),
+        Tuple::point(1.5, 0., 0.),
+    );
+    let expectations = [
+        (CsgOperation::Union, [true, true, true]),
+        (CsgOperation::Intersection, [false, true, false]),
+        (CsgOperation::Difference, [true, false, false]),
+    ];
+
+    for (operation, expected) in expectations {
+        let csg = Csg::create(operation, sphere(0.), sphere(1.));
+        let inside = [only_left, both, only_right].map(|point| csg.contains(point));
+        assert_eq!(expected, inside);
+        assert!(!csg.contains(Tuple::point(5., 0., 0.)));
+    }
+}
+
+#[test]
+fn intersection_bounds_overlap_of_operands() {
+    let mut right = frustum();
+    right.set_transform(translation(0., 1., 0.));
+    let expected = right.parent_space_bounds();
+
+    let csg = Csg::create(
+        CsgOperation::Intersection,
+        Box::new(Cone::create()),
+        Box::new(right),
+    );
+    assert_eq!(expected, csg.local_bounds());
+}
@@ -0,0 +1,396 @@
+use crate::intersection::intersection::Intersection;
+use crate::material::material::Material;
+use crate::math::bounds::Bounds;
+use crate::math::matrix::{Matrix, IDENTITY_MATRIX};
+#[cfg(test)]
+use crate::math::transformation::scaling;
+use crate::math::tuple::Tuple;
+use crate::ray::ray::Ray;
+use crate::shapes::shape::Shape;
+#[cfg(test)]
+use ray_tracer::approx_eq;
+
+fn lerp(a: Tuple, b: Tuple, amount: f64) -> Tuple {
+    a + (b - a) * amount
+}
+
+fn bezier(points: &[Tuple; 4], u: f64) -> Tuple {
+    let a = lerp(points[0], points[1], u);
+    let b = lerp(points[1], points[2], u);
+    let c = lerp(points[2], points[3], u);
+    lerp(lerp(a, b, u), lerp(b, c, u), u)
+}
+
+fn bezier_tangent(points: &[Tuple; 4], u: f64) -> Tuple {
+    let a = lerp(points[1] - points[0], points[2] - points[1], u);
+    let b = lerp(points[2] - points[1], points[3] - points[2], u);
+    lerp(a, b, u) * 3.
+}
+
+/// Splits the curve in two halves with de Casteljau's construction.
+fn split(points: &[Tuple; 4]) -> ([Tuple; 4], [Tuple; 4]) {
+    let a = lerp(points[0], points[1], 0.5);
+    let b = lerp(points[1], points[2], 0.5);
+    let c = lerp(points[2], points[3], 0.5);
+    let ab = lerp(a, b, 0.5);
+    let bc = lerp(b, c, 0.5);
+    let middle = lerp(ab, bc, 0.5);
+
+    ([points[0], a, ab, middle], [middle, bc, c, points[3]])
+}
+
+/// Tube swept along a cubic Bezier curve, for hair, fur and grass. The width changes
+/// linearly from `start_width` to `end_width` and the ends are left open.
+/// Intersections carry the position along the curve, from 0 to 1, as `u`. The curve
+/// is cut into pieces flat enough to pass for cylinders.
+#[derive(Debug)]
+pub struct Curve {
+    points: [Tuple; 4],
+    start_width: f64,
+    end_width: f64,
+    bounds: Bounds,
+    transform: Matrix,
+    inverse: Matrix,
+    material: Material,
+}
+
+impl Curve {
+    pub fn create(points: [Tuple; 4], start_width: f64, end_width: f64) -> Curve {
+        // The control points hold the curve, the width is added all around
+        let radius = start_width.max(end_width) / 2.;
+        let margin = Tuple::vector(radius, radius, radius);
+        let hull = Bounds::from_points(&points);
+
+        Curve {
+            points,
+            start_width,
+            end_width,
+            bounds: Bounds::create(hull.min - margin, hull.max + margin),
+            transform: IDENTITY_MATRIX,
+            inverse: IDENTITY_MATRIX,
+            material: Material::create(),
+        }
+    }
+
+    pub fn bounds(&self) -> &Bounds {
+        &self.bounds
+    }
+
+    pub fn points(&self) -> &[Tuple; 4] {
+        &self.points
+    }
+
+    pub fn point_at(&self, u: f64) -> Tuple {
+        bezier(&self.points, u)
+    }
+
+    pub fn width_at(&self, u: f64) -> f64 {
+        self.start_width + (self.end_width - self.start_width) * u
+    }
+
+    /// Looks for hits in the part of the curve between `u0` and `u1`, whose control
+    /// points are given in ray space: the ray starts at the origin and runs along z.
+    /// Adds where the ray enters and leaves each piece it crosses, both as `(z, u)`.
+    fn subdivide(
+        &self,
+        points: &[Tuple; 4],
+        u0: f64,
+        u1: f64,
+        depth: u32,
+        hits: &mut Vec<[(f64, f64); 2]>,
+    ) {
+        let radius = self.width_at(u0).max(self.width_at(u1)) / 2.;
+        let hull = Bounds::from_points(points);
+        if hull.min.x - radius > 0.
+            || hull.max.x + radius < 0.
+            || hull.min.y - radius > 0.
+            || hull.max.y + radius < 0.
+        {
+            return;
+        }
+
+        if depth > 0 {
+            let (first, second) = split(points);
+            let middle = (u0 + u1) / 2.;
+            self.subdivide(&first, u0, middle, depth - 1, hits);
+            self.subdivide(&second, middle, u1, depth - 1, hits);
+            return;
+        }
+
+        // Flat enough to treat as a segment: find where it passes closest to the ray
+        let (start, end) = (points[0], points[3]);
+        let (dx, dy) = (end.x - start.x, end.y - start.y);
+        let length_squared = dx * dx + dy * dy;
+        if length_squared == 0. {
+            return;
+        }
+        let w = -(start.x * dx + start.y * dy) / length_squared;
+        if !(0. ..1.).contains(&w) {
+            return;
+        }
+
+        let closest = bezier(points, w);
+        let distance_squared = closest.x * closest.x + closest.y * closest.y;
+        let u = u0 + (u1 - u0) * w;
+        let radius = self.width_at(u) / 2.;
+        if distance_squared > radius * radius {
+            return;
+        }
+
+        // Through the cylinder along the curve there, which the ray may cross at a
+        // slant
+        let tangent = bezier_tangent(points, w);
+        let axis = tangent.normalize();
+        let direction = Tuple::vector(0., 0., 1.);
+        let across = direction - axis * axis.z;
+        let offset = Tuple::point(0., 0., 0.) - closest;
+        let offset = offset - axis * offset.dot(axis);
+        let a = across.dot(across);
+        let b = 2. * offset.dot(across);
+        let c = offset.dot(offset) - radius * radius;
+        let root = (b * b - 4. * a * c).max(0.).sqrt();
+        // Positions along the curve go by how far along the cylinder the hits are
+        let speed = tangent.magnitude();
+        let hit = |z: f64| {
+            let along = (Tuple::point(0., 0., z) - closest).dot(axis) / speed;
+            (z, (u + (u1 - u0) * along).clamp(0., 1.))
+        };
+        hits.push([hit((-b - root) / (2. * a)), hit((-b + root) / (2. * a))]);
+    }
+}
+
+impl Shape for Curve {
+    fn transform(&self) -> &Matrix {
+        &self.transform
+    }
+
+    fn inverse_transform(&self) -> &Matrix {
+        &self.inverse
+    }
+
+    fn set_transform(&mut self, transform: Matrix) {
+        self.transform = transform;
+        self.inverse = transform.inverse();
+    }
+
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn set_material(&mut self, material: Material) {
+        self.material = material;
+    }
+
+    fn local_intersect(&self, ray: &Ray) -> Vec<Intersection<'_>> {
+        if self.bounds.intersect(ray).is_none() {
+            return vec![];
+        }
+
+        // Ray space: origin at the ray's, z along its direction
+        let speed = ray.direction.magnitude();
+        let z_axis = ray.direction / speed;
+        let helper = if z_axis.x.abs() > 0.9 {
+            Tuple::vector(0., 1., 0.)
+        } else {
+            Tuple::vector(1., 0., 0.)
+        };
+        let x_axis = helper.cross(z_axis).normalize();
+        let y_axis = z_axis.cross(x_axis);
+        let to_ray_space = |point: Tuple| {
+            let offset = point - ray.origin;
+            Tuple::point(offset.dot(x_axis), offset.dot(y_axis), offset.dot(z_axis))
+        };
+        let points = self.points.map(to_ray_space);
+
+        // Enough halvings for each piece to be within a twentieth of the width of
+        // a straight segment
+        let bend = (0..2)
+            .map(|i| points[i] - points[i + 1] * 2. + points[i + 2])
+            .map(|d| d.x.abs().max(d.y.abs()).max(d.z.abs()))
+            .fold(0., f64::max);
+        let tolerance = self.start_width.max(self.end_width) * 0.05;
+        let depth = if bend == 0. {
+            0
+        } else {
+            ((f64::sqrt(2.) * 6. * bend / (8. * tolerance))
+                .log2()
+                .floor()
+                / 2.)
+                .clamp(0., 10.) as u32
+        };
+
+        let mut spans = vec![];
+        self.subdivide(&points, 0., 1., depth, &mut spans);
+
+        // Neighbouring pieces overlap where they meet, and a ray crossing there is
+        // only in and out of the tube once
+        spans.sort_by(|a, b| a[0].0.total_cmp(&b[0].0));
+        let mut merged: Vec<[(f64, f64); 2]> = vec![];
+        for [entry, exit] in spans {
+            match merged.last_mut() {
+                Some(last) if entry.0 <= last[1].0 => {
+                    if exit.0 > last[1].0 {
+                        last[1] = exit;
+                    }
+                }
+                _ => merged.push([entry, exit]),
+            }
+        }
+        merged
+            .into_iter()
+            .flatten()
+            .map(|(z, u)| Intersection::create_with_uv(z / speed, self, u, 0.))
+            .collect()
+    }
+
+    fn local_normal_at(&self, point: Tuple, hit: &Intersection) -> Tuple {
+        // Away from the spine, square to the tangent
+        let tangent = bezier_tangent(&self.points, hit.u);
+        let outwards = point - self.point_at(hit.u);
+        outwards - tangent * (outwards.dot(tangent) / tangent.dot(tangent))
+    }
+
+    fn local_bounds(&self) -> Bounds {
+        self.bounds
+    }
+}
+
+#[cfg(test)]
+fn straight(start_width: f64, end_width: f64) -> Curve {
+    Curve::create(
+        [
+            Tuple::point(-1., 0., 0.),
+            Tuple::point(-1. / 3., 0., 0.),
+            Tuple::point(1. / 3., 0., 0.),
+            Tuple::point(1., 0., 0.),
+        ],
+        start_width,
+        end_width,
+    )
+}
+
+#[test]
+fn ray_hits_straight_curve() {
+    let curve = straight(0.2, 0.2);
+    let ray = Ray::create(Tuple::point(0.5, 0., -5.), Tuple::vector(0., 0., 1.));
+    let xs = curve.local_intersect(&ray);
+
+    assert_eq!(2, xs.len());
+    assert!(approx_eq(4.9, xs[0].t));
+    assert!(approx_eq(5.1, xs[1].t));
+    assert!(approx_eq(0.75, xs[0].u));
+}
+
+#[test]
+fn slanted_ray_crosses_tube_lengthwise() {
+    let curve = straight(0.2, 0.2);
+    let ray = Ray::create(
+        Tuple::point(-4.5, 0., -5.),
+        Tuple::vector(1., 0., 1.).normalize(),
+    );
+    let xs = curve.local_intersect(&ray);
+
+    assert_eq!(2, xs.len());
+    assert!(approx_eq(4.9 * f64::sqrt(2.), xs[0].t));
+    assert!(approx_eq(5.1 * f64::sqrt(2.), xs[1].t));
+}
+
+#[test]
+fn ray_misses_curve() {
+    let curve = straight(0.2, 0.2);
+    let rays = [
+        Ray::create(Tuple::point(0.5, 0.15, -5.), Tuple::vector(0., 0., 1.)),
+        Ray::create(Tuple::point(1.5, 0., -5.), Tuple::vector(0., 0., 1.)),
+        Ray::create(Tuple::point(0., 5., 0.), Tuple::vector(0., 0., 1.)),
+    ];
+
+    for ray in rays.iter() {
+        assert!(curve.local_intersect(ray).is_empty());
+    }
+}
+
+#[test]
+fn bounds_hold_control_points_and_width() {
+    let curve = straight(0.2, 0.4);
+
+    assert_eq!(Tuple::point(1., 0., 0.), curve.points()[3]);
+    assert_eq!(Tuple::point(-1.2, -0.2, -0.2), curve.bounds().min);
+    assert_eq!(Tuple::point(1.2, 0.2, 0.2), curve.bounds().max);
+}
+
+#[test]
+fn curve_width_tapers() {
+    let curve = straight(0.4, 0.);
+
+    let thick = Ray::create(Tuple::point(-0.5, 0.12, -5.), Tuple::vector(0., 0., 1.));
+    let thin = Ray::create(Tuple::point(0.5, 0.12, -5.), Tuple::vector(0., 0., 1.));
+    assert_eq!(2, curve.local_intersect(&thick).len());
+    assert!(curve.local_intersect(&thin).is_empty());
+}
+
+#[test]
+fn hits_on_bent_curve_lie_on_tube() {
+    let curve = Curve::create(
+        [
+            Tuple::point(-1., 0., 0.),
+            Tuple::point(-0.5, 1., 0.),
+            Tuple::point(0.5, -1., 0.5),
+            Tuple::point(1., 0., 0.),
+        ],
+        0.1,
+        0.05,
+    );
+
+    // Down through the spine, straight and at a slant, in and out of the tube once
+    // even where the pieces of the curve meet. The open ends are left out.
+    for i in 0..100 {
+        let spine = curve.point_at(0.05 + i as f64 * 0.009);
+        for direction in [Tuple::vector(0., -1., 0.), Tuple::vector(0.5, -1., 0.5)] {
+            let ray = Ray::create(spine - direction * 5., direction.normalize());
+            let xs = curve.local_intersect(&ray);
+            assert_eq!(2, xs.len());
+            for hit in xs {
+                let distance = (ray.position(hit.t) - curve.point_at(hit.u)).magnitude();
+                assert!((distance - curve.width_at(hit.u) / 2.).abs() < 0.005);
+            }
+        }
+    }
+}
+
+#[test]
+fn normal_points_away_from_spine() {
+    let mut curve = straight(0.2, 0.2);
+    curve.set_transform(scaling(2., 2., 2.));
+    let ray = Ray::create(Tuple::point(1., 5., 0.), Tuple::vector(0., -1., 0.));
+    let xs = curve.intersect(&ray);
+
+    assert!(approx_eq(4.8, xs[0].t));
+    assert_eq!(
+        Tuple::vector(0., 1., 0.),
+        curve.normal_at(ray.position(xs[0].t), &xs[0])
+    );
+}
+
+#[test]
+fn pieces_meeting_cross_once() {
+    let curve = Curve::create(
+        [
+            Tuple::point(-1., 0., 0.),
+            Tuple::point(-0.5, 1.5, 0.),
+            Tuple::point(0.5, -1.5, 0.),
+            Tuple::point(1., 0., 0.),
+        ],
+        0.1,
+        0.1,
+    );
+
+    // Rays square to the plane of the curve pass by the ends of pieces all along it
+    for i in 0..200 {
+        for j in 0..200 {
+            let origin = Tuple::point(-1. + i as f64 * 0.01, -1. + j as f64 * 0.01, -5.);
+            let ray = Ray::create(origin, Tuple::vector(0., 0., 1.));
+            let count = curve.local_intersect(&ray).len();
+            assert!(count == 0 || count == 2);
+        }
+    }
+}
@@ -0,0 +1,196 @@
+use crate::intersection::intersection::Intersection;
+use crate::material::material::Material;
+use crate::math::bounds::Bounds;
+use crate::math::matrix::{Matrix, IDENTITY_MATRIX};
+use crate::math::tuple::Tuple;
+use crate::ray::ray::Ray;
+use crate::shapes::shape::Shape;
+#[cfg(test)]
+use ray_tracer::approx_eq;
+use ray_tracer::EPSILON;
+use std::f64::consts::PI;
+
+/// Flat disk in the xz plane, centered at the origin and facing up the y axis.
+/// A non-zero `inner_radius` punches a hole in the middle, turning it into an annulus.
+#[derive(Debug)]
+pub struct Disk {
+    pub radius: f64,
+    pub inner_radius: f64,
+    transform: Matrix,
+    inverse: Matrix,
+    material: Material,
+}
+
+impl Disk {
+    pub fn create(radius: f64) -> Disk {
+        Disk {
+            radius,
+            inner_radius: 0.,
+            transform: IDENTITY_MATRIX,
+            inverse: IDENTITY_MATRIX,
+            material: Material::create(),
+        }
+    }
+
+    /// Maps an object space point to `u` around the disk, starting at the +x axis,
+    /// and `v` from the inner to the outer edge, both in `[0, 1]`. A ring without
+    /// width has nothing to spread `v` over and keeps it at 0.
+    pub fn uv_at(&self, point: Tuple) -> (f64, f64) {
+        let angle = point.z.atan2(point.x);
+        let u = if angle < 0. { angle + 2. * PI } else { angle } / (2. * PI);
+        let width = self.radius - self.inner_radius;
+        if width <= 0. {
+            return (u, 0.);
+        }
+        let distance = (point.x * point.x + point.z * point.z).sqrt();
+        (u, (distance - self.inner_radius) / width)
+    }
+
+    /// Surface area in object space.
+    pub fn area(&self) -> f64 {
+        PI * (self.radius * self.radius - self.inner_radius * self.inner_radius)
+    }
+}
+
+impl Shape for Disk {
+    fn transform(&self) -> &Matrix {
+        &self.transform
+    }
+
+    fn inverse_transform(&self) -> &Matrix {
+        &self.inverse
+    }
+
+    fn set_transform(&mut self, transform: Matrix) {
+        self.transform = transform;
+        self.inverse = transform.inverse();
+    }
+
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn set_material(&mut self, material: Material) {
+        self.material = material;
+    }
+
+    fn local_intersect(&self, ray: &Ray) -> Vec<Intersection<'_>> {
+        if ray.direction.y.abs() < EPSILON {
+            return vec![];
+        }
+
+        let t = -ray.origin.y / ray.direction.y;
+        let point = ray.position(t);
+        let distance = point.x * point.x + point.z * point.z;
+        if distance > self.radius * self.radius || distance < self.inner_radius * self.inner_radius
+        {
+            return vec![];
+        }
+
+        let (u, v) = self.uv_at(point);
+        vec![Intersection::create_with_uv(t, self, u, v)]
+    }
+
+    fn local_normal_at(&self, _point: Tuple, _hit: &Intersection) -> Tuple {
+        Tuple::vector(0., 1., 0.)
+    }
+
+    fn local_bounds(&self) -> Bounds {
+        Bounds::create(
+            Tuple::point(-self.radius, 0., -self.radius),
+            Tuple::point(self.radius, 0., self.radius),
+        )
+    }
+}
+
+#[test]
+fn ray_hits_disk() {
+    let disk = Disk::create(2.);
+    let ray = Ray::create(Tuple::point(1., 3., 1.), Tuple::vector(0., -1., 0.));
+    let xs = disk.local_intersect(&ray);
+
+    assert_eq!(1, xs.len());
+    assert_eq!(3., xs[0].t);
+}
+
+#[test]
+fn ray_misses_disk() {
+    let disk = Disk::create(1.);
+    let examples = [
+        Ray::create(Tuple::point(1., 3., 1.), Tuple::vector(0., -1., 0.)),
+        Ray::create(Tuple::point(0., 1., 0.), Tuple::vector(1., 0., 0.)),
+        Ray::create(Tuple::point(0., 0., -2.), Tuple::vector(0., 0., 1.)),
+    ];
+
+    for ray in examples.iter() {
+        assert!(disk.local_intersect(ray).is_empty());
+    }
+}
+
+#[test]
+fn ray_through_annulus_hole_misses() {
+    let mut disk = Disk::create(2.);
+    disk.inner_radius = 1.;
+
+    let ray = Ray::create(Tuple::point(0.5, 1., 0.), Tuple::vector(0., -1., 0.));
+    assert!(disk.local_intersect(&ray).is_empty());
+
+    let ray = Ray::create(Tuple::point(1.5, 1., 0.), Tuple::vector(0., -1., 0.));
+    assert_eq!(1, disk.local_intersect(&ray).len());
+}
+
+#[test]
+fn uv_mapping_on_annulus() {
+    let mut disk = Disk::create(2.);
+    disk.inner_radius = 1.;
+    let examples = [
+        (Tuple::point(1., 0., 0.), 0., 0.),
+        (Tuple::point(0., 0., 1.5), 0.25, 0.5),
+        (Tuple::point(-2., 0., 0.), 0.5, 1.),
+        (Tuple::point(0., 0., -1.25), 0.75, 0.25),
+    ];
+
+    for (point, u, v) in examples.iter() {
+        let (actual_u, actual_v) = disk.uv_at(*point);
+        assert!(approx_eq(*u, actual_u));
+        assert!(approx_eq(*v, actual_v));
+    }
+}
+
+#[test]
+fn uv_mapping_on_ring_without_width() {
+    let mut disk = Disk::create(1.);
+    disk.inner_radius = 1.;
+
+    assert_eq!((0.25, 0.), disk.uv_at(Tuple::point(0., 0., 1.)));
+}
+
+#[test]
+fn intersection_records_uv_on_disk() {
+    let disk = Disk::create(2.);
+    let ray = Ray::create(Tuple::point(0., 1., 1.), Tuple::vector(0., -1., 0.));
+    let xs = disk.local_intersect(&ray);
+
+    assert!(approx_eq(0.25, xs[0].u));
+    assert!(approx_eq(0.5, xs[0].v));
+}
+
+#[test]
+fn surface_area_of_disk_and_annulus() {
+    let mut disk = Disk::create(2.);
+    assert!(approx_eq(4. * PI, disk.area()));
+
+    disk.inner_radius = 1.;
+    assert!(approx_eq(3. * PI, disk.area()));
+}
+
+#[test]
+fn normal_of_disk_is_constant() {
+    let disk = Disk::create(1.);
+    let hit = Intersection::create(0., &disk);
+
+    assert_eq!(
+        Tuple::vector(0., 1., 0.),
+        disk.local_normal_at(Tuple::point(0.5, 0., 0.2), &hit)
+    );
+}
@@ -0,0 +1,364 @@
+use crate::canvas::canvas::Canvas;
+#[cfg(test)]
+use crate::color::color::Color;
+use crate::intersection::intersection::Intersection;
+use crate::material::material::Material;
+use crate::math::bounds::Bounds;
+use crate::math::matrix::Matrix;
+#[cfg(test)]
+use crate::math::transformation::translation;
+use crate::math::tuple::Tuple;
+use crate::ray::ray::Ray;
+use crate::shapes::mesh::Mesh;
+use crate::shapes::shape::Shape;
+#[cfg(test)]
+use ray_tracer::approx_eq;
+use ray_tracer::EPSILON;
+use std::cell::OnceCell;
+use std::collections::HashMap;
+use std::fmt;
+
+/// Offset along the normal for a point of the mesh and its uv coordinates.
+pub type DisplacementFunction = Box<dyn Fn(Tuple, f64, f64) -> f64>;
+
+/// Uses the brightness of a canvas, looked up by uv with bilinear filtering, as the
+/// displacement, scaled by `scale`. `v` runs from the bottom of the canvas up.
+pub fn texture(canvas: Canvas, scale: f64) -> DisplacementFunction {
+    let (width, height) = (canvas.width, canvas.height);
+    let brightness = move |x: usize, y: usize| {
+        let color = canvas.pixel_at(x, y);
+        (color.red + color.green + color.blue) / 3.
+    };
+
+    Box::new(move |_, u, v| {
+        let x = u.clamp(0., 1.) * (width - 1) as f64;
+        let y = (1. - v.clamp(0., 1.)) * (height - 1) as f64;
+        let (x0, y0) = (x.floor() as usize, y.floor() as usize);
+        let (x1, y1) = ((x0 + 1).min(width - 1), (y0 + 1).min(height - 1));
+        let (fx, fy) = (x - x0 as f64, y - y0 as f64);
+
+        let top = brightness(x0, y0) * (1. - fx) + brightness(x1, y0) * fx;
+        let bottom = brightness(x0, y1) * (1. - fx) + brightness(x1, y1) * fx;
+        (top * (1. - fy) + bottom * fy) * scale
+    })
+}
+
+/// Mesh whose vertices are pushed along their normals by a displacement function.
+/// The first ray to reach its bounds tessellates the mesh down to `max_edge_length`
+/// and displaces it; later rays reuse the result. Displacements are clamped to
+/// `max_displacement`, which the bounds are grown by so they always hold the surface.
+/// The displaced mesh takes over the base mesh's transform and material.
+pub struct Displaced {
+    base: Mesh,
+    displacement: DisplacementFunction,
+    max_edge_length: f64,
+    max_displacement: f64,
+    bounds: Bounds,
+    displaced: OnceCell<Mesh>,
+    transform: Matrix,
+    inverse: Matrix,
+    material: Material,
+}
+
+impl Displaced {
+    pub fn create(
+        base: Mesh,
+        displacement: DisplacementFunction,
+        max_edge_length: f64,
+        max_displacement: f64,
+    ) -> Displaced {
+        assert!(
+            max_edge_length > 0.,
+            "Edges can only be split down to a positive length!"
+        );
+        let margin = Tuple::vector(max_displacement, max_displacement, max_displacement);
+        let bounds = Bounds::create(base.bounds().min - margin, base.bounds().max + margin);
+
+        Displaced {
+            displacement,
+            max_edge_length,
+            max_displacement,
+            bounds,
+            displaced: OnceCell::new(),
+            transform: *base.transform(),
+            inverse: *base.inverse_transform(),
+            material: *base.material(),
+            base,
+        }
+    }
+
+    pub fn bounds(&self) -> &Bounds {
+        &self.bounds
+    }
+
+    /// The tessellated and displaced mesh, built on first use.
+    pub fn mesh(&self) -> &Mesh {
+        self.displaced.get_or_init(|| {
+            let mut mesh = self.base.tessellate(self.max_edge_length);
+            if mesh.normals().is_empty() {
+                mesh.smooth_normals();
+            }
+
+            // Creases and uv seams leave several copies of one position, which
+            // all move by the same offset along their averaged normal so that
+            // the surface stays closed
+            let mut shared: HashMap<[u64; 3], (Tuple, usize)> = HashMap::new();
+            for (index, &point) in mesh.positions().iter().enumerate() {
+                let copy = shared
+                    .entry(position_key(point))
+                    .or_insert((Tuple::vector(0., 0., 0.), index));
+                copy.0 = copy.0 + mesh.normals()[index];
+            }
+
+            let positions = mesh
+                .positions()
+                .iter()
+                .map(|&point| {
+                    let (normal, first) = shared[&position_key(point)];
+                    let (u, v) = mesh.uvs().get(first).copied().unwrap_or((0., 0.));
+                    let offset = (self.displacement)(point, u, v)
+                        .clamp(-self.max_displacement, self.max_displacement);
+                    if normal.magnitude() < EPSILON {
+                        point
+                    } else {
+                        point + normal.normalize() * offset
+                    }
+                })
+                .collect();
+
+            let mut displaced = Mesh::create(positions, mesh.triangles().to_vec());
+            displaced.set_uvs(mesh.uvs().to_vec());
+            displaced.smooth_normals();
+            displaced
+        })
+    }
+}
+
+fn position_key(point: Tuple) -> [u64; 3] {
+    [point.x.to_bits(), point.y.to_bits(), point.z.to_bits()]
+}
+
+impl fmt::Debug for Displaced {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Displaced")
+            .field("base", &self.base)
+            .field("max_edge_length", &self.max_edge_length)
+            .field("max_displacement", &self.max_displacement)
+            .field("transform", &self.transform)
+            .finish()
+    }
+}
+
+impl Shape for Displaced {
+    fn transform(&self) -> &Matrix {
+        &self.transform
+    }
+
+    fn inverse_transform(&self) -> &Matrix {
+        &self.inverse
+    }
+
+    fn set_transform(&mut self, transform: Matrix) {
+        self.transform = transform;
+        self.inverse = transform.inverse();
+    }
+
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn set_material(&mut self, material: Material) {
+        self.material = material;
+    }
+
+    fn local_intersect(&self, ray: &Ray) -> Vec<Intersection<'_>> {
+        if self.bounds.intersect(ray).is_none() {
+            return vec![];
+        }
+
+        self.mesh()
+            .local_intersect(ray)
+            .into_iter()
+            .map(|hit| {
+                let mut intersection = Intersection::create_with_uv(hit.t, self, hit.u, hit.v);
+                intersection.primitive = hit.primitive;
+                intersection
+            })
+            .collect()
+    }
+
+    fn local_normal_at(&self, point: Tuple, hit: &Intersection) -> Tuple {
+        self.mesh().local_normal_at(point, hit)
+    }
+
+    fn local_contains(&self, point: Tuple) -> bool {
+        self.bounds.contains(point) && self.mesh().local_contains(point)
+    }
+
+    fn local_bounds(&self) -> Bounds {
+        self.bounds
+    }
+}
+
+#[cfg(test)]
+fn quad() -> Mesh {
+    let mut mesh = Mesh::create(
+        vec![
+            Tuple::point(-1., -1., 0.),
+            Tuple::point(1., -1., 0.),
+            Tuple::point(1., 1., 0.),
+            Tuple::point(-1., 1., 0.),
+        ],
+        vec![[0, 1, 2], [0, 2, 3]],
+    );
+    mesh.set_uvs(vec![(0., 0.), (1., 0.), (1., 1.), (0., 1.)]);
+    mesh
+}
+
+#[test]
+fn constant_displacement_moves_surface() {
+    // The quad faces -z, so it moves towards the ray
+    let displaced = Displaced::create(quad(), Box::new(|_, _, _| 0.5), 0.5, 1.);
+    let ray = Ray::create(Tuple::point(0.2, 0.35, -2.), Tuple::vector(0., 0., 1.));
+    let xs = displaced.intersect(&ray);
+
+    assert_eq!(1, xs.len());
+    assert!(approx_eq(1.5, xs[0].t));
+    assert_eq!(
+        Tuple::vector(0., 0., -1.),
+        displaced.normal_at(ray.position(xs[0].t), &xs[0])
+    );
+}
+
+#[test]
+fn procedural_bump_adds_geometry() {
+    let bump = |p: Tuple, _, _| 0.5 * (1. - p.x * p.x - p.y * p.y).max(0.);
+    let displaced = Displaced::create(quad(), Box::new(bump), 0.1, 0.5);
+
+    let center = Ray::create(Tuple::point(0.01, 0.02, -2.), Tuple::vector(0., 0., 1.));
+    let xs = displaced.intersect(&center);
+    assert!((xs[0].t - 1.5).abs() < 0.01);
+
+    // A ray grazing the bump from the side hits it, which a flat quad never would
+    let side = Ray::create(Tuple::point(-2., 0.05, -0.2), Tuple::vector(1., 0., 0.));
+    let xs = displaced.intersect(&side);
+    assert_eq!(2, xs.len());
+    let normal = displaced.normal_at(side.position(xs[0].t), &xs[0]);
+    assert!(normal.x < 0. && normal.z < 0.);
+}
+
+#[test]
+fn base_transform_carries_over() {
+    let mut base = quad();
+    base.set_transform(translation(10., 0., 0.));
+    let displaced = Displaced::create(base, Box::new(|_, _, _| 0.5), 0.5, 1.);
+
+    let ray = Ray::create(Tuple::point(10.2, 0.35, -2.), Tuple::vector(0., 0., 1.));
+    let xs = displaced.intersect(&ray);
+    assert_eq!(1, xs.len());
+    assert!(approx_eq(1.5, xs[0].t));
+}
+
+#[test]
+fn bounds_grow_by_max_displacement() {
+    let displaced = Displaced::create(quad(), Box::new(|_, _, _| 10.), 0.5, 0.25);
+
+    assert_eq!(Tuple::point(-1.25, -1.25, -0.25), displaced.bounds().min);
+    assert_eq!(Tuple::point(1.25, 1.25, 0.25), displaced.bounds().max);
+    // Displacements are clamped, so the surface stays inside
+    for point in displaced.mesh().positions().iter() {
+        assert!(approx_eq(-0.25, point.z));
+    }
+}
+
+#[test]
+fn mesh_is_built_on_first_hit() {
+    let displaced = Displaced::create(quad(), Box::new(|_, _, _| 0.1), 0.5, 0.1);
+    let miss = Ray::create(Tuple::point(5., 5., -2.), Tuple::vector(0., 0., 1.));
+
+    assert!(displaced.intersect(&miss).is_empty());
+    assert!(displaced.displaced.get().is_none());
+
+    let hit = Ray::create(Tuple::point(0.1, 0.35, -2.), Tuple::vector(0., 0., 1.));
+    assert_eq!(1, displaced.intersect(&hit).len());
+    assert!(displaced.displaced.get().is_some());
+}
+
+#[test]
+fn displacement_from_texture() {
+    let mut canvas = Canvas::create(2, 2);
+    canvas.write(1, 0, Color::create(1., 1., 1.));
+    canvas.write(1, 1, Color::create(1., 1., 1.));
+    let texture = texture(canvas, 0.5);
+    let origin = Tuple::point(0., 0., 0.);
+
+    assert_eq!(0., texture(origin, 0., 0.5));
+    assert_eq!(0.5, texture(origin, 1., 0.5));
+    assert_eq!(0.25, texture(origin, 0.5, 0.));
+}
+
+#[test]
+fn creased_cube_stays_closed() {
+    let mut cube = Mesh::from_polygons(
+        vec![
+            Tuple::point(-1., -1., -1.),
+            Tuple::point(1., -1., -1.),
+            Tuple::point(1., 1., -1.),
+            Tuple::point(-1., 1., -1.),
+            Tuple::point(-1., -1., 1.),
+            Tuple::point(1., -1., 1.),
+            Tuple::point(1., 1., 1.),
+            Tuple::point(-1., 1., 1.),
+        ],
+        vec![
+            vec![0, 1, 2, 3],
+            vec![4, 7, 6, 5],
+            vec![0, 4, 5, 1],
+            vec![2, 6, 7, 3],
+            vec![0, 3, 7, 4],
+            vec![1, 5, 6, 2],
+        ],
+    );
+    cube.smooth_normals_with_creases(&[
+        [0, 1],
+        [1, 2],
+        [2, 3],
+        [3, 0],
+        [4, 5],
+        [5, 6],
+        [6, 7],
+        [7, 4],
+        [0, 4],
+        [1, 5],
+        [2, 6],
+        [3, 7],
+    ]);
+    let displaced = Displaced::create(cube, Box::new(|_, _, _| 0.2), 0.5, 0.2);
+    let mesh = displaced.mesh();
+
+    // Every edge, matched by position, still joins exactly two triangles
+    let mut edges = HashMap::new();
+    for triangle in mesh.triangles() {
+        for i in 0..3 {
+            let a = position_key(mesh.positions()[triangle[i] as usize]);
+            let b = position_key(mesh.positions()[triangle[(i + 1) % 3] as usize]);
+            *edges.entry((a.min(b), a.max(b))).or_insert(0) += 1;
+        }
+    }
+    assert!(edges.values().all(|&count| count == 2));
+
+    // Corners move out along the diagonal, while their copies still shade each
+    // face on its own
+    let corner = 1. + 0.2 / 3_f64.sqrt();
+    let copies: Vec<usize> = (0..mesh.positions().len())
+        .filter(|&index| mesh.positions()[index] == Tuple::point(corner, corner, corner))
+        .collect();
+    assert_eq!(3, copies.len());
+    assert_ne!(mesh.normals()[copies[0]], mesh.normals()[copies[1]]);
+    assert_ne!(mesh.normals()[copies[1]], mesh.normals()[copies[2]]);
+    assert_ne!(mesh.normals()[copies[0]], mesh.normals()[copies[2]]);
+
+    let ray = Ray::create(Tuple::point(0.1, 0.2, -5.), Tuple::vector(0., 0., 1.));
+    let xs = displaced.intersect(&ray);
+    assert!(approx_eq(3.8, xs[0].t));
+}
@@ -0,0 +1,231 @@
+//! Signed distance functions to build `Sdf` shapes from. Negative values are inside
+//! the surface. Primitives are centered at the origin.
+
+use crate::math::tuple::Tuple;
+#[cfg(test)]
+use ray_tracer::approx_eq;
+
+pub type DistanceFunction = Box<dyn Fn(Tuple) -> f64>;
+
+fn length(x: f64, y: f64, z: f64) -> f64 {
+    (x * x + y * y + z * z).sqrt()
+}
+
+pub fn sphere(radius: f64) -> DistanceFunction {
+    Box::new(move |p| length(p.x, p.y, p.z) - radius)
+}
+
+/// Axis aligned box, `half_extents` being the distance from the center to each face.
+pub fn cuboid(half_extents: Tuple) -> DistanceFunction {
+    Box::new(move |p| {
+        let qx = p.x.abs() - half_extents.x;
+        let qy = p.y.abs() - half_extents.y;
+        let qz = p.z.abs() - half_extents.z;
+
+        length(qx.max(0.), qy.max(0.), qz.max(0.)) + qx.max(qy).max(qz).min(0.)
+    })
+}
+
+/// Box with its edges rounded off by `radius`, keeping the same outer extents.
+pub fn round_cuboid(half_extents: Tuple, radius: f64) -> DistanceFunction {
+    let inner = cuboid(half_extents - Tuple::vector(radius, radius, radius));
+    Box::new(move |p| inner(p) - radius)
+}
+
+/// Segment from `a` to `b` swept by a sphere of `radius`.
+pub fn capsule(a: Tuple, b: Tuple, radius: f64) -> DistanceFunction {
+    Box::new(move |p| {
+        let pa = p - a;
+        let ba = b - a;
+        let h = (pa.dot(ba) / ba.dot(ba)).clamp(0., 1.);
+        let d = pa - ba * h;
+
+        length(d.x, d.y, d.z) - radius
+    })
+}
+
+/// Torus lying in the xz plane, like the analytic `Torus`.
+pub fn torus(major_radius: f64, minor_radius: f64) -> DistanceFunction {
+    Box::new(move |p| {
+        let ring = (p.x * p.x + p.z * p.z).sqrt() - major_radius;
+        (ring * ring + p.y * p.y).sqrt() - minor_radius
+    })
+}
+
+/// Distance estimate for the Mandelbulb fractal. It is only a lower bound, which is
+/// all sphere tracing needs. The set fits inside a sphere of radius 1.2.
+pub fn mandelbulb(power: f64, iterations: usize) -> DistanceFunction {
+    Box::new(move |p| {
+        let (mut x, mut y, mut z) = (p.x, p.y, p.z);
+        let mut dr = 1.;
+        let mut r = length(x, y, z);
+
+        for _ in 0..iterations {
+            if r > 2. || r == 0. {
+                break;
+            }
+
+            let theta = (z / r).acos() * power;
+            let phi = y.atan2(x) * power;
+            dr = r.powf(power - 1.) * power * dr + 1.;
+
+            let zr = r.powf(power);
+            x = zr * theta.sin() * phi.cos() + p.x;
+            y = zr * theta.sin() * phi.sin() + p.y;
+            z = zr * theta.cos() + p.z;
+            r = length(x, y, z);
+        }
+
+        if r == 0. {
+            return 0.;
+        }
+        0.5 * r.ln() * r / dr
+    })
+}
+
+pub fn union(a: DistanceFunction, b: DistanceFunction) -> DistanceFunction {
+    Box::new(move |p| a(p).min(b(p)))
+}
+
+/// Carves `b` out of `a`.
+pub fn subtract(a: DistanceFunction, b: DistanceFunction) -> DistanceFunction {
+    Box::new(move |p| a(p).max(-b(p)))
+}
+
+/// Union that blends the two surfaces together wherever they come within
+/// `smoothness` of each other, using the polynomial smooth minimum.
+pub fn smooth_union(a: DistanceFunction, b: DistanceFunction, smoothness: f64) -> DistanceFunction {
+    Box::new(move |p| {
+        let (da, db) = (a(p), b(p));
+        let h = (0.5 + 0.5 * (db - da) / smoothness).clamp(0., 1.);
+
+        db + (da - db) * h - smoothness * h * (1. - h)
+    })
+}
+
+/// Repeats the shape infinitely with the given spacing along each axis. A spacing
+/// of zero leaves that axis alone.
+pub fn repeat(shape: DistanceFunction, spacing: Tuple) -> DistanceFunction {
+    let wrap = |value: f64, spacing: f64| {
+        if spacing == 0. {
+            value
+        } else {
+            value - spacing * (value / spacing).round()
+        }
+    };
+
+    Box::new(move |p| {
+        shape(Tuple::point(
+            wrap(p.x, spacing.x),
+            wrap(p.y, spacing.y),
+            wrap(p.z, spacing.z),
+        ))
+    })
+}
+
+/// Twists the shape around the y axis by `rate` radians per unit of height. This
+/// stretches distances, so the result overestimates them. For a shape reaching out
+/// to `r` from the axis, set the `Sdf`'s `step_scale` to `1 / sqrt(1 + (rate·r)²)`.
+pub fn twist(shape: DistanceFunction, rate: f64) -> DistanceFunction {
+    Box::new(move |p| {
+        let (sin, cos) = (rate * p.y).sin_cos();
+        shape(Tuple::point(
+            cos * p.x - sin * p.z,
+            p.y,
+            sin * p.x + cos * p.z,
+        ))
+    })
+}
+
+#[test]
+fn distance_to_sphere() {
+    let sphere = sphere(1.);
+
+    assert_eq!(1., sphere(Tuple::point(0., 2., 0.)));
+    assert_eq!(-1., sphere(Tuple::point(0., 0., 0.)));
+}
+
+#[test]
+fn distance_to_cuboid() {
+    let cuboid = cuboid(Tuple::vector(1., 2., 3.));
+
+    assert_eq!(1., cuboid(Tuple::point(2., 0., 0.)));
+    assert_eq!(-1., cuboid(Tuple::point(0., 0., 0.)));
+    assert_eq!(f64::sqrt(2.), cuboid(Tuple::point(2., 3., 0.)));
+}
+
+#[test]
+fn distance_to_round_cuboid() {
+    let round = round_cuboid(Tuple::vector(1., 1., 1.), 0.25);
+
+    assert!(approx_eq(1., round(Tuple::point(2., 0., 0.))));
+    // The corner is cut off, so it is farther than the sharp box's corner
+    assert!(round(Tuple::point(1., 1., 1.)) > 0.);
+}
+
+#[test]
+fn distance_to_capsule() {
+    let capsule = capsule(Tuple::point(0., -1., 0.), Tuple::point(0., 1., 0.), 0.5);
+
+    assert_eq!(0.5, capsule(Tuple::point(1., 0., 0.)));
+    assert_eq!(1.5, capsule(Tuple::point(0., 3., 0.)));
+    assert_eq!(-0.5, capsule(Tuple::point(0., 0.5, 0.)));
+}
+
+#[test]
+fn distance_to_torus() {
+    let torus = torus(1., 0.25);
+
+    assert_eq!(-0.25, torus(Tuple::point(1., 0., 0.)));
+    assert_eq!(0.75, torus(Tuple::point(0., 0., 0.)));
+    assert_eq!(0.25, torus(Tuple::point(0., 0.5, -1.)));
+}
+
+#[test]
+fn mandelbulb_is_bounded() {
+    let bulb = mandelbulb(8., 10);
+
+    assert!(bulb(Tuple::point(0., 0., 3.)) > 1.);
+    assert!(bulb(Tuple::point(0., 0., 0.)) <= 0.);
+}
+
+#[test]
+fn combining_distances() {
+    let p = Tuple::point(1.5, 0., 0.);
+
+    assert_eq!(0.5, union(sphere(1.), sphere(0.5))(p));
+    assert_eq!(-0.5, subtract(sphere(2.), sphere(1.))(p));
+    assert_eq!(
+        1.,
+        subtract(sphere(2.), sphere(1.))(Tuple::point(0., 0., 0.))
+    );
+    // The blend pulls the surface outwards between the two shapes
+    assert!(smooth_union(sphere(1.), sphere(1.), 0.5)(p) < 0.5);
+    // Far enough from the other shape it is a plain union again
+    assert_eq!(
+        2.,
+        smooth_union(sphere(1.), sphere(0.5), 0.25)(Tuple::point(3., 0., 0.))
+    );
+}
+
+#[test]
+fn repeating_distance() {
+    let spheres = repeat(sphere(0.5), Tuple::vector(4., 0., 0.));
+
+    assert_eq!(-0.5, spheres(Tuple::point(8., 0., 0.)));
+    assert_eq!(-0.5, spheres(Tuple::point(-4., 0., 0.)));
+    assert_eq!(1.5, spheres(Tuple::point(2., 0., 0.)));
+    assert_eq!(2.5, spheres(Tuple::point(0., 3., 0.)));
+}
+
+#[test]
+fn twisting_distance() {
+    let twisted = twist(
+        cuboid(Tuple::vector(2., 10., 0.5)),
+        std::f64::consts::FRAC_PI_2,
+    );
+
+    // At y = 1 the box has turned a quarter, so its long side lies along z
+    assert!(twisted(Tuple::point(0., 1., 1.5)) < 0.);
+    assert!(twisted(Tuple::point(0., 0., 1.5)) > 0.);
+}
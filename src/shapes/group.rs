@@ -0,0 +1,296 @@
+use crate::intersection::intersection::Intersection;
+use crate::material::material::Material;
+use crate::math::bounds::Bounds;
+use crate::math::matrix::{Matrix, IDENTITY_MATRIX};
+#[cfg(test)]
+use crate::math::transformation::{rotation_y, scaling, translation};
+use crate::math::tuple::Tuple;
+use crate::ray::ray::Ray;
+#[cfg(test)]
+use crate::shapes::cone::Cone;
+#[cfg(test)]
+use crate::shapes::quadric::Quadric;
+use crate::shapes::shape::Shape;
+#[cfg(test)]
+use crate::shapes::triangle::Triangle;
+#[cfg(test)]
+use std::f64::consts::PI;
+
+/// Collection of shapes that share the group's transform on top of their own.
+#[derive(Debug)]
+pub struct Group {
+    children: Vec<Box<dyn Shape>>,
+    transform: Matrix,
+    inverse: Matrix,
+    material: Material,
+}
+
+impl Group {
+    pub fn create() -> Group {
+        Group {
+            children: vec![],
+            transform: IDENTITY_MATRIX,
+            inverse: IDENTITY_MATRIX,
+            material: Material::create(),
+        }
+    }
+
+    pub fn add_child(&mut self, child: Box<dyn Shape>) {
+        self.children.push(child);
+    }
+
+    pub fn children(&self) -> &[Box<dyn Shape>] {
+        &self.children
+    }
+}
+
+impl Shape for Group {
+    fn transform(&self) -> &Matrix {
+        &self.transform
+    }
+
+    fn inverse_transform(&self) -> &Matrix {
+        &self.inverse
+    }
+
+    fn set_transform(&mut self, transform: Matrix) {
+        self.transform = transform;
+        self.inverse = transform.inverse();
+    }
+
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn set_material(&mut self, material: Material) {
+        self.material = material;
+    }
+
+    fn local_intersect(&self, ray: &Ray) -> Vec<Intersection<'_>> {
+        let mut xs = vec![];
+        for child in &self.children {
+            for mut intersection in child.intersect(ray) {
+                intersection.parents.push(self);
+                xs.push(intersection);
+            }
+        }
+        xs
+    }
+
+    fn local_normal_at(&self, _point: Tuple, _hit: &Intersection) -> Tuple {
+        panic!("Groups have no surface, the normal is taken from the child that was hit!")
+    }
+
+    fn local_contains(&self, point: Tuple) -> bool {
+        self.children.iter().any(|child| child.contains(point))
+    }
+
+    fn local_occludes(&self, ray: &Ray, max_t: f64) -> bool {
+        self.children.iter().any(|child| child.occludes(ray, max_t))
+    }
+
+    fn local_bounds(&self) -> Bounds {
+        self.children.iter().fold(Bounds::empty(), |bounds, child| {
+            bounds.merge(&child.parent_space_bounds())
+        })
+    }
+}
+
+#[cfg(test)]
+fn test_triangle() -> Triangle {
+    Triangle::create(
+        Tuple::point(0., 1., 0.),
+        Tuple::point(-1., 0., 0.),
+        Tuple::point(1., 0., 0.),
+    )
+}
+
+#[cfg(test)]
+fn hexagon_corner() -> Box<dyn Shape> {
+    let mut corner = Cone::create();
+    corner.minimum = -0.25;
+    corner.maximum = 0.25;
+    corner.closed = true;
+    corner.set_transform(translation(0., 0., -1.));
+    Box::new(corner)
+}
+
+#[cfg(test)]
+fn hexagon_edge() -> Box<dyn Shape> {
+    let mut edge = test_triangle();
+    edge.set_transform(translation(0., 0., -1.) * rotation_y(-PI / 6.) * scaling(0.5, 0.25, 1.));
+    Box::new(edge)
+}
+
+#[cfg(test)]
+fn hexagon() -> Group {
+    let mut hexagon = Group::create();
+    for n in 0..6 {
+        let mut side = Group::create();
+        side.add_child(hexagon_corner());
+        side.add_child(hexagon_edge());
+        side.set_transform(rotation_y(n as f64 * PI / 3.));
+        hexagon.add_child(Box::new(side));
+    }
+    hexagon
+}
+
+#[test]
+fn creating_group() {
+    let group = Group::create();
+
+    assert_eq!(IDENTITY_MATRIX, *group.transform());
+    assert!(group.children().is_empty());
+}
+
+#[test]
+fn intersect_ray_with_empty_group() {
+    let group = Group::create();
+    let ray = Ray::create(Tuple::point(0., 0., 0.), Tuple::vector(0., 0., 1.));
+
+    assert!(group.local_intersect(&ray).is_empty());
+}
+
+#[test]
+fn intersect_ray_with_nonempty_group() {
+    let mut group = Group::create();
+    group.add_child(Box::new(test_triangle()));
+    let mut behind = test_triangle();
+    behind.set_transform(translation(0., 0., -3.));
+    group.add_child(Box::new(behind));
+    let mut aside = test_triangle();
+    aside.set_transform(translation(5., 0., 0.));
+    group.add_child(Box::new(aside));
+
+    let ray = Ray::create(Tuple::point(0., 0.5, -5.), Tuple::vector(0., 0., 1.));
+    let xs = group.intersect(&ray);
+
+    assert_eq!(2, xs.len());
+    assert!(std::ptr::addr_eq(&*group.children()[1], xs[0].object));
+    assert!(std::ptr::addr_eq(&*group.children()[0], xs[1].object));
+    assert_eq!(1, xs[0].parents.len());
+}
+
+#[test]
+fn intersect_transformed_group() {
+    let mut group = Group::create();
+    group.set_transform(scaling(2., 2., 2.));
+    let mut child = test_triangle();
+    child.set_transform(translation(5., 0., 0.));
+    group.add_child(Box::new(child));
+
+    let ray = Ray::create(Tuple::point(10., 0.5, -10.), Tuple::vector(0., 0., 1.));
+
+    assert_eq!(1, group.intersect(&ray).len());
+}
+
+#[test]
+fn convert_point_from_world_to_object_space() {
+    let mut g1 = Group::create();
+    g1.set_transform(rotation_y(PI / 2.));
+    let mut g2 = Group::create();
+    g2.set_transform(scaling(2., 2., 2.));
+    let mut shape = Cone::create();
+    shape.set_transform(translation(5., 0., 0.));
+
+    let point = shape.world_to_object(&[&g2, &g1], Tuple::point(-2., 0., -10.), 0.);
+
+    assert_eq!(Tuple::point(0., 0., -1.), point);
+}
+
+#[test]
+fn convert_normal_from_object_to_world_space() {
+    let mut g1 = Group::create();
+    g1.set_transform(rotation_y(PI / 2.));
+    let mut g2 = Group::create();
+    g2.set_transform(scaling(1., 2., 3.));
+    let mut shape = Cone::create();
+    shape.set_transform(translation(5., 0., 0.));
+
+    let third = f64::sqrt(3.) / 3.;
+    let normal = shape.normal_to_world(&[&g2, &g1], Tuple::vector(third, third, third), 0.);
+
+    assert_eq!(Tuple::vector(0.28571, 0.42857, -0.85714), normal);
+}
+
+#[test]
+fn normal_on_nested_child_matches_combined_transform() {
+    let mut shape = Cone::create();
+    shape.set_transform(translation(5., 0., 0.));
+    let mut g2 = Group::create();
+    g2.set_transform(scaling(1., 2., 3.));
+    let mut g1 = Group::create();
+    g1.set_transform(rotation_y(PI / 2.));
+
+    let combined = *g1.transform() * *g2.transform() * *shape.transform();
+    let mut expected = combined.inverse().transpose() * Tuple::vector(1., -f64::sqrt(2.), 1.);
+    expected.w = 0.;
+
+    let point = combined * Tuple::point(1., 1., 1.);
+    let mut hit = Intersection::create(0., &shape);
+    hit.parents = vec![&g2, &g1];
+
+    assert_eq!(expected.normalize(), shape.normal_at(point, &hit));
+}
+
+#[test]
+fn hexagon_from_reused_sub_groups() {
+    let hexagon = hexagon();
+    let corner = rotation_y(PI / 3.) * Tuple::point(0., 0., -1.);
+    let ray = Ray::create(
+        corner + Tuple::vector(0., 5., 0.),
+        Tuple::vector(0., -1., 0.),
+    );
+
+    let xs = hexagon.intersect(&ray);
+    let hit = xs.hit().unwrap();
+
+    assert_eq!(2, hit.parents.len());
+    assert!(std::ptr::addr_eq(&*hexagon.children()[1], hit.parents[0]));
+    assert_eq!(4.75, hit.t);
+    assert_eq!(
+        Tuple::vector(0., 1., 0.),
+        hit.object.normal_at(ray.position(hit.t), hit)
+    );
+}
+
+#[test]
+fn point_inside_group() {
+    let mut sphere = Quadric::create([1., 1., 1., 0., 0., 0., 0., 0., 0., -1.]);
+    sphere.set_transform(translation(5., 0., 0.));
+    let mut group = Group::create();
+    group.set_transform(scaling(2., 2., 2.));
+    group.add_child(Box::new(sphere));
+
+    assert!(group.contains(Tuple::point(11.5, 0., 0.)));
+    assert!(!group.contains(Tuple::point(5.5, 0., 0.)));
+}
+
+#[test]
+fn group_is_occluding_through_any_child() {
+    let mut sphere = Quadric::create([1., 1., 1., 0., 0., 0., 0., 0., 0., -1.]);
+    sphere.set_transform(translation(5., 0., 0.));
+    let mut group = Group::create();
+    group.set_transform(scaling(2., 2., 2.));
+    group.add_child(Box::new(test_triangle()));
+    group.add_child(Box::new(sphere));
+
+    let ray = Ray::create(Tuple::point(10., 0., -5.), Tuple::vector(0., 0., 1.));
+    assert!(group.occludes(&ray, 4.));
+    assert!(!group.occludes(&ray, 3.));
+}
+
+#[test]
+fn bounds_of_group_hold_transformed_children() {
+    let mut sphere = Quadric::create([1., 1., 1., 0., 0., 0., 0., 0., 0., -1.]);
+    sphere.set_transform(translation(5., 0., 0.));
+    let mut group = Group::create();
+    group.add_child(Box::new(sphere));
+    group.add_child(Box::new(test_triangle()));
+    group.add_child(Box::new(Group::create()));
+    group.set_transform(scaling(2., 2., 2.));
+
+    let bounds = group.parent_space_bounds();
+    assert_eq!(Tuple::point(-2., -2., -2.), bounds.min);
+    assert_eq!(Tuple::point(12., 2., 2.), bounds.max);
+}
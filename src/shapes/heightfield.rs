@@ -0,0 +1,395 @@
+use crate::canvas::canvas::Canvas;
+#[cfg(test)]
+use crate::color::color::Color;
+use crate::intersection::intersection::Intersection;
+use crate::material::material::Material;
+use crate::math::bounds::Bounds;
+use crate::math::matrix::{Matrix, IDENTITY_MATRIX};
+use crate::math::tuple::Tuple;
+use crate::ray::ray::Ray;
+use crate::shapes::shape::Shape;
+use crate::shapes::triangle::moller_trumbore;
+#[cfg(test)]
+use ray_tracer::approx_eq;
+use ray_tracer::EPSILON;
+
+/// Terrain from a grid of elevations. The samples are spread evenly over the unit
+/// square `0 <= x, z <= 1`, columns along x and rows along z, with the elevation as y.
+/// Each cell between four samples is split into two triangles, which are only built
+/// on the fly for the cells a ray actually crosses.
+#[derive(Debug)]
+pub struct Heightfield {
+    heights: Vec<Vec<f64>>,
+    normals: Vec<Vec<Tuple>>,
+    min_height: f64,
+    max_height: f64,
+    transform: Matrix,
+    inverse: Matrix,
+    material: Material,
+}
+
+impl Heightfield {
+    /// Takes the elevations row by row, every row needs the same number of columns
+    /// and there have to be at least two of each.
+    pub fn create(heights: Vec<Vec<f64>>) -> Heightfield {
+        let rows = heights.len();
+        let columns = heights.first().map_or(0, |row| row.len());
+        if rows < 2 || columns < 2 || heights.iter().any(|row| row.len() != columns) {
+            panic!("Heightfield needs a rectangular grid of at least 2x2 samples!")
+        }
+
+        let all = heights.iter().flatten();
+        let min_height = all.clone().cloned().fold(f64::INFINITY, f64::min);
+        let max_height = all.cloned().fold(f64::NEG_INFINITY, f64::max);
+
+        let mut field = Heightfield {
+            heights,
+            normals: vec![],
+            min_height,
+            max_height,
+            transform: IDENTITY_MATRIX,
+            inverse: IDENTITY_MATRIX,
+            material: Material::create(),
+        };
+        field.normals = (0..rows)
+            .map(|row| {
+                (0..columns)
+                    .map(|column| field.sample_normal(row, column))
+                    .collect()
+            })
+            .collect();
+        field
+    }
+
+    /// Uses the brightness of every pixel as its elevation.
+    pub fn from_canvas(canvas: &Canvas) -> Heightfield {
+        Heightfield::create(
+            (0..canvas.height)
+                .map(|y| {
+                    (0..canvas.width)
+                        .map(|x| {
+                            let color = canvas.pixel_at(x, y);
+                            (color.red + color.green + color.blue) / 3.
+                        })
+                        .collect()
+                })
+                .collect(),
+        )
+    }
+
+    fn rows(&self) -> usize {
+        self.heights.len()
+    }
+
+    fn columns(&self) -> usize {
+        self.heights[0].len()
+    }
+
+    fn cell_width(&self) -> f64 {
+        1. / (self.columns() - 1) as f64
+    }
+
+    fn cell_depth(&self) -> f64 {
+        1. / (self.rows() - 1) as f64
+    }
+
+    fn vertex(&self, row: usize, column: usize) -> Tuple {
+        Tuple::point(
+            column as f64 * self.cell_width(),
+            self.heights[row][column],
+            row as f64 * self.cell_depth(),
+        )
+    }
+
+    /// Normal at a sample from the slope to its neighbours, one-sided at the borders.
+    fn sample_normal(&self, row: usize, column: usize) -> Tuple {
+        let (left, right) = (
+            column.saturating_sub(1),
+            (column + 1).min(self.columns() - 1),
+        );
+        let (back, front) = (row.saturating_sub(1), (row + 1).min(self.rows() - 1));
+
+        let dx = (self.heights[row][right] - self.heights[row][left])
+            / ((right - left) as f64 * self.cell_width());
+        let dz = (self.heights[front][column] - self.heights[back][column])
+            / ((front - back) as f64 * self.cell_depth());
+
+        Tuple::vector(-dx, 1., -dz).normalize()
+    }
+
+    /// Nearest hit in front of the ray origin within one cell, if any.
+    fn intersect_cell(&self, ray: &Ray, row: usize, column: usize) -> Option<f64> {
+        let p00 = self.vertex(row, column);
+        let p10 = self.vertex(row, column + 1);
+        let p01 = self.vertex(row + 1, column);
+        let p11 = self.vertex(row + 1, column + 1);
+
+        [(p00, p10, p11), (p00, p11, p01)]
+            .iter()
+            .filter_map(|(a, b, c)| moller_trumbore(ray, *a, *b - *a, *c - *a))
+            .map(|(t, _, _)| t)
+            .filter(|t| *t >= 0.)
+            .fold(None, |nearest: Option<f64>, t| {
+                Some(nearest.map_or(t, |n| n.min(t)))
+            })
+    }
+
+    /// Parametric range in front of the ray in which it is inside the bounding box
+    /// of the field.
+    fn bounds(&self, ray: &Ray) -> Option<(f64, f64)> {
+        match self.local_bounds().intersect(ray) {
+            Some((_, t_max)) if t_max < 0. => None,
+            Some((t_min, t_max)) => Some((t_min.max(0.), t_max)),
+            None => None,
+        }
+    }
+}
+
+impl Shape for Heightfield {
+    fn transform(&self) -> &Matrix {
+        &self.transform
+    }
+
+    fn inverse_transform(&self) -> &Matrix {
+        &self.inverse
+    }
+
+    fn set_transform(&mut self, transform: Matrix) {
+        self.transform = transform;
+        self.inverse = transform.inverse();
+    }
+
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn set_material(&mut self, material: Material) {
+        self.material = material;
+    }
+
+    /// Walks the cells under the ray front to back with a 2D DDA and stops at the
+    /// first cell that is hit, so only the nearest hit in front of the ray is reported.
+    fn local_intersect(&self, ray: &Ray) -> Vec<Intersection<'_>> {
+        let (t_start, t_end) = match self.bounds(ray) {
+            Some(bounds) => bounds,
+            None => return vec![],
+        };
+
+        let (width, depth) = (self.cell_width(), self.cell_depth());
+        let (columns, rows) = (self.columns() - 1, self.rows() - 1);
+        let start = ray.position(t_start);
+        let mut column = ((start.x / width).floor().max(0.) as usize).min(columns - 1);
+        let mut row = ((start.z / depth).floor().max(0.) as usize).min(rows - 1);
+
+        let step = |direction: f64, cell: usize, size: f64, origin: f64| {
+            if direction > 0. {
+                (
+                    ((cell + 1) as f64 * size - origin) / direction,
+                    size / direction,
+                )
+            } else if direction < 0. {
+                ((cell as f64 * size - origin) / direction, -size / direction)
+            } else {
+                (f64::INFINITY, f64::INFINITY)
+            }
+        };
+        let (mut next_x, delta_x) = step(ray.direction.x, column, width, ray.origin.x);
+        let (mut next_z, delta_z) = step(ray.direction.z, row, depth, ray.origin.z);
+
+        let mut t_cell = t_start;
+        loop {
+            let t_exit = next_x.min(next_z).min(t_end);
+
+            // Skip the triangle tests when the ray passes entirely above or below the cell.
+            let y_in = ray.origin.y + ray.direction.y * t_cell;
+            let y_out = ray.origin.y + ray.direction.y * t_exit;
+            let cell_heights = [
+                self.heights[row][column],
+                self.heights[row][column + 1],
+                self.heights[row + 1][column],
+                self.heights[row + 1][column + 1],
+            ];
+            let cell_min = cell_heights.iter().cloned().fold(f64::INFINITY, f64::min);
+            let cell_max = cell_heights
+                .iter()
+                .cloned()
+                .fold(f64::NEG_INFINITY, f64::max);
+
+            if y_in.min(y_out) <= cell_max + EPSILON && y_in.max(y_out) >= cell_min - EPSILON {
+                if let Some(t) = self.intersect_cell(ray, row, column) {
+                    let point = ray.position(t);
+                    return vec![Intersection::create_with_uv(t, self, point.x, point.z)];
+                }
+            }
+
+            if t_exit >= t_end {
+                return vec![];
+            }
+
+            if next_x < next_z {
+                if ray.direction.x > 0. && column + 1 < columns {
+                    column += 1;
+                } else if ray.direction.x < 0. && column > 0 {
+                    column -= 1;
+                } else {
+                    return vec![];
+                }
+                t_cell = next_x;
+                next_x += delta_x;
+            } else {
+                if ray.direction.z > 0. && row + 1 < rows {
+                    row += 1;
+                } else if ray.direction.z < 0. && row > 0 {
+                    row -= 1;
+                } else {
+                    return vec![];
+                }
+                t_cell = next_z;
+                next_z += delta_z;
+            }
+        }
+    }
+
+    /// Bilinear blend of the sample normals around the point.
+    fn local_normal_at(&self, point: Tuple, _hit: &Intersection) -> Tuple {
+        let x = (point.x / self.cell_width()).clamp(0., (self.columns() - 1) as f64);
+        let z = (point.z / self.cell_depth()).clamp(0., (self.rows() - 1) as f64);
+        let column = (x.floor() as usize).min(self.columns() - 2);
+        let row = (z.floor() as usize).min(self.rows() - 2);
+        let (fx, fz) = (x - column as f64, z - row as f64);
+
+        let back = self.normals[row][column] * (1. - fx) + self.normals[row][column + 1] * fx;
+        let front =
+            self.normals[row + 1][column] * (1. - fx) + self.normals[row + 1][column + 1] * fx;
+        back * (1. - fz) + front * fz
+    }
+
+    fn local_bounds(&self) -> Bounds {
+        Bounds::create(
+            Tuple::point(0., self.min_height, 0.),
+            Tuple::point(1., self.max_height, 1.),
+        )
+    }
+}
+
+#[cfg(test)]
+fn bumpy_heights(size: usize) -> Vec<Vec<f64>> {
+    (0..size)
+        .map(|row| {
+            (0..size)
+                .map(|column| {
+                    let (x, z) = (column as f64, row as f64);
+                    0.5 + 0.3 * (x * 1.3).sin() * (z * 0.7).cos()
+                })
+                .collect()
+        })
+        .collect()
+}
+
+#[test]
+fn ray_hits_flat_heightfield() {
+    let field = Heightfield::create(vec![vec![0.5; 4]; 4]);
+    let ray = Ray::create(Tuple::point(0.3, 5., 0.6), Tuple::vector(0., -1., 0.));
+    let xs = field.local_intersect(&ray);
+
+    assert_eq!(1, xs.len());
+    assert!(approx_eq(4.5, xs[0].t));
+    assert!(approx_eq(0.3, xs[0].u));
+    assert!(approx_eq(0.6, xs[0].v));
+    assert_eq!(
+        Tuple::vector(0., 1., 0.),
+        field.normal_at(ray.position(xs[0].t), &xs[0])
+    );
+}
+
+#[test]
+fn ray_misses_outside_footprint() {
+    let field = Heightfield::create(vec![vec![0.5; 4]; 4]);
+    let examples = [
+        Ray::create(Tuple::point(1.3, 5., 0.6), Tuple::vector(0., -1., 0.)),
+        Ray::create(Tuple::point(0.5, 0.8, -1.), Tuple::vector(0., 0., 1.)),
+        Ray::create(Tuple::point(0.5, 0.4, 0.5), Tuple::vector(0., -1., 0.)),
+    ];
+
+    for ray in examples.iter() {
+        assert!(field.local_intersect(ray).is_empty());
+    }
+}
+
+#[test]
+fn sloped_heightfield_normal() {
+    let field = Heightfield::create(vec![vec![0., 0.5, 1.]; 3]);
+    let ray = Ray::create(Tuple::point(0.25, 5., 0.5), Tuple::vector(0., -1., 0.));
+    let xs = field.local_intersect(&ray);
+
+    assert!(approx_eq(4.75, xs[0].t));
+    assert_eq!(
+        Tuple::vector(-1., 1., 0.).normalize(),
+        field.normal_at(ray.position(xs[0].t), &xs[0])
+    );
+}
+
+#[test]
+fn grazing_ray_hits_far_peak() {
+    let mut heights = vec![vec![0.; 5]; 5];
+    heights[2][3] = 1.;
+    let field = Heightfield::create(heights);
+    let ray = Ray::create(Tuple::point(-1., 0.5, 0.5), Tuple::vector(1., 0., 0.));
+    let xs = field.local_intersect(&ray);
+
+    assert_eq!(1, xs.len());
+    assert!(approx_eq(1.625, xs[0].t));
+}
+
+#[test]
+fn dda_finds_same_hit_as_testing_every_cell() {
+    let field = Heightfield::create(bumpy_heights(12));
+
+    for i in 0..40 {
+        let angle = i as f64 * 0.37;
+        let origin = Tuple::point(0.5 - 1.5 * angle.cos(), 1.2, 0.5 - 1.5 * angle.sin());
+        let target = Tuple::point(0.5 + 0.3 * (i as f64).sin(), 0.3, 0.5);
+        let ray = Ray::create(origin, (target - origin).normalize());
+
+        let expected = (0..11)
+            .flat_map(|row| (0..11).map(move |column| (row, column)))
+            .filter_map(|(row, column)| field.intersect_cell(&ray, row, column))
+            .fold(None, |nearest: Option<f64>, t| {
+                Some(nearest.map_or(t, |n| n.min(t)))
+            });
+        let actual = field.local_intersect(&ray).first().map(|i| i.t);
+
+        match (expected, actual) {
+            (Some(expected), Some(actual)) => assert!(approx_eq(expected, actual)),
+            (None, None) => {}
+            _ => panic!("{:?} != {:?} for ray {}", expected, actual, i),
+        }
+    }
+}
+
+#[test]
+fn smooth_normal_from_neighbouring_samples() {
+    let heights = (0..3)
+        .map(|_| (0..5).map(|c| (c as f64 * 0.25).powi(2)).collect())
+        .collect();
+    let field = Heightfield::create(heights);
+    let hit = Intersection::create(0., &field);
+
+    // y = x², central difference at x = 0.5 gives the exact slope of 1
+    assert_eq!(
+        Tuple::vector(-1., 1., 0.).normalize(),
+        field.normal_at(Tuple::point(0.5, 0.25, 0.5), &hit)
+    );
+}
+
+#[test]
+fn heightfield_from_canvas() {
+    let mut canvas = Canvas::create(3, 2);
+    canvas.write(1, 1, Color::create(1., 1., 1.));
+    canvas.write(2, 0, Color::create(0.3, 0.6, 0.9));
+    let field = Heightfield::create(vec![vec![0., 0., 0.6], vec![0., 1., 0.]]);
+    let from_canvas = Heightfield::from_canvas(&canvas);
+
+    assert_eq!(field.heights, from_canvas.heights);
+    assert_eq!(1., from_canvas.max_height);
+}
@@ -0,0 +1,201 @@
+#[cfg(test)]
+use crate::color::color::Color;
+use crate::intersection::intersection::Intersection;
+use crate::material::material::Material;
+use crate::math::bounds::Bounds;
+use crate::math::matrix::{Matrix, IDENTITY_MATRIX};
+#[cfg(test)]
+use crate::math::transformation::{scaling, translation};
+use crate::math::tuple::Tuple;
+use crate::ray::ray::Ray;
+#[cfg(test)]
+use crate::shapes::group::Group;
+#[cfg(test)]
+use crate::shapes::quadric::Quadric;
+use crate::shapes::shape::Shape;
+#[cfg(test)]
+use crate::shapes::triangle::Triangle;
+#[cfg(test)]
+use ray_tracer::approx_eq;
+use std::rc::Rc;
+
+/// Placement of a shared prototype shape with its own transform and, optionally, its
+/// own material. The prototype is reference counted, so every instance costs the
+/// same no matter how heavy the prototype is.
+#[derive(Debug)]
+pub struct Instance {
+    prototype: Rc<dyn Shape>,
+    material_override: Option<Material>,
+    transform: Matrix,
+    inverse: Matrix,
+}
+
+impl Instance {
+    pub fn create(prototype: Rc<dyn Shape>) -> Instance {
+        Instance {
+            prototype,
+            material_override: None,
+            transform: IDENTITY_MATRIX,
+            inverse: IDENTITY_MATRIX,
+        }
+    }
+
+    pub fn prototype(&self) -> &Rc<dyn Shape> {
+        &self.prototype
+    }
+}
+
+impl Shape for Instance {
+    fn transform(&self) -> &Matrix {
+        &self.transform
+    }
+
+    fn inverse_transform(&self) -> &Matrix {
+        &self.inverse
+    }
+
+    fn set_transform(&mut self, transform: Matrix) {
+        self.transform = transform;
+        self.inverse = transform.inverse();
+    }
+
+    /// The overriding material, or the prototype's own until one is set.
+    fn material(&self) -> &Material {
+        self.material_override
+            .as_ref()
+            .unwrap_or_else(|| self.prototype.material())
+    }
+
+    /// Shades everything in the prototype with this material instead of its own.
+    fn set_material(&mut self, material: Material) {
+        self.material_override = Some(material);
+    }
+
+    fn material_override(&self) -> Option<&Material> {
+        self.material_override.as_ref()
+    }
+
+    fn local_intersect(&self, ray: &Ray) -> Vec<Intersection<'_>> {
+        self.prototype
+            .intersect(ray)
+            .into_iter()
+            .map(|mut intersection| {
+                intersection.parents.push(self);
+                intersection
+            })
+            .collect()
+    }
+
+    fn local_normal_at(&self, _point: Tuple, _hit: &Intersection) -> Tuple {
+        panic!("Instances have no surface, the normal is taken from the prototype that was hit!")
+    }
+
+    fn local_contains(&self, point: Tuple) -> bool {
+        self.prototype.contains(point)
+    }
+
+    fn local_occludes(&self, ray: &Ray, max_t: f64) -> bool {
+        self.prototype.occludes(ray, max_t)
+    }
+
+    fn local_bounds(&self) -> Bounds {
+        self.prototype.parent_space_bounds()
+    }
+}
+
+#[cfg(test)]
+fn prototype() -> Rc<dyn Shape> {
+    let mut group = Group::create();
+    let mut triangle = Triangle::create(
+        Tuple::point(0., 1., 0.),
+        Tuple::point(-1., 0., 0.),
+        Tuple::point(1., 0., 0.),
+    );
+    let mut material = Material::create();
+    material.color = Color::create(0., 1., 0.);
+    triangle.set_material(material);
+    group.add_child(Box::new(triangle));
+    Rc::new(group)
+}
+
+#[test]
+fn instances_share_prototype() {
+    let prototype = prototype();
+    let instances: Vec<Instance> = (0..1000)
+        .map(|i| {
+            let mut instance = Instance::create(Rc::clone(&prototype));
+            instance.set_transform(translation(i as f64 * 3., 0., 0.));
+            instance
+        })
+        .collect();
+
+    assert_eq!(1001, Rc::strong_count(&prototype));
+    assert!(Rc::ptr_eq(&prototype, instances[999].prototype()));
+}
+
+#[test]
+fn intersect_transformed_instance() {
+    let prototype = prototype();
+    let mut instance = Instance::create(Rc::clone(&prototype));
+    instance.set_transform(translation(10., 0., 0.) * scaling(2., 2., 2.));
+
+    let ray = Ray::create(Tuple::point(0., 0.5, -5.), Tuple::vector(0., 0., 1.));
+    assert!(instance.intersect(&ray).is_empty());
+
+    let ray = Ray::create(Tuple::point(10., 0.5, -5.), Tuple::vector(0., 0., 1.));
+    let xs = instance.intersect(&ray);
+    assert_eq!(1, xs.len());
+    assert!(approx_eq(5., xs[0].t));
+    assert_eq!(2, xs[0].parents.len());
+    assert_eq!(
+        Tuple::vector(0., 0., -1.),
+        xs[0].object.normal_at(ray.position(xs[0].t), &xs[0])
+    );
+}
+
+#[test]
+fn instance_material_override() {
+    let prototype = prototype();
+    let plain = Instance::create(Rc::clone(&prototype));
+    let mut red = Instance::create(Rc::clone(&prototype));
+    let mut material = Material::create();
+    material.color = Color::create(1., 0., 0.);
+    red.set_material(material);
+
+    let ray = Ray::create(Tuple::point(0., 0.5, -5.), Tuple::vector(0., 0., 1.));
+    let plain_xs = plain.intersect(&ray);
+    let red_xs = red.intersect(&ray);
+
+    assert_eq!(Color::create(0., 1., 0.), plain_xs[0].material().color);
+    assert_eq!(Color::create(1., 0., 0.), red_xs[0].material().color);
+    assert!(std::ptr::addr_eq(plain_xs[0].object, red_xs[0].object));
+    assert_eq!(prototype.material(), plain.material());
+    assert_eq!(Color::create(1., 0., 0.), red.material().color);
+}
+
+#[test]
+fn outermost_override_wins() {
+    let mut inner = Instance::create(prototype());
+    let mut blue = Material::create();
+    blue.color = Color::create(0., 0., 1.);
+    inner.set_material(blue);
+
+    let mut outer = Instance::create(Rc::new(inner));
+    let ray = Ray::create(Tuple::point(0., 0.5, -5.), Tuple::vector(0., 0., 1.));
+    assert_eq!(blue, *outer.intersect(&ray)[0].material());
+
+    let mut red = Material::create();
+    red.color = Color::create(1., 0., 0.);
+    outer.set_material(red);
+    assert_eq!(red, *outer.intersect(&ray)[0].material());
+}
+
+#[test]
+fn point_inside_instance() {
+    let sphere: Rc<dyn Shape> = Rc::new(Quadric::create([1., 1., 1., 0., 0., 0., 0., 0., 0., -1.]));
+    let mut instance = Instance::create(sphere);
+    instance.set_transform(translation(10., 0., 0.));
+
+    assert!(instance.contains(Tuple::point(10.5, 0., 0.)));
+    assert!(!instance.contains(Tuple::point(0., 0., 0.)));
+}
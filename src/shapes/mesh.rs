@@ -0,0 +1,741 @@
+use crate::acceleration::accelerator::Accelerator;
+use crate::acceleration::bvh::Bvh;
+use crate::acceleration::linear_bvh::LinearBvh;
+use crate::intersection::intersection::Intersection;
+use crate::material::material::Material;
+use crate::math::bounds::Bounds;
+use crate::math::matrix::{Matrix, IDENTITY_MATRIX};
+use crate::math::tuple::Tuple;
+use crate::ray::ray::Ray;
+use crate::shapes::shape::Shape;
+use crate::shapes::triangle::moller_trumbore;
+#[cfg(test)]
+use ray_tracer::approx_eq;
+use ray_tracer::EPSILON;
+use std::collections::{HashMap, HashSet};
+
+/// Triangle mesh kept in flat buffers: one list of vertices and one of triangles
+/// indexing into it. `normals` and `uvs` are optional, but when given they need an
+/// entry per vertex. Without normals every triangle is shaded flat.
+#[derive(Debug)]
+pub struct Mesh {
+    positions: Vec<Tuple>,
+    normals: Vec<Tuple>,
+    uvs: Vec<(f64, f64)>,
+    triangles: Vec<[u32; 3]>,
+    /// Polygons the triangles were fanned out of, if the mesh was built from them.
+    polygons: Vec<Vec<u32>>,
+    bounds: Bounds,
+    /// Over the triangles by index. Nothing moves them once the mesh is built.
+    accelerator: LinearBvh,
+    transform: Matrix,
+    inverse: Matrix,
+    material: Material,
+}
+
+impl Mesh {
+    pub fn create(positions: Vec<Tuple>, triangles: Vec<[u32; 3]>) -> Mesh {
+        assert!(
+            triangles
+                .iter()
+                .flatten()
+                .all(|&index| (index as usize) < positions.len()),
+            "Triangles can only use vertices the mesh has!"
+        );
+        let bounds = Bounds::from_points(&positions);
+        let triangle_bounds: Vec<Bounds> = triangles
+            .iter()
+            .map(|triangle| {
+                Bounds::from_points(triangle.iter().map(|&index| &positions[index as usize]))
+            })
+            .collect();
+        let accelerator = Bvh::create(&triangle_bounds, 4).flatten();
+        Mesh {
+            positions,
+            normals: vec![],
+            uvs: vec![],
+            triangles,
+            polygons: vec![],
+            bounds,
+            accelerator,
+            transform: IDENTITY_MATRIX,
+            inverse: IDENTITY_MATRIX,
+            material: Material::create(),
+        }
+    }
+
+    /// Mesh of polygons with any number of sides, each fanned out into triangles from
+    /// its first vertex. The polygons are kept for `catmull_clark`.
+    pub fn from_polygons(positions: Vec<Tuple>, polygons: Vec<Vec<u32>>) -> Mesh {
+        let triangles = polygons
+            .iter()
+            .flat_map(|polygon| {
+                assert!(polygon.len() >= 3, "Polygons need at least three vertices!");
+                (1..polygon.len() - 1).map(move |i| [polygon[0], polygon[i], polygon[i + 1]])
+            })
+            .collect();
+
+        let mut mesh = Mesh::create(positions, triangles);
+        mesh.polygons = polygons;
+        mesh
+    }
+
+    pub fn bounds(&self) -> &Bounds {
+        &self.bounds
+    }
+
+    pub fn positions(&self) -> &[Tuple] {
+        &self.positions
+    }
+
+    pub fn normals(&self) -> &[Tuple] {
+        &self.normals
+    }
+
+    /// Vertex normals, one per position, or none to shade every triangle flat.
+    pub fn set_normals(&mut self, normals: Vec<Tuple>) {
+        assert!(normals.is_empty() || normals.len() == self.positions.len());
+        self.normals = normals;
+    }
+
+    pub fn uvs(&self) -> &[(f64, f64)] {
+        &self.uvs
+    }
+
+    /// Texture coordinates, one pair per position, or none.
+    pub fn set_uvs(&mut self, uvs: Vec<(f64, f64)>) {
+        assert!(uvs.is_empty() || uvs.len() == self.positions.len());
+        self.uvs = uvs;
+    }
+
+    pub fn triangles(&self) -> &[[u32; 3]] {
+        &self.triangles
+    }
+
+    /// The polygons the mesh was built from, or its triangles if it was not.
+    pub fn polygons(&self) -> Vec<Vec<u32>> {
+        if self.polygons.is_empty() {
+            self.triangles
+                .iter()
+                .map(|triangle| triangle.to_vec())
+                .collect()
+        } else {
+            self.polygons.clone()
+        }
+    }
+
+    /// Fills `normals` with the area weighted average of the faces around each vertex.
+    pub fn smooth_normals(&mut self) {
+        self.smooth_normals_with_creases(&[]);
+    }
+
+    /// Like `smooth_normals`, but faces only share normals across edges that are not
+    /// creases. A vertex on a crease is duplicated for each side of it, so that the
+    /// crease is shaded as sharp as it is.
+    pub fn smooth_normals_with_creases(&mut self, creases: &[[u32; 2]]) {
+        let creases: HashSet<(u32, u32)> =
+            creases.iter().map(|&[a, b]| (a.min(b), a.max(b))).collect();
+        let on_crease: HashSet<u32> = creases.iter().flat_map(|&(a, b)| [a, b]).collect();
+
+        // Corners, numbered triangle * 3 + slot, that share a normal are joined into
+        // one group. Around a vertex on a crease that takes an edge in between.
+        let mut groups: Vec<usize> = (0..self.triangles.len() * 3).collect();
+        let mut first_corners = HashMap::new();
+        let mut around_edges: HashMap<(u32, u32), Vec<usize>> = HashMap::new();
+        for (primitive, triangle) in self.triangles.iter().enumerate() {
+            for slot in 0..3 {
+                let (corner, vertex) = (primitive * 3 + slot, triangle[slot]);
+                if !on_crease.contains(&vertex) {
+                    let first = *first_corners.entry(vertex).or_insert(corner);
+                    join(&mut groups, first, corner);
+                    continue;
+                }
+                for other in [triangle[(slot + 1) % 3], triangle[(slot + 2) % 3]] {
+                    if !creases.contains(&(vertex.min(other), vertex.max(other))) {
+                        around_edges
+                            .entry((vertex, other))
+                            .or_default()
+                            .push(corner);
+                    }
+                }
+            }
+        }
+        for corners in around_edges.values() {
+            for &corner in &corners[1..] {
+                join(&mut groups, corners[0], corner);
+            }
+        }
+
+        let mut sums: HashMap<usize, Tuple> = HashMap::new();
+        for primitive in 0..self.triangles.len() {
+            let [p1, p2, p3] = self.vertices(primitive);
+            let face_normal = (p3 - p1).cross(p2 - p1);
+            for slot in 0..3 {
+                let group = find(&mut groups, primitive * 3 + slot);
+                let sum = sums.entry(group).or_insert(Tuple::vector(0., 0., 0.));
+                *sum = *sum + face_normal;
+            }
+        }
+
+        // The first group around a vertex keeps it, the others get a copy
+        let mut normals = vec![Tuple::vector(0., 0., 0.); self.positions.len()];
+        let mut claimed = vec![false; self.positions.len()];
+        let mut indices: HashMap<usize, u32> = HashMap::new();
+        for primitive in 0..self.triangles.len() {
+            for slot in 0..3 {
+                let group = find(&mut groups, primitive * 3 + slot);
+                let vertex = self.triangles[primitive][slot];
+                let index = *indices.entry(group).or_insert_with(|| {
+                    if !claimed[vertex as usize] {
+                        claimed[vertex as usize] = true;
+                        return vertex;
+                    }
+                    self.positions.push(self.positions[vertex as usize]);
+                    if !self.uvs.is_empty() {
+                        self.uvs.push(self.uvs[vertex as usize]);
+                    }
+                    normals.push(Tuple::vector(0., 0., 0.));
+                    (self.positions.len() - 1) as u32
+                });
+                self.triangles[primitive][slot] = index;
+                normals[index as usize] = sums[&group];
+            }
+        }
+
+        self.normals = normals
+            .into_iter()
+            .map(|normal| {
+                if normal.magnitude() == 0. {
+                    normal
+                } else {
+                    normal.normalize()
+                }
+            })
+            .collect();
+    }
+
+    /// Splits edges at their midpoints until none is longer than `max_edge_length`.
+    /// Both triangles on an edge split it the same way, so no cracks open up. Normals
+    /// and uvs are interpolated for the new vertices.
+    pub fn tessellate(&self, max_edge_length: f64) -> Mesh {
+        assert!(
+            max_edge_length > 0.,
+            "Edges can only be split down to a positive length!"
+        );
+        let mut positions = self.positions.clone();
+        let mut normals = self.normals.clone();
+        let mut uvs = self.uvs.clone();
+        let mut triangles = self.triangles.clone();
+
+        loop {
+            let mut midpoints = HashMap::new();
+            for triangle in triangles.iter() {
+                for i in 0..3 {
+                    let (a, b) = (triangle[i] as usize, triangle[(i + 1) % 3] as usize);
+                    let key = (a.min(b) as u32, a.max(b) as u32);
+                    let edge = positions[b] - positions[a];
+                    if edge.magnitude() <= max_edge_length || midpoints.contains_key(&key) {
+                        continue;
+                    }
+
+                    midpoints.insert(key, positions.len() as u32);
+                    positions.push((positions[a] + positions[b]) * 0.5);
+                    if !normals.is_empty() {
+                        let sum = normals[a] + normals[b];
+                        normals.push(if sum.magnitude() < EPSILON {
+                            // Opposite normals cancel out, the face gives a direction
+                            let [p1, p2, p3] = triangle.map(|index| positions[index as usize]);
+                            (p3 - p1).cross(p2 - p1).normalize()
+                        } else {
+                            sum.normalize()
+                        });
+                    }
+                    if !uvs.is_empty() {
+                        let ((u1, v1), (u2, v2)) = (uvs[a], uvs[b]);
+                        uvs.push(((u1 + u2) / 2., (v1 + v2) / 2.));
+                    }
+                }
+            }
+
+            if midpoints.is_empty() {
+                break;
+            }
+            triangles = triangles
+                .into_iter()
+                .flat_map(|triangle| split_triangle(triangle, &midpoints))
+                .collect();
+        }
+
+        let mut result = Mesh::create(positions, triangles);
+        result.set_normals(normals);
+        result.set_uvs(uvs);
+        result.transform = self.transform;
+        result.inverse = self.inverse;
+        result.material = self.material;
+        result
+    }
+
+    pub fn vertices(&self, primitive: usize) -> [Tuple; 3] {
+        let [a, b, c] = self.triangles[primitive];
+        [
+            self.positions[a as usize],
+            self.positions[b as usize],
+            self.positions[c as usize],
+        ]
+    }
+
+    /// Hit on a single triangle of the mesh, recording its index and barycentrics.
+    pub fn intersect_triangle(&self, ray: &Ray, primitive: usize) -> Option<Intersection<'_>> {
+        let [p1, p2, p3] = self.vertices(primitive);
+
+        moller_trumbore(ray, p1, p2 - p1, p3 - p1).map(|(t, u, v)| {
+            let mut intersection = Intersection::create_with_uv(t, self, u, v);
+            intersection.primitive = primitive;
+            intersection
+        })
+    }
+
+    /// Texture coordinates at a hit, interpolated from the vertex uvs. Meshes without
+    /// uvs give the barycentric coordinates within the triangle instead.
+    pub fn uv_at(&self, hit: &Intersection) -> (f64, f64) {
+        if self.uvs.is_empty() {
+            return (hit.u, hit.v);
+        }
+
+        let [a, b, c] = self.triangles[hit.primitive];
+        let (u1, v1) = self.uvs[a as usize];
+        let (u2, v2) = self.uvs[b as usize];
+        let (u3, v3) = self.uvs[c as usize];
+        let w = 1. - hit.u - hit.v;
+
+        (
+            u1 * w + u2 * hit.u + u3 * hit.v,
+            v1 * w + v2 * hit.u + v3 * hit.v,
+        )
+    }
+}
+
+/// Group a corner belongs to, in a union-find forest of corners.
+fn find(groups: &mut [usize], mut corner: usize) -> usize {
+    while groups[corner] != corner {
+        groups[corner] = groups[groups[corner]];
+        corner = groups[corner];
+    }
+    corner
+}
+
+fn join(groups: &mut [usize], a: usize, b: usize) {
+    let (a, b) = (find(groups, a), find(groups, b));
+    groups[a] = b;
+}
+
+/// Replaces a triangle by the pieces it falls into once some of its edges get a
+/// vertex in the middle, keeping the winding.
+fn split_triangle(triangle: [u32; 3], midpoints: &HashMap<(u32, u32), u32>) -> Vec<[u32; 3]> {
+    let split: Vec<Option<u32>> = (0..3)
+        .map(|i| {
+            let (a, b) = (triangle[i], triangle[(i + 1) % 3]);
+            midpoints.get(&(a.min(b), a.max(b))).copied()
+        })
+        .collect();
+    // Rotates the corners so that the edge at `start` comes first
+    let corner = |start: usize, offset: usize| triangle[(start + offset) % 3];
+
+    match split.iter().flatten().count() {
+        0 => vec![triangle],
+        1 => {
+            let start = split.iter().position(Option::is_some).unwrap();
+            let (a, b, c) = (corner(start, 0), corner(start, 1), corner(start, 2));
+            let ab = split[start].unwrap();
+            vec![[a, ab, c], [ab, b, c]]
+        }
+        2 => {
+            // The edge left whole runs from c back to a
+            let start = (split.iter().position(Option::is_none).unwrap() + 1) % 3;
+            let (a, b, c) = (corner(start, 0), corner(start, 1), corner(start, 2));
+            let (ab, bc) = (split[start].unwrap(), split[(start + 1) % 3].unwrap());
+            vec![[ab, b, bc], [a, ab, bc], [a, bc, c]]
+        }
+        _ => {
+            let [a, b, c] = triangle;
+            let (ab, bc, ca) = (split[0].unwrap(), split[1].unwrap(), split[2].unwrap());
+            vec![[a, ab, ca], [ab, b, bc], [ca, bc, c], [ab, bc, ca]]
+        }
+    }
+}
+
+impl Shape for Mesh {
+    fn transform(&self) -> &Matrix {
+        &self.transform
+    }
+
+    fn inverse_transform(&self) -> &Matrix {
+        &self.inverse
+    }
+
+    fn set_transform(&mut self, transform: Matrix) {
+        self.transform = transform;
+        self.inverse = transform.inverse();
+    }
+
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn set_material(&mut self, material: Material) {
+        self.material = material;
+    }
+
+    fn local_intersect(&self, ray: &Ray) -> Vec<Intersection<'_>> {
+        if self.bounds.intersect(ray).is_none() {
+            return vec![];
+        }
+
+        let mut hits = vec![];
+        self.accelerator.all_hits(ray, &mut |primitive| {
+            hits.extend(self.intersect_triangle(ray, primitive))
+        });
+        hits
+    }
+
+    fn local_occludes(&self, ray: &Ray, max_t: f64) -> bool {
+        if self.bounds.intersect(ray).is_none() {
+            return false;
+        }
+
+        self.accelerator.any_hit(ray, max_t, &mut |primitive| {
+            self.intersect_triangle(ray, primitive)
+                .is_some_and(|hit| hit.t >= 0. && hit.t < max_t)
+        })
+    }
+
+    fn local_normal_at(&self, _point: Tuple, hit: &Intersection) -> Tuple {
+        if self.normals.is_empty() {
+            let [p1, p2, p3] = self.vertices(hit.primitive);
+            return (p3 - p1).cross(p2 - p1).normalize();
+        }
+
+        let [a, b, c] = self.triangles[hit.primitive];
+        self.normals[b as usize] * hit.u
+            + self.normals[c as usize] * hit.v
+            + self.normals[a as usize] * (1. - hit.u - hit.v)
+    }
+
+    /// Counts how often rays from the point cross the surface, which is odd from
+    /// inside. That only means something for closed meshes. A few rays vote, in case
+    /// one runs exactly along an edge.
+    fn local_contains(&self, point: Tuple) -> bool {
+        if !self.bounds.contains(point) {
+            return false;
+        }
+
+        let directions = [
+            Tuple::vector(0.5774, 0.5771, 0.5777),
+            Tuple::vector(-0.6121, 0.3417, 0.7131),
+            Tuple::vector(0.2113, -0.8452, -0.4908),
+        ];
+        let odd = directions
+            .iter()
+            .filter(|&&direction| {
+                let ray = Ray::create(point, direction);
+                let crossings = self.local_intersect(&ray);
+                crossings.iter().filter(|hit| hit.t > 0.).count() % 2 == 1
+            })
+            .count();
+        odd >= 2
+    }
+
+    fn local_bounds(&self) -> Bounds {
+        self.bounds
+    }
+}
+
+#[cfg(test)]
+fn quad() -> Mesh {
+    Mesh::create(
+        vec![
+            Tuple::point(-1., -1., 0.),
+            Tuple::point(1., -1., 0.),
+            Tuple::point(1., 1., 0.),
+            Tuple::point(-1., 1., 0.),
+        ],
+        vec![[0, 1, 2], [0, 2, 3]],
+    )
+}
+
+#[test]
+fn mesh_bounds_cover_vertices() {
+    let mesh = quad();
+
+    assert_eq!(Tuple::point(-1., -1., 0.), mesh.bounds().min);
+    assert_eq!(Tuple::point(1., 1., 0.), mesh.bounds().max);
+}
+
+#[test]
+fn intersection_records_primitive_and_barycentrics() {
+    let mesh = quad();
+    let ray = Ray::create(Tuple::point(-0.5, 0.5, -2.), Tuple::vector(0., 0., 1.));
+    let xs = mesh.local_intersect(&ray);
+
+    assert_eq!(1, xs.len());
+    assert!(approx_eq(2., xs[0].t));
+    assert_eq!(1, xs[0].primitive);
+    assert!(approx_eq(0.25, xs[0].u));
+    assert!(approx_eq(0.5, xs[0].v));
+}
+
+#[test]
+fn ray_misses_mesh() {
+    let mesh = quad();
+    let ray = Ray::create(Tuple::point(1.5, 0., -2.), Tuple::vector(0., 0., 1.));
+
+    assert!(mesh.local_intersect(&ray).is_empty());
+}
+
+#[test]
+fn flat_normal_without_vertex_normals() {
+    let mesh = quad();
+    let ray = Ray::create(Tuple::point(0.5, -0.5, -2.), Tuple::vector(0., 0., 1.));
+    let xs = mesh.intersect(&ray);
+
+    assert_eq!(0, xs[0].primitive);
+    assert_eq!(
+        Tuple::vector(0., 0., -1.),
+        mesh.normal_at(ray.position(xs[0].t), &xs[0])
+    );
+}
+
+#[test]
+fn interpolated_normal_and_uv() {
+    let mut mesh = quad();
+    mesh.set_normals(vec![
+        Tuple::vector(-1., 0., -1.).normalize(),
+        Tuple::vector(1., 0., -1.).normalize(),
+        Tuple::vector(1., 0., -1.).normalize(),
+        Tuple::vector(-1., 0., -1.).normalize(),
+    ]);
+    mesh.set_uvs(vec![(0., 0.), (1., 0.), (1., 1.), (0., 1.)]);
+
+    let ray = Ray::create(Tuple::point(0., 0., -2.), Tuple::vector(0., 0., 1.));
+    let xs = mesh.intersect(&ray);
+    let hit = xs.hit().unwrap();
+
+    assert_eq!(
+        Tuple::vector(0., 0., -1.),
+        mesh.normal_at(ray.position(hit.t), hit)
+    );
+    let (u, v) = mesh.uv_at(hit);
+    assert!(approx_eq(0.5, u));
+    assert!(approx_eq(0.5, v));
+}
+
+#[test]
+fn uv_falls_back_to_barycentrics() {
+    let mesh = quad();
+    let ray = Ray::create(Tuple::point(-0.5, 0.5, -2.), Tuple::vector(0., 0., 1.));
+    let xs = mesh.local_intersect(&ray);
+
+    let (u, v) = mesh.uv_at(&xs[0]);
+    assert!(approx_eq(0.25, u));
+    assert!(approx_eq(0.5, v));
+}
+
+#[test]
+fn smoothing_normals_averages_faces() {
+    let mut mesh = Mesh::create(
+        vec![
+            Tuple::point(-1., 0., -1.),
+            Tuple::point(0., 0., 0.),
+            Tuple::point(0., 1., -1.),
+            Tuple::point(1., 0., -1.),
+        ],
+        vec![[0, 2, 1], [1, 2, 3]],
+    );
+    mesh.smooth_normals();
+
+    assert_eq!(4, mesh.normals.len());
+    assert_eq!(Tuple::vector(-1., 1., 1.).normalize(), mesh.normals[0]);
+    assert_eq!(Tuple::vector(0., 1., 1.).normalize(), mesh.normals[1]);
+}
+
+#[test]
+fn creases_split_vertex_normals() {
+    let mut mesh = Mesh::create(
+        vec![
+            Tuple::point(-1., 0., -1.),
+            Tuple::point(0., 0., 0.),
+            Tuple::point(0., 1., -1.),
+            Tuple::point(1., 0., -1.),
+        ],
+        vec![[0, 2, 1], [1, 2, 3]],
+    );
+    mesh.smooth_normals_with_creases(&[[2, 1]]);
+
+    // Both ends of the crease get a copy for the second face
+    assert_eq!(6, mesh.positions().len());
+    assert_eq!([0, 2, 1], mesh.triangles()[0]);
+    assert_eq!([4, 5, 3], mesh.triangles()[1]);
+    assert_eq!(mesh.positions()[1], mesh.positions()[4]);
+    assert_eq!(mesh.normals()[0], mesh.normals()[1]);
+    assert_eq!(mesh.normals()[3], mesh.normals()[4]);
+    assert_ne!(mesh.normals()[1], mesh.normals()[4]);
+}
+
+#[test]
+#[should_panic(expected = "Polygons need at least three vertices!")]
+fn polygons_need_three_vertices() {
+    Mesh::from_polygons(
+        vec![Tuple::point(0., 0., 0.), Tuple::point(1., 0., 0.)],
+        vec![vec![0, 1]],
+    );
+}
+
+#[test]
+#[should_panic(expected = "Triangles can only use vertices the mesh has!")]
+fn triangles_need_existing_vertices() {
+    Mesh::create(
+        vec![Tuple::point(0., 0., 0.), Tuple::point(1., 0., 0.)],
+        vec![[0, 1, 2]],
+    );
+}
+
+#[test]
+fn tessellating_bounds_edge_length() {
+    let mut mesh = quad();
+    mesh.set_uvs(vec![(0., 0.), (1., 0.), (1., 1.), (0., 1.)]);
+    let fine = mesh.tessellate(0.3);
+
+    assert!(fine.triangles.len() > 2);
+    assert_eq!(fine.positions.len(), fine.uvs.len());
+    let mut edges = HashMap::new();
+    for primitive in 0..fine.triangles.len() {
+        let triangle = fine.triangles[primitive];
+        for i in 0..3 {
+            let (a, b) = (triangle[i], triangle[(i + 1) % 3]);
+            let edge = fine.positions[b as usize] - fine.positions[a as usize];
+            assert!(edge.magnitude() <= 0.3);
+            *edges.entry((a.min(b), a.max(b))).or_insert(0) += 1;
+        }
+    }
+
+    // Every edge used by a single triangle is on the border of the quad, so no
+    // cracks opened up between triangles split differently
+    for ((a, b), count) in edges {
+        if count == 1 {
+            let (p, q) = (fine.positions[a as usize], fine.positions[b as usize]);
+            let on_border = |c1: f64, c2: f64| approx_eq(c1.abs(), 1.) && approx_eq(c1, c2);
+            assert!(on_border(p.x, q.x) || on_border(p.y, q.y));
+        }
+    }
+
+    let index = fine
+        .positions
+        .iter()
+        .position(|&p| p == Tuple::point(0., 0., 0.))
+        .unwrap();
+    let (u, v) = fine.uvs[index];
+    assert!(approx_eq(0.5, u) && approx_eq(0.5, v));
+}
+
+#[test]
+fn opposite_normals_split_to_face_normal() {
+    let mut mesh = Mesh::create(
+        vec![
+            Tuple::point(0., 0., 0.),
+            Tuple::point(1., 0., 0.),
+            Tuple::point(0., 0.5, 0.),
+        ],
+        vec![[0, 1, 2]],
+    );
+    mesh.set_normals(vec![
+        Tuple::vector(0., 0., -1.),
+        Tuple::vector(0., 0., 1.),
+        Tuple::vector(0., 0., -1.),
+    ]);
+    let fine = mesh.tessellate(1.05);
+
+    // Only the long edge is split, between the two opposite normals
+    assert_eq!(4, fine.positions().len());
+    assert_eq!(Tuple::vector(0., 0., -1.), fine.normals()[3]);
+}
+
+#[test]
+#[should_panic(expected = "Edges can only be split down to a positive length!")]
+fn tessellating_needs_positive_edge_length() {
+    quad().tessellate(f64::NAN);
+}
+
+#[test]
+fn large_mesh_shares_vertices() {
+    // A grid of n × n quads stores (n + 1)² vertices instead of 6n² corners.
+    let n = 100;
+    let positions = (0..=n)
+        .flat_map(|row| (0..=n).map(move |column| Tuple::point(column as f64, row as f64, 0.)))
+        .collect();
+    let triangles = (0..n)
+        .flat_map(|row| {
+            (0..n).flat_map(move |column| {
+                let corner = (row * (n + 1) + column) as u32;
+                let above = corner + (n + 1) as u32;
+                vec![[corner, corner + 1, above + 1], [corner, above + 1, above]]
+            })
+        })
+        .collect();
+    let mesh = Mesh::create(positions, triangles);
+
+    assert_eq!(101 * 101, mesh.positions.len());
+    assert_eq!(2 * 100 * 100, mesh.triangles.len());
+
+    let ray = Ray::create(Tuple::point(42.25, 17.75, -1.), Tuple::vector(0., 0., 1.));
+    let xs = mesh.local_intersect(&ray);
+    assert_eq!(1, xs.len());
+    assert_eq!(2 * (17 * 100 + 42) + 1, xs[0].primitive);
+}
+
+#[test]
+fn point_inside_closed_mesh() {
+    let tetrahedron = Mesh::create(
+        vec![
+            Tuple::point(1., 1., 1.),
+            Tuple::point(1., -1., -1.),
+            Tuple::point(-1., 1., -1.),
+            Tuple::point(-1., -1., 1.),
+        ],
+        vec![[0, 1, 2], [0, 3, 1], [0, 2, 3], [1, 3, 2]],
+    );
+
+    assert!(tetrahedron.contains(Tuple::point(0., 0., 0.)));
+    assert!(tetrahedron.contains(Tuple::point(0.5, 0.5, 0.4)));
+    // Inside the bounding box, but past one of the faces
+    assert!(!tetrahedron.contains(Tuple::point(0.9, -0.9, 0.9)));
+    assert!(!tetrahedron.contains(Tuple::point(3., 0., 0.)));
+}
+
+#[test]
+fn hits_behind_ray_are_kept() {
+    let mesh = quad();
+    let ray = Ray::create(Tuple::point(0.25, 0.5, 2.), Tuple::vector(0., 0., 1.));
+
+    let xs = mesh.local_intersect(&ray);
+    assert_eq!(1, xs.len());
+    assert!(approx_eq(-2., xs[0].t));
+    assert!(!mesh.local_occludes(&ray, f64::INFINITY));
+}
+
+#[test]
+fn hits_around_origin_found_once() {
+    let tetrahedron = Mesh::create(
+        vec![
+            Tuple::point(1., 1., 1.),
+            Tuple::point(1., -1., -1.),
+            Tuple::point(-1., 1., -1.),
+            Tuple::point(-1., -1., 1.),
+        ],
+        vec![[0, 1, 2], [0, 3, 1], [0, 2, 3], [1, 3, 2]],
+    );
+    let ray = Ray::create(Tuple::point(0., 0., 0.), Tuple::vector(0.2, 0.1, 1.));
+
+    let xs = tetrahedron.local_intersect(&ray);
+    assert_eq!(2, xs.len());
+    assert_eq!(1, xs.iter().filter(|hit| hit.t < 0.).count());
+}
@@ -0,0 +1,22 @@
+pub mod animated;
+pub mod blob;
+pub mod cone;
+pub mod csg;
+pub mod curve;
+pub mod disk;
+pub mod displaced;
+pub mod distance;
+pub mod group;
+pub mod heightfield;
+pub mod instance;
+pub mod mesh;
+pub mod parametric;
+pub mod quadric;
+pub mod rectangle;
+pub mod sdf;
+pub mod shape;
+pub mod smooth_triangle;
+pub mod subdivision;
+pub mod sweep;
+pub mod torus;
+pub mod triangle;
@@ -0,0 +1,196 @@
+//! Triangle meshes from parametric surfaces, functions mapping `(u, v)` in the unit
+//! square to points. Normals come from the partial derivatives of the surface rather
+//! than the triangles, so seams and non-orientable surfaces shade correctly.
+
+use crate::math::tuple::Tuple;
+#[cfg(test)]
+use crate::ray::ray::Ray;
+use crate::shapes::mesh::Mesh;
+#[cfg(test)]
+use crate::shapes::shape::Shape;
+#[cfg(test)]
+use ray_tracer::approx_eq;
+use std::f64::consts::{FRAC_PI_2, PI, TAU};
+
+pub type SurfaceFunction = Box<dyn Fn(f64, f64) -> Tuple>;
+
+/// Normal from the partial derivatives, taken by central differences. The surface is
+/// sampled a little outside the unit square at its edges.
+fn derivative_normal(surface: &dyn Fn(f64, f64) -> Tuple, u: f64, v: f64) -> Tuple {
+    let h = 1e-5;
+    let du = surface(u + h, v) - surface(u - h, v);
+    let dv = surface(u, v + h) - surface(u, v - h);
+    dv.cross(du)
+}
+
+/// Samples the surface on a `u_steps` by `v_steps` grid and triangulates it, keeping
+/// `(u, v)` as each vertex's uv. Where the derivatives vanish, as at the poles of a
+/// sphere, the normal is taken a short way into the square instead.
+pub fn tessellate(surface: &dyn Fn(f64, f64) -> Tuple, u_steps: usize, v_steps: usize) -> Mesh {
+    let mut positions = vec![];
+    let mut normals = vec![];
+    let mut uvs = vec![];
+
+    for j in 0..=v_steps {
+        for i in 0..=u_steps {
+            let (u, v) = (i as f64 / u_steps as f64, j as f64 / v_steps as f64);
+            let mut normal = derivative_normal(surface, u, v);
+            if normal.magnitude() < 1e-12 {
+                let nudge = |w: f64| w + (0.5 - w) * 1e-3;
+                normal = derivative_normal(surface, nudge(u), nudge(v));
+            }
+
+            positions.push(surface(u, v));
+            normals.push(normal.normalize());
+            uvs.push((u, v));
+        }
+    }
+
+    let index = |i: usize, j: usize| (j * (u_steps + 1) + i) as u32;
+    let triangles = (0..v_steps)
+        .flat_map(|j| {
+            (0..u_steps).flat_map(move |i| {
+                let (a, b) = (index(i, j), index(i + 1, j));
+                let (c, d) = (index(i + 1, j + 1), index(i, j + 1));
+                [[a, b, c], [a, c, d]]
+            })
+        })
+        .collect();
+
+    let mut mesh = Mesh::create(positions, triangles);
+    mesh.set_normals(normals);
+    mesh.set_uvs(uvs);
+    mesh
+}
+
+/// Torus around the y axis, `u` going around the axis and `v` around the tube.
+pub fn torus(major_radius: f64, minor_radius: f64) -> SurfaceFunction {
+    Box::new(move |u, v| {
+        let (around, tube) = (u * TAU, v * TAU);
+        let distance = major_radius + minor_radius * tube.cos();
+        Tuple::point(
+            distance * around.cos(),
+            minor_radius * tube.sin(),
+            distance * around.sin(),
+        )
+    })
+}
+
+/// Möbius strip around the y axis, `v` running across its width.
+pub fn mobius_strip(radius: f64, width: f64) -> SurfaceFunction {
+    Box::new(move |u, v| {
+        let angle = u * TAU;
+        let across = (v - 0.5) * width;
+        let distance = radius + across * (angle / 2.).cos();
+        Tuple::point(
+            distance * angle.cos(),
+            across * (angle / 2.).sin(),
+            distance * angle.sin(),
+        )
+    })
+}
+
+/// The figure eight immersion of the Klein bottle, which passes through itself.
+pub fn klein_bottle(radius: f64) -> SurfaceFunction {
+    Box::new(move |u, v| {
+        let (angle, tube) = (u * TAU, v * TAU);
+        let (sin_half, cos_half) = (angle / 2.).sin_cos();
+        let distance = radius + cos_half * tube.sin() - sin_half * (2. * tube).sin();
+        Tuple::point(
+            distance * angle.cos(),
+            sin_half * tube.sin() + cos_half * (2. * tube).sin(),
+            distance * angle.sin(),
+        )
+    })
+}
+
+/// Superellipsoid with unit radii. `vertical` shapes the profile from pole to pole
+/// and `horizontal` the cross section; both at 1 give a sphere, towards 0 a cube and
+/// at 2 an octahedron.
+pub fn superquadric(vertical: f64, horizontal: f64) -> SurfaceFunction {
+    let power = |value: f64, exponent: f64| value.signum() * value.abs().powf(exponent);
+
+    Box::new(move |u, v| {
+        let longitude = u * TAU - PI;
+        let latitude = v * PI - FRAC_PI_2;
+        let ring = power(latitude.cos(), vertical);
+        Tuple::point(
+            ring * power(longitude.cos(), horizontal),
+            power(latitude.sin(), vertical),
+            ring * power(longitude.sin(), horizontal),
+        )
+    })
+}
+
+#[test]
+fn tessellated_grid() {
+    let mesh = tessellate(&torus(1., 0.25), 8, 4);
+
+    assert_eq!(9 * 5, mesh.positions().len());
+    assert_eq!(9 * 5, mesh.normals().len());
+    assert_eq!(9 * 5, mesh.uvs().len());
+    assert_eq!(2 * 8 * 4, mesh.triangles().len());
+    assert_eq!((0.5, 0.25), mesh.uvs()[9 + 4]);
+}
+
+#[test]
+fn torus_vertices_and_normals() {
+    let mesh = tessellate(&torus(1., 0.25), 16, 8);
+
+    for (point, normal) in mesh.positions().iter().zip(mesh.normals().iter()) {
+        let ring = (point.x * point.x + point.z * point.z).sqrt() - 1.;
+        assert!(approx_eq(0.25, (ring * ring + point.y * point.y).sqrt()));
+
+        // Normals point away from the center of the tube
+        let center = Tuple::point(point.x / (ring + 1.), 0., point.z / (ring + 1.));
+        let outwards = (*point - center).normalize();
+        assert_eq!(outwards, *normal);
+    }
+}
+
+#[test]
+fn rendering_tessellated_torus() {
+    let mesh = tessellate(&torus(1., 0.25), 64, 32);
+    let ray = Ray::create(Tuple::point(5., 0.01, 0.02), Tuple::vector(-1., 0., 0.));
+    let xs = mesh.intersect(&ray);
+
+    assert_eq!(4, xs.len());
+    let hit = xs.hit().unwrap();
+    assert!((hit.t - 3.75).abs() < 0.01);
+    let normal = mesh.normal_at(ray.position(hit.t), hit);
+    assert!((normal - Tuple::vector(1., 0., 0.)).magnitude() < 0.1);
+}
+
+#[test]
+fn mobius_strip_has_one_side() {
+    let mesh = tessellate(&mobius_strip(1., 0.5), 32, 4);
+    let (first, last) = (4 * 33, 33 - 1);
+
+    // Going once around ends up on the other edge, facing the other way
+    assert_eq!(mesh.positions()[first], mesh.positions()[last]);
+    assert_eq!(-mesh.normals()[first], mesh.normals()[last]);
+}
+
+#[test]
+fn klein_bottle_normals_are_defined() {
+    let mesh = tessellate(&klein_bottle(2.), 32, 16);
+
+    for normal in mesh.normals().iter() {
+        assert!(approx_eq(1., normal.magnitude()));
+    }
+}
+
+#[test]
+fn superquadrics() {
+    let sphere = tessellate(&superquadric(1., 1.), 16, 8);
+    for (point, normal) in sphere.positions().iter().zip(sphere.normals().iter()) {
+        let outwards = *point - Tuple::point(0., 0., 0.);
+        assert!(approx_eq(1., outwards.magnitude()));
+        // Pole normals are taken slightly off the pole
+        assert!((outwards - *normal).magnitude() < 0.01);
+    }
+
+    let cube = tessellate(&superquadric(0.1, 0.1), 16, 8);
+    let corner = cube.positions()[2 * 17 + 2];
+    assert!(corner.x.abs() > 0.8 && corner.y.abs() > 0.8 && corner.z.abs() > 0.8);
+}
@@ -0,0 +1,344 @@
+use crate::intersection::intersection::Intersection;
+use crate::material::material::Material;
+use crate::math::bounds::Bounds;
+use crate::math::matrix::{Matrix, IDENTITY_MATRIX};
+use crate::math::polynomial::solve_quadratic;
+use crate::math::tuple::Tuple;
+use crate::ray::ray::Ray;
+#[cfg(test)]
+use crate::shapes::cone::Cone;
+use crate::shapes::shape::Shape;
+#[cfg(test)]
+use ray_tracer::approx_eq;
+
+/// General second-degree surface
+/// `a·x² + b·y² + c·z² + d·xy + e·xz + f·yz + g·x + h·y + i·z + j = 0`.
+#[derive(Debug)]
+pub struct Quadric {
+    pub a: f64,
+    pub b: f64,
+    pub c: f64,
+    pub d: f64,
+    pub e: f64,
+    pub f: f64,
+    pub g: f64,
+    pub h: f64,
+    pub i: f64,
+    pub j: f64,
+    transform: Matrix,
+    inverse: Matrix,
+    material: Material,
+}
+
+impl Quadric {
+    /// Coefficients in the order `a` to `j` of the equation above.
+    pub fn create(coefficients: [f64; 10]) -> Quadric {
+        let [a, b, c, d, e, f, g, h, i, j] = coefficients;
+        Quadric {
+            a,
+            b,
+            c,
+            d,
+            e,
+            f,
+            g,
+            h,
+            i,
+            j,
+            transform: IDENTITY_MATRIX,
+            inverse: IDENTITY_MATRIX,
+            material: Material::create(),
+        }
+    }
+
+    /// Scales the equation so that it leads with a positive `x²`, which makes
+    /// `-x² - y² - z² + 1` the same sphere as `x² + y² + z² - 1`.
+    fn sign(&self) -> f64 {
+        if self.a < 0. {
+            -1.
+        } else {
+            1.
+        }
+    }
+}
+
+impl Shape for Quadric {
+    fn transform(&self) -> &Matrix {
+        &self.transform
+    }
+
+    fn inverse_transform(&self) -> &Matrix {
+        &self.inverse
+    }
+
+    fn set_transform(&mut self, transform: Matrix) {
+        self.transform = transform;
+        self.inverse = transform.inverse();
+    }
+
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn set_material(&mut self, material: Material) {
+        self.material = material;
+    }
+
+    fn local_intersect(&self, ray: &Ray) -> Vec<Intersection<'_>> {
+        let (o, d) = (ray.origin, ray.direction);
+
+        let qa = self.a * d.x * d.x
+            + self.b * d.y * d.y
+            + self.c * d.z * d.z
+            + self.d * d.x * d.y
+            + self.e * d.x * d.z
+            + self.f * d.y * d.z;
+        let qb = 2. * (self.a * o.x * d.x + self.b * o.y * d.y + self.c * o.z * d.z)
+            + self.d * (o.x * d.y + o.y * d.x)
+            + self.e * (o.x * d.z + o.z * d.x)
+            + self.f * (o.y * d.z + o.z * d.y)
+            + self.g * d.x
+            + self.h * d.y
+            + self.i * d.z;
+        let qc = self.a * o.x * o.x
+            + self.b * o.y * o.y
+            + self.c * o.z * o.z
+            + self.d * o.x * o.y
+            + self.e * o.x * o.z
+            + self.f * o.y * o.z
+            + self.g * o.x
+            + self.h * o.y
+            + self.i * o.z
+            + self.j;
+
+        solve_quadratic(qa, qb, qc)
+            .into_iter()
+            .map(|t| Intersection::create(t, self))
+            .collect()
+    }
+
+    fn local_normal_at(&self, point: Tuple, _hit: &Intersection) -> Tuple {
+        let (x, y, z) = (point.x, point.y, point.z);
+
+        Tuple::vector(
+            2. * self.a * x + self.d * y + self.e * z + self.g,
+            2. * self.b * y + self.d * x + self.f * z + self.h,
+            2. * self.c * z + self.e * x + self.f * y + self.i,
+        )
+    }
+
+    /// Inside is where the equation is negative, as for `x² + y² + z² - 1`, once
+    /// its sign is normalized.
+    fn local_contains(&self, point: Tuple) -> bool {
+        let Tuple { x, y, z, .. } = point;
+        let value = self.a * x * x
+            + self.b * y * y
+            + self.c * z * z
+            + self.d * x * y
+            + self.e * x * z
+            + self.f * y * z
+            + self.g * x
+            + self.h * y
+            + self.i * z
+            + self.j;
+        self.sign() * value < 0.
+    }
+
+    /// Only ellipsoids are bounded. For those the quadratic part of the equation is
+    /// definite, and its inverse gives the center and the extent along each axis.
+    fn local_bounds(&self) -> Bounds {
+        let sign = self.sign();
+        let rows = [
+            [self.a, self.d / 2., self.e / 2.],
+            [self.d / 2., self.b, self.f / 2.],
+            [self.e / 2., self.f / 2., self.c],
+        ];
+        let mut quadratic = IDENTITY_MATRIX;
+        for (row, values) in rows.iter().enumerate() {
+            for (col, value) in values.iter().enumerate() {
+                quadratic.set(row, col, sign * value);
+            }
+        }
+
+        let minor = quadratic[0][0] * quadratic[1][1] - quadratic[0][1] * quadratic[1][0];
+        if quadratic[0][0] <= 0. || minor <= 0. || quadratic.determinant() <= 0. {
+            return Bounds::infinite();
+        }
+
+        let inverse = quadratic.inverse();
+        let linear = Tuple::vector(self.g, self.h, self.i) * sign;
+        let offset = inverse * linear * -0.5;
+        // Moved to its center the ellipsoid is `x·Ax = scale`
+        let scale = -(sign * self.j + linear.dot(offset) / 2.);
+        if scale < 0. {
+            return Bounds::empty();
+        }
+
+        let center = Tuple::point(offset.x, offset.y, offset.z);
+        let extent = Tuple::vector(
+            (scale * inverse[0][0]).sqrt(),
+            (scale * inverse[1][1]).sqrt(),
+            (scale * inverse[2][2]).sqrt(),
+        );
+        Bounds::create(center - extent, center + extent)
+    }
+}
+
+#[test]
+fn unit_sphere_as_quadric() {
+    let sphere = Quadric::create([1., 1., 1., 0., 0., 0., 0., 0., 0., -1.]);
+    let ray = Ray::create(Tuple::point(0., 0., -5.), Tuple::vector(0., 0., 1.));
+    let xs = sphere.local_intersect(&ray);
+
+    assert_eq!(2, xs.len());
+    assert!(approx_eq(4., xs[0].t));
+    assert!(approx_eq(6., xs[1].t));
+
+    let hit = Intersection::create(0., &sphere);
+    let third = f64::sqrt(3.) / 3.;
+    assert_eq!(
+        Tuple::vector(third, third, third),
+        sphere.normal_at(Tuple::point(third, third, third), &hit)
+    );
+}
+
+#[test]
+fn ellipsoid_as_quadric() {
+    // x²/4 + y² + z²/9 = 1
+    let ellipsoid = Quadric::create([0.25, 1., 1. / 9., 0., 0., 0., 0., 0., 0., -1.]);
+    let ray = Ray::create(Tuple::point(-5., 0., 0.), Tuple::vector(1., 0., 0.));
+    let xs = ellipsoid.local_intersect(&ray);
+
+    assert!(approx_eq(3., xs[0].t));
+    assert!(approx_eq(7., xs[1].t));
+}
+
+#[test]
+fn ray_parallel_to_paraboloid_axis_hits_once() {
+    // y = x² + z²
+    let paraboloid = Quadric::create([1., 0., 1., 0., 0., 0., 0., -1., 0., 0.]);
+    let ray = Ray::create(Tuple::point(1., 5., 1.), Tuple::vector(0., -1., 0.));
+    let xs = paraboloid.local_intersect(&ray);
+
+    assert_eq!(1, xs.len());
+    assert!(approx_eq(3., xs[0].t));
+
+    let hit = Intersection::create(0., &paraboloid);
+    assert_eq!(
+        Tuple::vector(2., -1., 2.).normalize(),
+        paraboloid.normal_at(Tuple::point(1., 2., 1.), &hit)
+    );
+}
+
+#[test]
+fn hyperbolic_paraboloid_as_quadric() {
+    // y = x² - z², a saddle that a vertical ray always hits exactly once
+    let saddle = Quadric::create([1., 0., -1., 0., 0., 0., 0., -1., 0., 0.]);
+    let ray = Ray::create(Tuple::point(1., 5., 2.), Tuple::vector(0., -1., 0.));
+    let xs = saddle.local_intersect(&ray);
+
+    assert_eq!(1, xs.len());
+    assert!(approx_eq(8., xs[0].t));
+}
+
+#[test]
+fn hyperboloid_of_one_sheet_as_quadric() {
+    // x² - y² + z² = 1, the ray through the waist along x enters and leaves
+    let hyperboloid = Quadric::create([1., -1., 1., 0., 0., 0., 0., 0., 0., -1.]);
+    let ray = Ray::create(Tuple::point(-5., 0., 0.), Tuple::vector(1., 0., 0.));
+    let xs = hyperboloid.local_intersect(&ray);
+
+    assert!(approx_eq(4., xs[0].t));
+    assert!(approx_eq(6., xs[1].t));
+
+    // Along the axis it never touches the surface
+    let ray = Ray::create(Tuple::point(0., -5., 0.), Tuple::vector(0., 1., 0.));
+    assert!(hyperboloid.local_intersect(&ray).is_empty());
+}
+
+#[test]
+fn quadric_matches_dedicated_cone() {
+    let cone = Cone::create();
+    let quadric = Quadric::create([1., -1., 1., 0., 0., 0., 0., 0., 0., 0.]);
+    let rays = [
+        Ray::create(Tuple::point(0., 0., -5.), Tuple::vector(0., 0., 1.)),
+        Ray::create(
+            Tuple::point(0., 0., -5.),
+            Tuple::vector(1., 1., 1.).normalize(),
+        ),
+        Ray::create(
+            Tuple::point(1., 1., -5.),
+            Tuple::vector(-0.5, -1., 1.).normalize(),
+        ),
+        Ray::create(
+            Tuple::point(0., 0., -1.),
+            Tuple::vector(0., 1., 1.).normalize(),
+        ),
+    ];
+
+    for ray in rays.iter() {
+        let expected = cone.local_intersect(ray);
+        let actual = quadric.local_intersect(ray);
+
+        assert_eq!(expected.len(), actual.len());
+        for (expected, actual) in expected.iter().zip(actual.iter()) {
+            assert!(approx_eq(expected.t, actual.t));
+
+            // The gradient vanishes at the apex, where the cone falls back to its axis.
+            let point = ray.position(actual.t);
+            if point == Tuple::point(0., 0., 0.) {
+                continue;
+            }
+            assert_eq!(
+                cone.normal_at(point, expected),
+                quadric.normal_at(point, actual)
+            );
+        }
+    }
+}
+
+#[test]
+fn point_inside_quadric() {
+    // Paraboloid y = x² + z², open upwards
+    let paraboloid = Quadric::create([1., 0., 1., 0., 0., 0., 0., -1., 0., 0.]);
+
+    assert!(paraboloid.contains(Tuple::point(0., 1., 0.5)));
+    assert!(!paraboloid.contains(Tuple::point(0., -1., 0.)));
+    assert!(!paraboloid.contains(Tuple::point(2., 1., 0.)));
+}
+
+#[test]
+fn containment_ignores_sign_of_equation() {
+    let sphere = Quadric::create([1., 1., 1., 0., 0., 0., 0., 0., 0., -1.]);
+    let flipped = Quadric::create([-1., -1., -1., 0., 0., 0., 0., 0., 0., 1.]);
+
+    for quadric in [sphere, flipped] {
+        assert!(quadric.contains(Tuple::point(0.5, 0., 0.)));
+        assert!(!quadric.contains(Tuple::point(2., 0., 0.)));
+        assert_eq!(Tuple::point(-1., -1., -1.), quadric.local_bounds().min);
+    }
+}
+
+#[test]
+fn bounds_of_ellipsoid() {
+    // (x - 1)² / 4 + y² + (z + 2)² / 9 = 1
+    let ellipsoid = Quadric::create([
+        1. / 4.,
+        1.,
+        1. / 9.,
+        0.,
+        0.,
+        0.,
+        -1. / 2.,
+        0.,
+        4. / 9.,
+        1. / 4. + 4. / 9. - 1.,
+    ]);
+    let bounds = ellipsoid.local_bounds();
+
+    assert_eq!(Tuple::point(-1., -1., -5.), bounds.min);
+    assert_eq!(Tuple::point(3., 1., 1.), bounds.max);
+
+    let paraboloid = Quadric::create([1., 0., 1., 0., 0., 0., 0., -1., 0., 0.]);
+    assert!(!paraboloid.local_bounds().is_finite());
+}
@@ -0,0 +1,141 @@
+use crate::intersection::intersection::Intersection;
+use crate::material::material::Material;
+use crate::math::bounds::Bounds;
+use crate::math::matrix::{Matrix, IDENTITY_MATRIX};
+use crate::math::tuple::Tuple;
+use crate::ray::ray::Ray;
+use crate::shapes::shape::Shape;
+#[cfg(test)]
+use ray_tracer::approx_eq;
+use ray_tracer::EPSILON;
+
+/// Flat quad in the xz plane, centered at the origin and facing up the y axis.
+/// `width` runs along x and `height` along z.
+#[derive(Debug)]
+pub struct Rectangle {
+    pub width: f64,
+    pub height: f64,
+    transform: Matrix,
+    inverse: Matrix,
+    material: Material,
+}
+
+impl Rectangle {
+    pub fn create(width: f64, height: f64) -> Rectangle {
+        Rectangle {
+            width,
+            height,
+            transform: IDENTITY_MATRIX,
+            inverse: IDENTITY_MATRIX,
+            material: Material::create(),
+        }
+    }
+
+    /// Maps an object space point to `u` along x and `v` along z, both in `[0, 1]`.
+    pub fn uv_at(&self, point: Tuple) -> (f64, f64) {
+        (point.x / self.width + 0.5, point.z / self.height + 0.5)
+    }
+
+    /// Surface area in object space.
+    pub fn area(&self) -> f64 {
+        self.width * self.height
+    }
+}
+
+impl Shape for Rectangle {
+    fn transform(&self) -> &Matrix {
+        &self.transform
+    }
+
+    fn inverse_transform(&self) -> &Matrix {
+        &self.inverse
+    }
+
+    fn set_transform(&mut self, transform: Matrix) {
+        self.transform = transform;
+        self.inverse = transform.inverse();
+    }
+
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn set_material(&mut self, material: Material) {
+        self.material = material;
+    }
+
+    fn local_intersect(&self, ray: &Ray) -> Vec<Intersection<'_>> {
+        if ray.direction.y.abs() < EPSILON {
+            return vec![];
+        }
+
+        let t = -ray.origin.y / ray.direction.y;
+        let point = ray.position(t);
+        if point.x.abs() > self.width / 2. || point.z.abs() > self.height / 2. {
+            return vec![];
+        }
+
+        let (u, v) = self.uv_at(point);
+        vec![Intersection::create_with_uv(t, self, u, v)]
+    }
+
+    fn local_normal_at(&self, _point: Tuple, _hit: &Intersection) -> Tuple {
+        Tuple::vector(0., 1., 0.)
+    }
+
+    fn local_bounds(&self) -> Bounds {
+        let (x, z) = (self.width / 2., self.height / 2.);
+        Bounds::create(Tuple::point(-x, 0., -z), Tuple::point(x, 0., z))
+    }
+}
+
+#[test]
+fn ray_hits_rectangle() {
+    let rectangle = Rectangle::create(4., 2.);
+    let ray = Ray::create(Tuple::point(1.5, -2., 0.5), Tuple::vector(0., 1., 0.));
+    let xs = rectangle.local_intersect(&ray);
+
+    assert_eq!(1, xs.len());
+    assert_eq!(2., xs[0].t);
+    assert!(approx_eq(0.875, xs[0].u));
+    assert!(approx_eq(0.75, xs[0].v));
+}
+
+#[test]
+fn ray_misses_rectangle() {
+    let rectangle = Rectangle::create(4., 2.);
+    let examples = [
+        Ray::create(Tuple::point(2.5, 1., 0.), Tuple::vector(0., -1., 0.)),
+        Ray::create(Tuple::point(0., 1., 1.5), Tuple::vector(0., -1., 0.)),
+        Ray::create(Tuple::point(0., 1., 0.), Tuple::vector(0., 0., 1.)),
+    ];
+
+    for ray in examples.iter() {
+        assert!(rectangle.local_intersect(ray).is_empty());
+    }
+}
+
+#[test]
+fn uv_mapping_on_rectangle() {
+    let rectangle = Rectangle::create(4., 2.);
+
+    assert_eq!((0., 0.), rectangle.uv_at(Tuple::point(-2., 0., -1.)));
+    assert_eq!((0.5, 0.5), rectangle.uv_at(Tuple::point(0., 0., 0.)));
+    assert_eq!((1., 1.), rectangle.uv_at(Tuple::point(2., 0., 1.)));
+}
+
+#[test]
+fn surface_area_of_rectangle() {
+    assert_eq!(8., Rectangle::create(4., 2.).area());
+}
+
+#[test]
+fn normal_of_rectangle_is_constant() {
+    let rectangle = Rectangle::create(1., 1.);
+    let hit = Intersection::create(0., &rectangle);
+
+    assert_eq!(
+        Tuple::vector(0., 1., 0.),
+        rectangle.local_normal_at(Tuple::point(0.25, 0., -0.25), &hit)
+    );
+}
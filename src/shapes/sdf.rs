@@ -0,0 +1,234 @@
+use crate::intersection::intersection::Intersection;
+use crate::material::material::Material;
+use crate::math::matrix::{Matrix, IDENTITY_MATRIX};
+#[cfg(test)]
+use crate::math::transformation::translation;
+use crate::math::tuple::Tuple;
+use crate::ray::ray::Ray;
+use crate::shapes::distance::DistanceFunction;
+#[cfg(test)]
+use crate::shapes::distance::{cuboid, mandelbulb, sphere};
+use crate::shapes::shape::Shape;
+#[cfg(test)]
+use ray_tracer::approx_eq;
+use std::fmt;
+
+/// Shape defined by a signed distance function and rendered by sphere tracing:
+/// the ray advances by the distance to the closest surface until it is within
+/// `epsilon` of it. Only the first surface crossing in front of the ray is reported.
+pub struct Sdf {
+    distance: DistanceFunction,
+    /// Fraction of the distance the ray advances per step. Distance functions that
+    /// overestimate, e.g. `twist`, need it below one by their Lipschitz constant so
+    /// that no step overshoots through the surface.
+    pub step_scale: f64,
+    pub max_steps: usize,
+    pub epsilon: f64,
+    pub max_distance: f64,
+    transform: Matrix,
+    inverse: Matrix,
+    material: Material,
+}
+
+impl Sdf {
+    pub fn create(distance: DistanceFunction) -> Sdf {
+        Sdf {
+            distance,
+            step_scale: 1.,
+            max_steps: 256,
+            epsilon: 1e-6,
+            max_distance: 1000.,
+            transform: IDENTITY_MATRIX,
+            inverse: IDENTITY_MATRIX,
+            material: Material::create(),
+        }
+    }
+
+    pub fn distance(&self, point: Tuple) -> f64 {
+        (self.distance)(point)
+    }
+}
+
+impl fmt::Debug for Sdf {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("Sdf")
+            .field("step_scale", &self.step_scale)
+            .field("max_steps", &self.max_steps)
+            .field("epsilon", &self.epsilon)
+            .field("max_distance", &self.max_distance)
+            .field("transform", &self.transform)
+            .finish()
+    }
+}
+
+impl Shape for Sdf {
+    fn transform(&self) -> &Matrix {
+        &self.transform
+    }
+
+    fn inverse_transform(&self) -> &Matrix {
+        &self.inverse
+    }
+
+    fn set_transform(&mut self, transform: Matrix) {
+        self.transform = transform;
+        self.inverse = transform.inverse();
+    }
+
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn set_material(&mut self, material: Material) {
+        self.material = material;
+    }
+
+    fn local_intersect(&self, ray: &Ray) -> Vec<Intersection<'_>> {
+        // March in object space units, the transformed direction is not normalized.
+        let d = ray.direction;
+        let speed = (d.x * d.x + d.y * d.y + d.z * d.z).sqrt();
+        let mut travelled = 0.;
+
+        for _ in 0..self.max_steps {
+            let t = travelled / speed;
+            // A ray starting inside still needs to step towards the surface.
+            let distance = self.distance(ray.position(t)).abs();
+            if distance < self.epsilon {
+                return vec![Intersection::create(t, self)];
+            }
+
+            travelled += distance * self.step_scale;
+            if travelled > self.max_distance {
+                break;
+            }
+        }
+        vec![]
+    }
+
+    fn local_normal_at(&self, point: Tuple, _hit: &Intersection) -> Tuple {
+        // Central differences, stepping a little wider than the hit tolerance.
+        let h = self.epsilon * 10.;
+        let gradient =
+            |offset: Tuple| self.distance(point + offset) - self.distance(point - offset);
+
+        Tuple::vector(
+            gradient(Tuple::vector(h, 0., 0.)),
+            gradient(Tuple::vector(0., h, 0.)),
+            gradient(Tuple::vector(0., 0., h)),
+        )
+    }
+
+    fn local_contains(&self, point: Tuple) -> bool {
+        self.distance(point) < 0.
+    }
+}
+
+#[test]
+fn ray_marches_to_sphere() {
+    let sdf = Sdf::create(sphere(1.));
+    let ray = Ray::create(Tuple::point(0., 0., -5.), Tuple::vector(0., 0., 1.));
+    let xs = sdf.local_intersect(&ray);
+
+    assert_eq!(1, xs.len());
+    assert!(approx_eq(4., xs[0].t));
+}
+
+#[test]
+fn ray_misses_sphere_sdf() {
+    let sdf = Sdf::create(sphere(1.));
+    let ray = Ray::create(Tuple::point(0., 2., -5.), Tuple::vector(0., 0., 1.));
+
+    assert!(sdf.local_intersect(&ray).is_empty());
+}
+
+#[test]
+fn ray_from_inside_finds_exit() {
+    let sdf = Sdf::create(sphere(1.));
+    let ray = Ray::create(Tuple::point(0., 0., 0.), Tuple::vector(0., 0., 1.));
+    let xs = sdf.local_intersect(&ray);
+
+    assert!(approx_eq(1., xs[0].t));
+}
+
+#[test]
+fn step_scale_keeps_overestimates_from_overshooting() {
+    let sphere = sphere(1.);
+    let mut sdf = Sdf::create(Box::new(move |p| 3. * sphere(p)));
+    let ray = Ray::create(Tuple::point(0., 0., -5.), Tuple::vector(0., 0., 1.));
+    assert!(sdf.local_intersect(&ray).is_empty());
+
+    sdf.step_scale = 1. / 3.;
+    let xs = sdf.local_intersect(&ray);
+    assert_eq!(1, xs.len());
+    assert!(approx_eq(4., xs[0].t));
+}
+
+#[test]
+fn step_limit_stops_marching() {
+    let mut sdf = Sdf::create(cuboid(Tuple::vector(1., 1., 1.)));
+    // Grazing rays close in on the surface slowly
+    let ray = Ray::create(
+        Tuple::point(1.5, 0., -5.),
+        Tuple::vector(-0.1, 0., 1.).normalize(),
+    );
+    assert_eq!(1, sdf.local_intersect(&ray).len());
+
+    sdf.max_steps = 20;
+    assert!(sdf.local_intersect(&ray).is_empty());
+}
+
+#[test]
+fn ray_beyond_max_distance_misses() {
+    let mut sdf = Sdf::create(sphere(1.));
+    sdf.max_distance = 3.;
+    let ray = Ray::create(Tuple::point(0., 0., -5.), Tuple::vector(0., 0., 1.));
+
+    assert!(sdf.local_intersect(&ray).is_empty());
+}
+
+#[test]
+fn intersect_transformed_sdf() {
+    let mut sdf = Sdf::create(sphere(1.));
+    sdf.set_transform(translation(0., 0., 2.));
+    let ray = Ray::create(Tuple::point(0., 0., -5.), Tuple::vector(0., 0., 2.));
+    let xs = sdf.intersect(&ray);
+
+    assert!(approx_eq(3., xs[0].t));
+}
+
+#[test]
+fn normal_from_distance_gradient() {
+    let sdf = Sdf::create(cuboid(Tuple::vector(1., 1., 1.)));
+    let hit = Intersection::create(0., &sdf);
+
+    assert_eq!(
+        Tuple::vector(1., 0., 0.),
+        sdf.normal_at(Tuple::point(1., 0.3, -0.2), &hit)
+    );
+
+    let sdf = Sdf::create(sphere(1.));
+    let third = f64::sqrt(3.) / 3.;
+    assert_eq!(
+        Tuple::vector(third, third, third),
+        sdf.normal_at(Tuple::point(third, third, third), &hit)
+    );
+}
+
+#[test]
+fn ray_marches_to_mandelbulb() {
+    let sdf = Sdf::create(mandelbulb(8., 10));
+    let ray = Ray::create(Tuple::point(0., 0., -3.), Tuple::vector(0., 0., 1.));
+    let xs = sdf.local_intersect(&ray);
+
+    assert_eq!(1, xs.len());
+    assert!(xs[0].t > 1.8 && xs[0].t < 3.);
+}
+
+#[test]
+fn point_inside_sdf() {
+    let mut sdf = Sdf::create(sphere(1.));
+    sdf.set_transform(translation(0., 0., 2.));
+
+    assert!(sdf.contains(Tuple::point(0., 0.5, 2.5)));
+    assert!(!sdf.contains(Tuple::point(0., 0., 0.)));
+}
@@ -0,0 +1,120 @@
+use crate::intersection::intersection::{Intersection, Intersections};
+use crate::material::material::Material;
+use crate::math::bounds::Bounds;
+use crate::math::matrix::Matrix;
+use crate::math::tuple::Tuple;
+use crate::ray::ray::Ray;
+use std::fmt::Debug;
+
+pub trait Shape: Debug {
+    fn transform(&self) -> &Matrix;
+
+    /// Cached inverse of the transform, so it is not recomputed for every ray.
+    fn inverse_transform(&self) -> &Matrix;
+
+    fn set_transform(&mut self, transform: Matrix);
+
+    /// Inverse of the transform at a point in time. Only shapes that move need
+    /// more than their cached inverse, see `Animated`.
+    fn inverse_transform_at(&self, _time: f64) -> Matrix {
+        *self.inverse_transform()
+    }
+
+    fn material(&self) -> &Material;
+
+    fn set_material(&mut self, material: Material);
+
+    /// Material that replaces the one of every shape below this one, see `Instance`.
+    fn material_override(&self) -> Option<&Material> {
+        None
+    }
+
+    /// Intersects a ray that has already been converted into object space.
+    fn local_intersect(&self, ray: &Ray) -> Vec<Intersection<'_>>;
+
+    /// Normal at a point given in object space. The hit is passed along for shapes
+    /// that need more than the point, e.g. the `u`/`v` of a smooth triangle.
+    fn local_normal_at(&self, point: Tuple, hit: &Intersection) -> Tuple;
+
+    /// Whether a point in object space is inside the shape. Only closed shapes have
+    /// an inside, so by default nothing is.
+    fn local_contains(&self, _point: Tuple) -> bool {
+        false
+    }
+
+    /// Whether a world point is inside the shape. Moving shapes are tested where
+    /// they are at time 0.
+    fn contains(&self, point: Tuple) -> bool {
+        self.local_contains(self.inverse_transform_at(0.) * point)
+    }
+
+    /// Whether a ray in object space hits the shape between its origin and `max_t`.
+    /// Shapes made of others override it to stop at the first one in the way.
+    fn local_occludes(&self, ray: &Ray, max_t: f64) -> bool {
+        self.local_intersect(ray)
+            .iter()
+            .any(|hit| hit.t >= 0. && hit.t < max_t)
+    }
+
+    /// Whether the ray hits the shape between its origin and `max_t`, as for shadow
+    /// rays. Unlike `intersect` the hits are neither sorted nor searched for the
+    /// nearest.
+    fn occludes(&self, ray: &Ray, max_t: f64) -> bool {
+        self.local_occludes(&ray.transform(self.inverse_transform_at(ray.time)), max_t)
+    }
+
+    /// Box around the shape in object space. Shapes that reach out to infinity keep
+    /// the default.
+    fn local_bounds(&self) -> Bounds {
+        Bounds::infinite()
+    }
+
+    /// Box around the shape in the space of its group, or of the world for shapes
+    /// outside of any.
+    fn parent_space_bounds(&self) -> Bounds {
+        self.local_bounds().transform(*self.transform())
+    }
+
+    fn intersect(&self, ray: &Ray) -> Intersections<'_> {
+        let local_ray = ray.transform(self.inverse_transform_at(ray.time));
+        Intersections::create(
+            self.local_intersect(&local_ray)
+                .into_iter()
+                .map(|mut hit| {
+                    hit.time = ray.time;
+                    hit
+                })
+                .collect(),
+        )
+    }
+
+    /// Converts a world point into object space, walking the parent chain from the
+    /// outermost group down to this shape. `parents` is ordered innermost first.
+    fn world_to_object(&self, parents: &[&dyn Shape], point: Tuple, time: f64) -> Tuple {
+        let point = parents.iter().rev().fold(point, |point, parent| {
+            parent.inverse_transform_at(time) * point
+        });
+        self.inverse_transform_at(time) * point
+    }
+
+    /// Converts an object space normal into world space, walking the parent chain
+    /// from this shape up to the outermost group.
+    fn normal_to_world(&self, parents: &[&dyn Shape], normal: Tuple, time: f64) -> Tuple {
+        let to_parent = |inverse: Matrix, normal: Tuple| {
+            let mut normal = inverse.transpose() * normal;
+            normal.w = 0.;
+            normal.normalize()
+        };
+
+        parents.iter().fold(
+            to_parent(self.inverse_transform_at(time), normal),
+            |normal, parent| to_parent(parent.inverse_transform_at(time), normal),
+        )
+    }
+
+    fn normal_at(&self, world_point: Tuple, hit: &Intersection) -> Tuple {
+        let local_point = self.world_to_object(&hit.parents, world_point, hit.time);
+        let local_normal = self.local_normal_at(local_point, hit);
+        self.normal_to_world(&hit.parents, local_normal, hit.time)
+    }
+}
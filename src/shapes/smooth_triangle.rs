@@ -0,0 +1,134 @@
+use crate::intersection::intersection::Intersection;
+use crate::material::material::Material;
+use crate::math::bounds::Bounds;
+use crate::math::matrix::{Matrix, IDENTITY_MATRIX};
+use crate::math::tuple::Tuple;
+use crate::ray::ray::Ray;
+use crate::shapes::shape::Shape;
+use crate::shapes::triangle::moller_trumbore;
+#[cfg(test)]
+use ray_tracer::approx_eq;
+
+/// Triangle whose normal is interpolated from the normals at its three vertices.
+#[derive(Debug)]
+pub struct SmoothTriangle {
+    pub p1: Tuple,
+    pub p2: Tuple,
+    pub p3: Tuple,
+    pub n1: Tuple,
+    pub n2: Tuple,
+    pub n3: Tuple,
+    pub e1: Tuple,
+    pub e2: Tuple,
+    transform: Matrix,
+    inverse: Matrix,
+    material: Material,
+}
+
+impl SmoothTriangle {
+    pub fn create(
+        p1: Tuple,
+        p2: Tuple,
+        p3: Tuple,
+        n1: Tuple,
+        n2: Tuple,
+        n3: Tuple,
+    ) -> SmoothTriangle {
+        SmoothTriangle {
+            p1,
+            p2,
+            p3,
+            n1,
+            n2,
+            n3,
+            e1: p2 - p1,
+            e2: p3 - p1,
+            transform: IDENTITY_MATRIX,
+            inverse: IDENTITY_MATRIX,
+            material: Material::create(),
+        }
+    }
+}
+
+impl Shape for SmoothTriangle {
+    fn transform(&self) -> &Matrix {
+        &self.transform
+    }
+
+    fn inverse_transform(&self) -> &Matrix {
+        &self.inverse
+    }
+
+    fn set_transform(&mut self, transform: Matrix) {
+        self.transform = transform;
+        self.inverse = transform.inverse();
+    }
+
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn set_material(&mut self, material: Material) {
+        self.material = material;
+    }
+
+    fn local_intersect(&self, ray: &Ray) -> Vec<Intersection<'_>> {
+        match moller_trumbore(ray, self.p1, self.e1, self.e2) {
+            Some((t, u, v)) => vec![Intersection::create_with_uv(t, self, u, v)],
+            None => vec![],
+        }
+    }
+
+    fn local_normal_at(&self, _point: Tuple, hit: &Intersection) -> Tuple {
+        self.n2 * hit.u + self.n3 * hit.v + self.n1 * (1. - hit.u - hit.v)
+    }
+
+    fn local_bounds(&self) -> Bounds {
+        Bounds::from_points(&[self.p1, self.p2, self.p3])
+    }
+}
+
+#[cfg(test)]
+fn test_smooth_triangle() -> SmoothTriangle {
+    SmoothTriangle::create(
+        Tuple::point(0., 1., 0.),
+        Tuple::point(-1., 0., 0.),
+        Tuple::point(1., 0., 0.),
+        Tuple::vector(0., 1., 0.),
+        Tuple::vector(-1., 0., 0.),
+        Tuple::vector(1., 0., 0.),
+    )
+}
+
+#[test]
+fn constructing_smooth_triangle() {
+    let tri = test_smooth_triangle();
+
+    assert_eq!(Tuple::point(0., 1., 0.), tri.p1);
+    assert_eq!(Tuple::point(-1., 0., 0.), tri.p2);
+    assert_eq!(Tuple::point(1., 0., 0.), tri.p3);
+    assert_eq!(Tuple::vector(-1., 0., 0.), tri.n2);
+    assert_eq!(Tuple::vector(-1., -1., 0.), tri.e1);
+    assert_eq!(Tuple::vector(1., -1., 0.), tri.e2);
+}
+
+#[test]
+fn intersection_with_smooth_triangle_stores_uv() {
+    let tri = test_smooth_triangle();
+    let ray = Ray::create(Tuple::point(-0.2, 0.3, -2.), Tuple::vector(0., 0., 1.));
+    let xs = tri.local_intersect(&ray);
+
+    assert!(approx_eq(0.45, xs[0].u));
+    assert!(approx_eq(0.25, xs[0].v));
+}
+
+#[test]
+fn smooth_triangle_interpolates_normal() {
+    let tri = test_smooth_triangle();
+    let hit = Intersection::create_with_uv(1., &tri, 0.45, 0.25);
+
+    assert_eq!(
+        Tuple::vector(-0.5547, 0.83205, 0.),
+        tri.normal_at(Tuple::point(0., 0., 0.), &hit)
+    );
+}
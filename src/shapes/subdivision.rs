@@ -0,0 +1,457 @@
+//! Loop and Catmull-Clark subdivision of control cages into smooth `Mesh`es.
+//!
+//! Creases are given as pairs of vertex indices. Creased and boundary edges are
+//! both sharp: their new points are midpoints, a vertex on exactly two sharp edges
+//! follows the curve they make, and a vertex on three or more is a corner and stays
+//! put. Uvs are not carried over.
+
+use crate::math::tuple::Tuple;
+#[cfg(test)]
+use crate::ray::ray::Ray;
+use crate::shapes::mesh::Mesh;
+use crate::shapes::shape::Shape;
+#[cfg(test)]
+use ray_tracer::approx_eq;
+use std::collections::{HashMap, HashSet};
+
+type Edge = (u32, u32);
+
+fn edge(a: u32, b: u32) -> Edge {
+    (a.min(b), a.max(b))
+}
+
+fn average(points: impl IntoIterator<Item = Tuple>) -> Tuple {
+    let mut sum = Tuple::point(0., 0., 0.);
+    let mut count = 0.;
+    for point in points {
+        sum = sum + Tuple::vector(point.x, point.y, point.z);
+        count += 1.;
+    }
+
+    Tuple::point(sum.x / count, sum.y / count, sum.z / count)
+}
+
+/// Moves `from` by `amount` of the way towards `to`.
+fn blend(from: Tuple, to: Tuple, amount: f64) -> Tuple {
+    from + (to - from) * amount
+}
+
+/// Faces around every edge, in the order they were found.
+fn edge_faces(faces: &[Vec<u32>]) -> HashMap<Edge, Vec<usize>> {
+    let mut edges: HashMap<Edge, Vec<usize>> = HashMap::new();
+    for (index, face) in faces.iter().enumerate() {
+        for i in 0..face.len() {
+            let key = edge(face[i], face[(i + 1) % face.len()]);
+            edges.entry(key).or_default().push(index);
+        }
+    }
+    edges
+}
+
+fn sharp_edges(edges: &HashMap<Edge, Vec<usize>>, creases: &HashSet<Edge>) -> HashSet<Edge> {
+    edges
+        .iter()
+        .filter(|(key, faces)| faces.len() == 1 || creases.contains(key))
+        .map(|(key, _)| *key)
+        .collect()
+}
+
+/// Neighbouring vertices of each vertex, split into those along sharp edges and all.
+fn neighbours(
+    count: usize,
+    edges: &HashMap<Edge, Vec<usize>>,
+    sharp: &HashSet<Edge>,
+) -> (Vec<Vec<u32>>, Vec<Vec<u32>>) {
+    let mut all = vec![vec![]; count];
+    let mut along_sharp = vec![vec![]; count];
+    for &(a, b) in edges.keys() {
+        all[a as usize].push(b);
+        all[b as usize].push(a);
+        if sharp.contains(&(a, b)) {
+            along_sharp[a as usize].push(b);
+            along_sharp[b as usize].push(a);
+        }
+    }
+    (all, along_sharp)
+}
+
+/// Position of a vertex on a crease or boundary, if it is on one.
+fn sharp_vertex(positions: &[Tuple], vertex: usize, along_sharp: &[u32]) -> Option<Tuple> {
+    let point = positions[vertex];
+    match along_sharp {
+        [a, b] => {
+            let middle = average([positions[*a as usize], positions[*b as usize]]);
+            Some(blend(point, middle, 0.25))
+        }
+        _ if along_sharp.len() > 2 => Some(point),
+        _ => None,
+    }
+}
+
+/// The children of sharp edges are sharp themselves.
+fn split_creases(creases: &HashSet<Edge>, edge_points: &HashMap<Edge, u32>) -> HashSet<Edge> {
+    creases
+        .iter()
+        .filter_map(|key| edge_points.get(key).map(|&middle| (key, middle)))
+        .flat_map(|(&(a, b), middle)| [edge(a, middle), edge(middle, b)])
+        .collect()
+}
+
+fn loop_step(
+    positions: &[Tuple],
+    triangles: &[[u32; 3]],
+    creases: &HashSet<Edge>,
+) -> (Vec<Tuple>, Vec<[u32; 3]>, HashSet<Edge>) {
+    let faces: Vec<Vec<u32>> = triangles.iter().map(|t| t.to_vec()).collect();
+    let edges = edge_faces(&faces);
+    let sharp = sharp_edges(&edges, creases);
+    let (all, along_sharp) = neighbours(positions.len(), &edges, &sharp);
+
+    let mut new_positions: Vec<Tuple> = (0..positions.len())
+        .map(|vertex| {
+            if all[vertex].is_empty() {
+                // Not part of any triangle, so there is nothing to smooth towards
+                return positions[vertex];
+            }
+            sharp_vertex(positions, vertex, &along_sharp[vertex]).unwrap_or_else(|| {
+                let n = all[vertex].len() as f64;
+                let beta = if all[vertex].len() == 3 {
+                    3. / 16.
+                } else {
+                    3. / (8. * n)
+                };
+                let ring = average(all[vertex].iter().map(|&v| positions[v as usize]));
+                blend(positions[vertex], ring, n * beta)
+            })
+        })
+        .collect();
+
+    let mut edge_points = HashMap::new();
+    let mut sorted: Vec<&Edge> = edges.keys().collect();
+    sorted.sort();
+    for key in sorted {
+        let (a, b) = (positions[key.0 as usize], positions[key.1 as usize]);
+        let point = if sharp.contains(key) {
+            average([a, b])
+        } else {
+            // The two vertices facing the edge in its neighbouring triangles
+            let opposite: Vec<Tuple> = edges[key]
+                .iter()
+                .map(|&face| {
+                    let third = triangles[face]
+                        .iter()
+                        .find(|&&v| v != key.0 && v != key.1)
+                        .unwrap();
+                    positions[*third as usize]
+                })
+                .collect();
+            blend(average([a, b]), average(opposite), 0.25)
+        };
+        edge_points.insert(*key, new_positions.len() as u32);
+        new_positions.push(point);
+    }
+
+    let new_triangles = triangles
+        .iter()
+        .flat_map(|&[a, b, c]| {
+            let ab = edge_points[&edge(a, b)];
+            let bc = edge_points[&edge(b, c)];
+            let ca = edge_points[&edge(c, a)];
+            [[a, ab, ca], [ab, b, bc], [ca, bc, c], [ab, bc, ca]]
+        })
+        .collect();
+
+    (
+        new_positions,
+        new_triangles,
+        split_creases(creases, &edge_points),
+    )
+}
+
+/// Loop subdivision of a triangle mesh, `levels` times. Each level splits every
+/// triangle in four. The result has smooth vertex normals, split along creases, and
+/// keeps the source's transform and material.
+pub fn loop_subdivide(mesh: &Mesh, creases: &[[u32; 2]], levels: usize) -> Mesh {
+    let mut positions = mesh.positions().to_vec();
+    let mut triangles = mesh.triangles().to_vec();
+    let mut creases: HashSet<Edge> = creases.iter().map(|&[a, b]| edge(a, b)).collect();
+
+    for _ in 0..levels {
+        (positions, triangles, creases) = loop_step(&positions, &triangles, &creases);
+    }
+
+    finish(Mesh::create(positions, triangles), &creases, mesh)
+}
+
+/// Gives a subdivided mesh normals and the transform and material of its source.
+fn finish(mut result: Mesh, creases: &HashSet<Edge>, source: &Mesh) -> Mesh {
+    let creases: Vec<[u32; 2]> = creases.iter().map(|&(a, b)| [a, b]).collect();
+    result.smooth_normals_with_creases(&creases);
+    result.set_transform(*source.transform());
+    result.set_material(*source.material());
+    result
+}
+
+fn catmull_clark_step(
+    positions: &[Tuple],
+    faces: &[Vec<u32>],
+    creases: &HashSet<Edge>,
+) -> (Vec<Tuple>, Vec<Vec<u32>>, HashSet<Edge>) {
+    let edges = edge_faces(faces);
+    let sharp = sharp_edges(&edges, creases);
+    let (all, along_sharp) = neighbours(positions.len(), &edges, &sharp);
+
+    let face_points: Vec<Tuple> = faces
+        .iter()
+        .map(|face| average(face.iter().map(|&v| positions[v as usize])))
+        .collect();
+    let mut vertex_faces = vec![vec![]; positions.len()];
+    for (index, face) in faces.iter().enumerate() {
+        for &v in face {
+            vertex_faces[v as usize].push(index);
+        }
+    }
+
+    let mut new_positions: Vec<Tuple> = (0..positions.len())
+        .map(|vertex| {
+            if all[vertex].is_empty() {
+                return positions[vertex];
+            }
+            sharp_vertex(positions, vertex, &along_sharp[vertex]).unwrap_or_else(|| {
+                let n = all[vertex].len() as f64;
+                let f = average(vertex_faces[vertex].iter().map(|&face| face_points[face]));
+                let r = average(
+                    all[vertex]
+                        .iter()
+                        .map(|&v| average([positions[vertex], positions[v as usize]])),
+                );
+                let p = positions[vertex];
+                Tuple::point(
+                    (f.x + 2. * r.x + (n - 3.) * p.x) / n,
+                    (f.y + 2. * r.y + (n - 3.) * p.y) / n,
+                    (f.z + 2. * r.z + (n - 3.) * p.z) / n,
+                )
+            })
+        })
+        .collect();
+
+    let mut face_indices = vec![];
+    for point in face_points.iter() {
+        face_indices.push(new_positions.len() as u32);
+        new_positions.push(*point);
+    }
+
+    let mut edge_points = HashMap::new();
+    let mut sorted: Vec<&Edge> = edges.keys().collect();
+    sorted.sort();
+    for key in sorted {
+        let (a, b) = (positions[key.0 as usize], positions[key.1 as usize]);
+        let point = if sharp.contains(key) {
+            average([a, b])
+        } else {
+            average(
+                [a, b]
+                    .iter()
+                    .copied()
+                    .chain(edges[key].iter().map(|&face| face_points[face])),
+            )
+        };
+        edge_points.insert(*key, new_positions.len() as u32);
+        new_positions.push(point);
+    }
+
+    let new_faces = faces
+        .iter()
+        .enumerate()
+        .flat_map(|(index, face)| {
+            let n = face.len();
+            let edge_points = &edge_points;
+            let center = face_indices[index];
+            (0..n).map(move |i| {
+                let (previous, current, next) = (face[(i + n - 1) % n], face[i], face[(i + 1) % n]);
+                vec![
+                    current,
+                    edge_points[&edge(current, next)],
+                    center,
+                    edge_points[&edge(previous, current)],
+                ]
+            })
+        })
+        .collect();
+
+    (
+        new_positions,
+        new_faces,
+        split_creases(creases, &edge_points),
+    )
+}
+
+/// Catmull-Clark subdivision of a polygon cage, `levels` times, see
+/// `Mesh::from_polygons`. Faces may have any number of sides; after the first level
+/// they are all quads, which become two triangles each in the result. Like
+/// `loop_subdivide` it has smooth normals and keeps the transform and material.
+pub fn catmull_clark(mesh: &Mesh, creases: &[[u32; 2]], levels: usize) -> Mesh {
+    let mut positions = mesh.positions().to_vec();
+    let mut faces = mesh.polygons();
+    let mut creases: HashSet<Edge> = creases.iter().map(|&[a, b]| edge(a, b)).collect();
+
+    for _ in 0..levels {
+        (positions, faces, creases) = catmull_clark_step(&positions, &faces, &creases);
+    }
+
+    finish(Mesh::from_polygons(positions, faces), &creases, mesh)
+}
+
+#[cfg(test)]
+fn tetrahedron() -> Mesh {
+    Mesh::create(
+        vec![
+            Tuple::point(1., 1., 1.),
+            Tuple::point(1., -1., -1.),
+            Tuple::point(-1., 1., -1.),
+            Tuple::point(-1., -1., 1.),
+        ],
+        vec![[0, 1, 2], [0, 3, 1], [0, 2, 3], [1, 3, 2]],
+    )
+}
+
+#[cfg(test)]
+fn cube() -> Mesh {
+    let positions = (0..8)
+        .map(|i| {
+            let coordinate = |bit: u32| if i & bit == 0 { -1. } else { 1. };
+            Tuple::point(coordinate(1), coordinate(2), coordinate(4))
+        })
+        .collect();
+    let faces = vec![
+        vec![0, 1, 3, 2],
+        vec![4, 6, 7, 5],
+        vec![0, 4, 5, 1],
+        vec![2, 3, 7, 6],
+        vec![0, 2, 6, 4],
+        vec![1, 5, 7, 3],
+    ];
+    Mesh::from_polygons(positions, faces)
+}
+
+#[test]
+fn loop_subdivision_splits_triangles() {
+    let mesh = loop_subdivide(&tetrahedron(), &[], 1);
+
+    assert_eq!(4 + 6, mesh.positions().len());
+    assert_eq!(16, mesh.triangles().len());
+    assert_eq!(mesh.positions().len(), mesh.normals().len());
+
+    let mesh = loop_subdivide(&tetrahedron(), &[], 3);
+    assert_eq!(4 * 4 * 4 * 4, mesh.triangles().len());
+}
+
+#[test]
+fn loop_subdivision_smooths_vertices() {
+    let mesh = loop_subdivide(&tetrahedron(), &[], 1);
+
+    // Valence three: beta = 3/16, so the corner moves 9/16 of the way to its ring
+    let corner = mesh.positions()[0];
+    let expected = 1. - 9. / 16. * (1. - -1. / 3.);
+    assert!(approx_eq(expected, corner.x));
+    assert!(approx_eq(expected, corner.y));
+    assert!(approx_eq(expected, corner.z));
+}
+
+#[test]
+fn boundary_and_crease_rules() {
+    let triangle = Mesh::create(
+        vec![
+            Tuple::point(0., 0., 0.),
+            Tuple::point(4., 0., 0.),
+            Tuple::point(0., 4., 0.),
+        ],
+        vec![[0, 1, 2]],
+    );
+    let mesh = loop_subdivide(&triangle, &[], 1);
+
+    // Boundary corners follow the curve of their two boundary edges
+    assert_eq!(Tuple::point(0.5, 0.5, 0.), mesh.positions()[0]);
+    assert_eq!(Tuple::point(2., 0., 0.), mesh.positions()[3]);
+
+    // A crease on the tetrahedron keeps its new midpoint on the original edge
+    let creased = loop_subdivide(&tetrahedron(), &[[0, 1]], 1);
+    let smooth = loop_subdivide(&tetrahedron(), &[], 1);
+    let midpoint = Tuple::point(1., 0., 0.);
+    assert!(creased.positions().contains(&midpoint));
+    assert!(!smooth.positions().contains(&midpoint));
+}
+
+#[test]
+fn catmull_clark_cube() {
+    let mesh = catmull_clark(&cube(), &[], 1);
+
+    assert_eq!(8 + 6 + 12, mesh.positions().len());
+    assert_eq!(24 * 2, mesh.triangles().len());
+    assert_eq!(Tuple::point(5. / 9., 5. / 9., 5. / 9.), mesh.positions()[7]);
+}
+
+#[test]
+fn fully_creased_cube_stays_a_cube() {
+    let cube = cube();
+    let creases: Vec<[u32; 2]> = cube
+        .polygons()
+        .iter()
+        .flat_map(|face| (0..4).map(move |i| [face[i], face[(i + 1) % 4]]))
+        .collect();
+    let mesh = catmull_clark(&cube, &creases, 2);
+
+    for point in mesh.positions().iter() {
+        let largest = point.x.abs().max(point.y.abs()).max(point.z.abs());
+        assert!(approx_eq(1., largest));
+    }
+}
+
+#[test]
+fn subdivided_cube_renders_smooth() {
+    let mesh = catmull_clark(&cube(), &[], 3);
+    let ray = Ray::create(Tuple::point(0.3, 0.2, -5.), Tuple::vector(0., 0., 1.));
+    let xs = mesh.intersect(&ray);
+
+    assert_eq!(2, xs.len());
+    let hit = xs.hit().unwrap();
+    let normal = mesh.normal_at(ray.position(hit.t), hit);
+    // Facing the ray, but tilted outwards like on a rounded surface
+    assert!(normal.z < 0.);
+    assert!(normal.x > 0. && normal.y > 0.);
+}
+
+#[test]
+fn creases_are_shaded_sharp() {
+    let cube = cube();
+    let creases: Vec<[u32; 2]> = cube
+        .polygons()
+        .iter()
+        .flat_map(|face| (0..4).map(move |i| [face[i], face[(i + 1) % 4]]))
+        .collect();
+    let mesh = catmull_clark(&cube, &creases, 2);
+
+    // Right next to the creased edge at x = 1 the face is still shaded flat
+    let ray = Ray::create(Tuple::point(0.95, 0.2, -5.), Tuple::vector(0., 0., 1.));
+    let xs = mesh.intersect(&ray);
+    let hit = xs.hit().unwrap();
+    assert_eq!(
+        Tuple::vector(0., 0., -1.),
+        mesh.normal_at(ray.position(hit.t), hit)
+    );
+}
+
+#[test]
+fn isolated_vertices_stay_put() {
+    let mut positions = tetrahedron().positions().to_vec();
+    positions.push(Tuple::point(5., 5., 5.));
+    let mesh = Mesh::create(positions, tetrahedron().triangles().to_vec());
+
+    let subdivided = loop_subdivide(&mesh, &[], 2);
+    assert_eq!(Tuple::point(5., 5., 5.), subdivided.positions()[4]);
+    let cage = Mesh::from_polygons(mesh.positions().to_vec(), mesh.polygons());
+    assert_eq!(
+        Tuple::point(5., 5., 5.),
+        catmull_clark(&cage, &[], 1).positions()[4]
+    );
+}
@@ -0,0 +1,608 @@
+//! Meshes built by sweeping a 2D profile: around the y axis with `lathe`, or along a
+//! path with `extrude`.
+//!
+//! Profile normals are smoothed across each vertex, except where the profile turns
+//! by more than `CREASE_ANGLE`; those corners get one vertex per side so they stay
+//! sharp. Triangles are wound to agree with their vertex normals.
+
+use crate::math::tuple::Tuple;
+#[cfg(test)]
+use crate::ray::ray::Ray;
+use crate::shapes::mesh::Mesh;
+#[cfg(test)]
+use crate::shapes::shape::Shape;
+#[cfg(test)]
+use ray_tracer::approx_eq;
+use ray_tracer::EPSILON;
+use std::f64::consts::TAU;
+
+pub const CREASE_ANGLE: f64 = std::f64::consts::FRAC_PI_4;
+
+struct ProfileVertex {
+    point: (f64, f64),
+    normal: (f64, f64),
+    v: f64,
+}
+
+fn normalize_2d((x, y): (f64, f64)) -> (f64, f64) {
+    let length = (x * x + y * y).sqrt();
+    (x / length, y / length)
+}
+
+/// Vertices along the profile with their normals and distance along it. Closed
+/// profiles end with their first point again to close the seam.
+fn profile_vertices(profile: &[(f64, f64)], closed: bool) -> Vec<ProfileVertex> {
+    let n = profile.len();
+    let segments = if closed { n } else { n - 1 };
+    let segment_normal = |i: usize| {
+        let ((x1, y1), (x2, y2)) = (profile[i % n], profile[(i + 1) % n]);
+        normalize_2d((y2 - y1, x1 - x2))
+    };
+    let mut lengths = vec![0.];
+    for i in 0..segments {
+        let ((x1, y1), (x2, y2)) = (profile[i], profile[(i + 1) % n]);
+        lengths.push(lengths[i] + ((x2 - x1).powi(2) + (y2 - y1).powi(2)).sqrt());
+    }
+    let total = lengths[segments];
+
+    let mut vertices = vec![];
+    for i in 0..=segments {
+        let point = profile[i % n];
+        let v = lengths[i] / total;
+        let before = if i > 0 {
+            Some(segment_normal(i - 1))
+        } else if closed {
+            Some(segment_normal(n - 1))
+        } else {
+            None
+        };
+        let after = if i < segments {
+            Some(segment_normal(i))
+        } else if closed {
+            Some(segment_normal(0))
+        } else {
+            None
+        };
+
+        match (before, after) {
+            (Some(b), Some(a)) if b.0 * a.0 + b.1 * a.1 < CREASE_ANGLE.cos() => {
+                // Around a closed profile's seam only the side facing inwards is kept
+                if i > 0 {
+                    vertices.push(ProfileVertex {
+                        point,
+                        normal: b,
+                        v,
+                    });
+                }
+                if i < segments {
+                    vertices.push(ProfileVertex {
+                        point,
+                        normal: a,
+                        v,
+                    });
+                }
+            }
+            (Some(b), Some(a)) => {
+                let normal = normalize_2d((b.0 + a.0, b.1 + a.1));
+                vertices.push(ProfileVertex { point, normal, v });
+            }
+            (Some(normal), None) | (None, Some(normal)) => {
+                vertices.push(ProfileVertex { point, normal, v })
+            }
+            (None, None) => {}
+        }
+    }
+    vertices
+}
+
+/// Joins `columns` copies of the profile, each `rows` vertices long and laid out one
+/// after another from `start`, with a band of quads between neighbouring vertices.
+fn stitch(start: usize, rows: usize, columns: usize, profile: &[ProfileVertex]) -> Vec<[u32; 3]> {
+    let index = |column: usize, row: usize| (start + column * rows + row) as u32;
+    let mut triangles = vec![];
+    for column in 0..columns - 1 {
+        for row in 0..rows - 1 {
+            // Split creases leave zero width bands
+            if profile[row].point == profile[row + 1].point {
+                continue;
+            }
+            let (a, b) = (index(column, row), index(column + 1, row));
+            let (c, d) = (index(column + 1, row + 1), index(column, row + 1));
+            triangles.push([a, b, c]);
+            triangles.push([a, c, d]);
+        }
+    }
+    triangles
+}
+
+/// Builds the mesh, dropping triangles collapsed onto a line, as happens around the
+/// axis of a lathe, and flipping those facing away from their vertex normals.
+fn finish(
+    positions: Vec<Tuple>,
+    normals: Vec<Tuple>,
+    uvs: Vec<(f64, f64)>,
+    triangles: Vec<[u32; 3]>,
+) -> Mesh {
+    let triangles = triangles
+        .into_iter()
+        .filter_map(|[a, b, c]| {
+            let (p1, p2, p3) = (
+                positions[a as usize],
+                positions[b as usize],
+                positions[c as usize],
+            );
+            let face = (p3 - p1).cross(p2 - p1);
+            let normal = normals[a as usize] + normals[b as usize] + normals[c as usize];
+            if face.magnitude() < EPSILON * EPSILON {
+                None
+            } else if face.dot(normal) < 0. {
+                Some([a, c, b])
+            } else {
+                Some([a, b, c])
+            }
+        })
+        .collect();
+
+    let mut mesh = Mesh::create(positions, triangles);
+    mesh.set_normals(normals);
+    mesh.set_uvs(uvs);
+    mesh
+}
+
+/// Revolves a profile of `(radius, height)` points around the y axis in `segments`
+/// steps. The outside of the surface is to the right when walking along the profile,
+/// so a profile going up at a positive radius faces outwards. `capped` closes off
+/// ends of the profile away from the axis with flat disks.
+pub fn lathe(profile: &[(f64, f64)], segments: usize, capped: bool) -> Mesh {
+    assert!(
+        profile.len() >= 2,
+        "Lathe profiles need at least two points!"
+    );
+    assert!(segments >= 3, "Lathes need at least three segments!");
+    let mut vertices = profile_vertices(profile, false);
+    // Ends on the axis face straight along it, or the surface would be pinched there
+    for index in [0, vertices.len() - 1] {
+        let vertex = &mut vertices[index];
+        if vertex.point.0.abs() < EPSILON {
+            vertex.normal = (0., vertex.normal.1.signum());
+        }
+    }
+    let mut positions = vec![];
+    let mut normals = vec![];
+    let mut uvs = vec![];
+
+    // The seam is one column twice, so that u runs all the way from 0 to 1
+    for column in 0..=segments {
+        let u = column as f64 / segments as f64;
+        let (sin, cos) = (u * TAU).sin_cos();
+        for vertex in vertices.iter() {
+            let (radius, height) = vertex.point;
+            let (normal_radius, normal_height) = vertex.normal;
+            positions.push(Tuple::point(radius * cos, height, radius * sin));
+            normals.push(Tuple::vector(
+                normal_radius * cos,
+                normal_height,
+                normal_radius * sin,
+            ));
+            uvs.push((u, vertex.v));
+        }
+    }
+    let mut triangles = stitch(0, vertices.len(), segments + 1, &vertices);
+
+    if capped {
+        let ends = [
+            (profile[0], profile[1]),
+            (profile[profile.len() - 1], profile[profile.len() - 2]),
+        ];
+        for ((radius, height), (_, next_height)) in ends {
+            if radius.abs() < EPSILON {
+                continue;
+            }
+
+            // The cap faces away from the rest of the profile
+            let normal = Tuple::vector(0., if next_height > height { -1. } else { 1. }, 0.);
+            let center = positions.len() as u32;
+            positions.push(Tuple::point(0., height, 0.));
+            normals.push(normal);
+            uvs.push((0.5, 0.5));
+            for column in 0..segments {
+                let (sin, cos) = (column as f64 / segments as f64 * TAU).sin_cos();
+                positions.push(Tuple::point(radius * cos, height, radius * sin));
+                normals.push(normal);
+                uvs.push(((cos + 1.) / 2., (sin + 1.) / 2.));
+            }
+            for column in 0..segments as u32 {
+                let next = (column + 1) % segments as u32;
+                triangles.push([center, center + 1 + column, center + 1 + next]);
+            }
+        }
+    }
+
+    finish(positions, normals, uvs, triangles)
+}
+
+fn cross_2d(o: (f64, f64), a: (f64, f64), b: (f64, f64)) -> f64 {
+    (a.0 - o.0) * (b.1 - o.1) - (a.1 - o.1) * (b.0 - o.0)
+}
+
+/// Twice the area of a polygon, negative if it winds clockwise.
+fn signed_area(polygon: &[(f64, f64)]) -> f64 {
+    (0..polygon.len())
+        .map(|i| cross_2d((0., 0.), polygon[i], polygon[(i + 1) % polygon.len()]))
+        .sum()
+}
+
+/// Triangulates a simple polygon by ear clipping, returning indices into it.
+fn triangulate(polygon: &[(f64, f64)]) -> Vec<[u32; 3]> {
+    let mut remaining: Vec<usize> = (0..polygon.len()).collect();
+    if signed_area(polygon) < 0. {
+        remaining.reverse();
+    }
+
+    let mut triangles = vec![];
+    while remaining.len() > 3 {
+        let n = remaining.len();
+        let ear = (0..n).find(|&i| {
+            let (a, b, c) = (
+                remaining[(i + n - 1) % n],
+                remaining[i],
+                remaining[(i + 1) % n],
+            );
+            let (pa, pb, pc) = (polygon[a], polygon[b], polygon[c]);
+            // Convex corner with no other vertex inside the triangle it cuts off
+            cross_2d(pa, pb, pc) > 0.
+                && remaining.iter().all(|&other| {
+                    let p = polygon[other];
+                    [a, b, c].contains(&other)
+                        || cross_2d(pa, pb, p) < 0.
+                        || cross_2d(pb, pc, p) < 0.
+                        || cross_2d(pc, pa, p) < 0.
+                })
+        });
+        // Degenerate polygons may have no proper ear left, cut anywhere
+        let i = ear.unwrap_or(0);
+        let (a, b, c) = (
+            remaining[(i + n - 1) % n],
+            remaining[i],
+            remaining[(i + 1) % n],
+        );
+        triangles.push([a as u32, b as u32, c as u32]);
+        remaining.remove(i);
+    }
+    triangles.push([
+        remaining[0] as u32,
+        remaining[1] as u32,
+        remaining[2] as u32,
+    ]);
+    triangles
+}
+
+/// Catmull-Rom spline through the given points, with `steps` samples per span, as a
+/// path to extrude along.
+pub fn spline(points: &[Tuple], steps: usize) -> Vec<Tuple> {
+    assert!(points.len() >= 2, "Splines need at least two points!");
+    assert!(steps >= 1, "Splines need at least one step per span!");
+    let n = points.len();
+    let mut path = vec![];
+    for span in 0..n - 1 {
+        let p0 = points[span.saturating_sub(1)];
+        let (p1, p2) = (points[span], points[span + 1]);
+        let p3 = points[(span + 2).min(n - 1)];
+        for step in 0..steps {
+            let t = step as f64 / steps as f64;
+            let (t2, t3) = (t * t, t * t * t);
+            path.push(
+                p1 + ((p2 - p0) * t
+                    + (p0 * 2. - p1 * 5. + p2 * 4. - p3) * t2
+                    + (p1 * 3. - p0 - p2 * 3. + p3) * t3)
+                    * 0.5,
+            );
+        }
+    }
+    path.push(points[n - 1]);
+    path
+}
+
+/// Sweeps a closed profile along a path of points. The profile's x axis is kept
+/// level and its y axis points up as far as the path allows, with the frame carried
+/// along the path without twisting. `capped` closes both ends. Clockwise profiles
+/// are reversed, so that the normals always face out.
+pub fn extrude(profile: &[(f64, f64)], path: &[Tuple], capped: bool) -> Mesh {
+    assert!(
+        profile.len() >= 3,
+        "Extrusion profiles need at least three points!"
+    );
+    assert!(path.len() >= 2, "Extrusion paths need at least two points!");
+    let mut profile = profile.to_vec();
+    if signed_area(&profile) < 0. {
+        profile.reverse();
+    }
+    let profile = &profile[..];
+    let vertices = profile_vertices(profile, true);
+    let tangents: Vec<Tuple> = (0..path.len())
+        .map(|i| (path[(i + 1).min(path.len() - 1)] - path[i.saturating_sub(1)]).normalize())
+        .collect();
+
+    let mut frames = vec![];
+    let first = tangents[0];
+    let up = if first.y.abs() < 0.9 {
+        Tuple::vector(0., 1., 0.)
+    } else {
+        Tuple::vector(0., 0., -1.)
+    };
+    let mut y_axis = (up - first * up.dot(first)).normalize();
+    for tangent in tangents.iter() {
+        y_axis = (y_axis - *tangent * y_axis.dot(*tangent)).normalize();
+        frames.push((y_axis.cross(*tangent), y_axis));
+    }
+    let place = |(x, y): (f64, f64), (x_axis, y_axis): (Tuple, Tuple)| x_axis * x + y_axis * y;
+
+    let mut positions = vec![];
+    let mut normals = vec![];
+    let mut uvs = vec![];
+    for (column, point) in path.iter().enumerate() {
+        let u = column as f64 / (path.len() - 1) as f64;
+        for vertex in vertices.iter() {
+            positions.push(*point + place(vertex.point, frames[column]));
+            normals.push(place(vertex.normal, frames[column]));
+            uvs.push((u, vertex.v));
+        }
+    }
+    let mut triangles = stitch(0, vertices.len(), path.len(), &vertices);
+
+    if capped {
+        let ends = [
+            (0, -tangents[0]),
+            (path.len() - 1, tangents[path.len() - 1]),
+        ];
+        // Cap uvs span the profile's bounds, like those of a lathe's caps
+        let low = profile
+            .iter()
+            .fold((f64::INFINITY, f64::INFINITY), |low, &(x, y)| {
+                (low.0.min(x), low.1.min(y))
+            });
+        let high = profile
+            .iter()
+            .fold((f64::NEG_INFINITY, f64::NEG_INFINITY), |high, &(x, y)| {
+                (high.0.max(x), high.1.max(y))
+            });
+        for (column, normal) in ends {
+            let start = positions.len() as u32;
+            for &(x, y) in profile.iter() {
+                positions.push(path[column] + place((x, y), frames[column]));
+                normals.push(normal);
+                uvs.push((
+                    (x - low.0) / (high.0 - low.0),
+                    (y - low.1) / (high.1 - low.1),
+                ));
+            }
+            triangles.extend(
+                triangulate(profile)
+                    .iter()
+                    .map(|[a, b, c]| [start + a, start + b, start + c]),
+            );
+        }
+    }
+
+    finish(positions, normals, uvs, triangles)
+}
+
+#[cfg(test)]
+fn agrees_with_normals(mesh: &Mesh) -> bool {
+    (0..mesh.triangles().len()).all(|primitive| {
+        let [p1, p2, p3] = mesh.vertices(primitive);
+        let face = (p3 - p1).cross(p2 - p1);
+        mesh.triangles()[primitive]
+            .iter()
+            .all(|&index| face.dot(mesh.normals()[index as usize]) > 0.)
+    })
+}
+
+#[test]
+fn lathed_cylinder() {
+    let mesh = lathe(&[(1., 0.), (1., 2.)], 8, true);
+
+    // Two rows of nine columns for the side, and a center plus rim for each cap
+    assert_eq!(2 * 9 + 2 * 9, mesh.positions().len());
+    assert_eq!(2 * 8 + 2 * 8, mesh.triangles().len());
+    assert!(agrees_with_normals(&mesh));
+
+    // The seam column repeats the first one
+    assert_eq!(mesh.positions()[0], mesh.positions()[16]);
+    assert_eq!((1., 0.), mesh.uvs()[16]);
+
+    let top = Ray::create(Tuple::point(0.1, 5., 0.2), Tuple::vector(0., -1., 0.));
+    let xs = mesh.intersect(&top);
+    assert!(approx_eq(3., xs[0].t));
+    assert_eq!(
+        Tuple::vector(0., 1., 0.),
+        mesh.normal_at(top.position(xs[0].t), &xs[0])
+    );
+
+    let side = Ray::create(Tuple::point(0.05, 1.03, -5.), Tuple::vector(0., 0., 1.));
+    let xs = mesh.intersect(&side);
+    let normal = mesh.normal_at(side.position(xs[0].t), &xs[0]);
+    assert!((normal - Tuple::vector(0., 0., -1.)).magnitude() < 0.1);
+}
+
+#[test]
+fn lathe_keeps_sharp_corners() {
+    // A cup: floor out from the axis, then a wall straight up
+    let mesh = lathe(&[(0., 0.), (1., 0.), (1., 1.)], 4, false);
+
+    // The corner is split, so three profile points give four rows
+    assert_eq!(4 * 5, mesh.positions().len());
+    assert_eq!(Tuple::vector(0., -1., 0.), mesh.normals()[1]);
+    assert_eq!(Tuple::vector(1., 0., 0.), mesh.normals()[2]);
+    assert!(agrees_with_normals(&mesh));
+}
+
+#[test]
+fn lathe_smooths_gentle_bends() {
+    let profile: Vec<(f64, f64)> = (0..=8)
+        .map(|i| {
+            let angle = (i as f64 / 8. - 0.5) * std::f64::consts::PI;
+            (angle.cos(), angle.sin())
+        })
+        .collect();
+    let sphere = lathe(&profile, 16, false);
+
+    assert_eq!(9 * 17, sphere.positions().len());
+    for (point, normal) in sphere.positions().iter().zip(sphere.normals().iter()) {
+        assert_eq!(*point - Tuple::point(0., 0., 0.), *normal);
+    }
+}
+
+#[test]
+fn ear_clipping_concave_profile() {
+    let l_shape = [(0., 0.), (2., 0.), (2., 1.), (1., 1.), (1., 2.), (0., 2.)];
+    let triangles = triangulate(&l_shape);
+
+    assert_eq!(4, triangles.len());
+    let area: f64 = triangles
+        .iter()
+        .map(|&[a, b, c]| {
+            cross_2d(
+                l_shape[a as usize],
+                l_shape[b as usize],
+                l_shape[c as usize],
+            )
+        })
+        .map(|doubled| doubled / 2.)
+        .sum();
+    assert!(approx_eq(3., area));
+}
+
+#[test]
+fn extruding_square_along_line() {
+    let square = [(-1., -1.), (1., -1.), (1., 1.), (-1., 1.)];
+    let mesh = extrude(
+        &square,
+        &[Tuple::point(0., 0., 0.), Tuple::point(0., 0., 4.)],
+        true,
+    );
+
+    // Each corner is sharp, so every side gets its own pair of vertices
+    assert_eq!(2 * 8 + 2 * 4, mesh.positions().len());
+    assert_eq!(4 * 2 + 2 * 2, mesh.triangles().len());
+    assert!(agrees_with_normals(&mesh));
+
+    let side = Ray::create(Tuple::point(0.3, 5., 2.2), Tuple::vector(0., -1., 0.));
+    let xs = mesh.intersect(&side);
+    assert!(approx_eq(4., xs[0].t));
+    assert_eq!(
+        Tuple::vector(0., 1., 0.),
+        mesh.normal_at(side.position(xs[0].t), &xs[0])
+    );
+
+    let end = Ray::create(Tuple::point(0.3, 0.2, 6.), Tuple::vector(0., 0., -1.));
+    let xs = mesh.intersect(&end);
+    assert!(approx_eq(2., xs[0].t));
+    assert_eq!(
+        Tuple::vector(0., 0., 1.),
+        mesh.normal_at(end.position(xs[0].t), &xs[0])
+    );
+}
+
+#[test]
+fn clockwise_profiles_face_out() {
+    let square = [(-1., -1.), (-1., 1.), (1., 1.), (1., -1.)];
+    let mesh = extrude(
+        &square,
+        &[Tuple::point(0., 0., 0.), Tuple::point(0., 0., 4.)],
+        true,
+    );
+
+    assert!(agrees_with_normals(&mesh));
+    let side = Ray::create(Tuple::point(0.3, 5., 2.2), Tuple::vector(0., -1., 0.));
+    let xs = mesh.intersect(&side);
+    assert_eq!(
+        Tuple::vector(0., 1., 0.),
+        mesh.normal_at(side.position(xs[0].t), &xs[0])
+    );
+
+    // The caps' uvs cover the unit square from corner to corner
+    let caps = &mesh.uvs()[mesh.uvs().len() - 8..];
+    assert!(caps.contains(&(0., 0.)) && caps.contains(&(1., 1.)));
+    assert!(caps
+        .iter()
+        .all(|&(u, v)| (0. ..=1.).contains(&u) && (0. ..=1.).contains(&v)));
+}
+
+#[test]
+fn extruding_along_spline() {
+    let points = [
+        Tuple::point(0., 0., 0.),
+        Tuple::point(2., 1., 2.),
+        Tuple::point(4., 0., 2.),
+        Tuple::point(6., 0., 0.),
+    ];
+    let path = spline(&points, 8);
+
+    assert_eq!(3 * 8 + 1, path.len());
+    for (i, point) in points.iter().enumerate() {
+        assert_eq!(*point, path[i * 8]);
+    }
+
+    let circle: Vec<(f64, f64)> = (0..12)
+        .map(|i| {
+            let angle = i as f64 / 12. * TAU;
+            (0.2 * angle.cos(), 0.2 * angle.sin())
+        })
+        .collect();
+    let tube = extrude(&circle, &path, true);
+    assert!(agrees_with_normals(&tube));
+
+    // Every ring stays square to the path and at the right distance from it
+    let ring = 13;
+    for (column, point) in path.iter().enumerate() {
+        let tangent =
+            (path[(column + 1).min(path.len() - 1)] - path[column.saturating_sub(1)]).normalize();
+        for row in 0..ring {
+            let offset = tube.positions()[column * ring + row] - *point;
+            assert!(approx_eq(0.2, offset.magnitude()));
+            assert!(approx_eq(0., offset.dot(tangent)));
+        }
+    }
+}
+
+#[test]
+#[should_panic(expected = "Lathe profiles need at least two points!")]
+fn lathe_needs_profile() {
+    lathe(&[], 8, false);
+}
+
+#[test]
+#[should_panic(expected = "Lathes need at least three segments!")]
+fn lathe_needs_segments() {
+    lathe(&[(1., 0.), (1., 2.)], 0, false);
+}
+
+#[test]
+#[should_panic(expected = "Splines need at least two points!")]
+fn spline_needs_points() {
+    spline(&[], 8);
+}
+
+#[test]
+#[should_panic(expected = "Splines need at least one step per span!")]
+fn spline_needs_steps() {
+    spline(&[Tuple::point(0., 0., 0.), Tuple::point(1., 0., 0.)], 0);
+}
+
+#[test]
+#[should_panic(expected = "Extrusion profiles need at least three points!")]
+fn extrusion_needs_profile() {
+    let path = [Tuple::point(0., 0., 0.), Tuple::point(0., 0., 1.)];
+    extrude(&[(0., 0.), (1., 0.)], &path, true);
+}
+
+#[test]
+#[should_panic(expected = "Extrusion paths need at least two points!")]
+fn extrusion_needs_path() {
+    extrude(
+        &[(0., 0.), (1., 0.), (0., 1.)],
+        &[Tuple::point(0., 0., 0.)],
+        true,
+    );
+}
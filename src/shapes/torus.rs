@@ -0,0 +1,196 @@
+use crate::intersection::intersection::Intersection;
+use crate::material::material::Material;
+use crate::math::bounds::Bounds;
+use crate::math::matrix::{Matrix, IDENTITY_MATRIX};
+use crate::math::polynomial::solve_quartic;
+#[cfg(test)]
+use crate::math::transformation::scaling;
+use crate::math::tuple::Tuple;
+use crate::ray::ray::Ray;
+use crate::shapes::shape::Shape;
+#[cfg(test)]
+use ray_tracer::approx_eq;
+
+/// Torus centered at the origin, lying in the xz plane. The tube of radius
+/// `minor_radius` sweeps around the y axis at distance `major_radius`.
+#[derive(Debug)]
+pub struct Torus {
+    pub major_radius: f64,
+    pub minor_radius: f64,
+    transform: Matrix,
+    inverse: Matrix,
+    material: Material,
+}
+
+impl Torus {
+    pub fn create(major_radius: f64, minor_radius: f64) -> Torus {
+        Torus {
+            major_radius,
+            minor_radius,
+            transform: IDENTITY_MATRIX,
+            inverse: IDENTITY_MATRIX,
+            material: Material::create(),
+        }
+    }
+}
+
+impl Shape for Torus {
+    fn transform(&self) -> &Matrix {
+        &self.transform
+    }
+
+    fn inverse_transform(&self) -> &Matrix {
+        &self.inverse
+    }
+
+    fn set_transform(&mut self, transform: Matrix) {
+        self.transform = transform;
+        self.inverse = transform.inverse();
+    }
+
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn set_material(&mut self, material: Material) {
+        self.material = material;
+    }
+
+    fn local_intersect(&self, ray: &Ray) -> Vec<Intersection<'_>> {
+        let d = ray.direction;
+        let dd = d.x * d.x + d.y * d.y + d.z * d.z;
+
+        // Solve from the point closest to the center, which keeps the quartic's
+        // coefficients small for rays that start far away, and lets us reject rays
+        // that never enter the bounding sphere.
+        let shift = -(ray.origin.x * d.x + ray.origin.y * d.y + ray.origin.z * d.z) / dd;
+        let o = ray.position(shift);
+        let oo = o.x * o.x + o.y * o.y + o.z * o.z;
+        let bound = self.major_radius + self.minor_radius;
+        if oo > bound * bound {
+            return vec![];
+        }
+
+        let od = o.x * d.x + o.y * d.y + o.z * d.z;
+        let r2 = self.major_radius * self.major_radius;
+        let k = oo + r2 - self.minor_radius * self.minor_radius;
+
+        solve_quartic(
+            dd * dd,
+            4. * dd * od,
+            2. * dd * k + 4. * od * od - 4. * r2 * (d.x * d.x + d.z * d.z),
+            4. * od * k - 8. * r2 * (o.x * d.x + o.z * d.z),
+            k * k - 4. * r2 * (o.x * o.x + o.z * o.z),
+        )
+        .into_iter()
+        .map(|t| Intersection::create(t + shift, self))
+        .collect()
+    }
+
+    fn local_normal_at(&self, point: Tuple, _hit: &Intersection) -> Tuple {
+        // Gradient of (x² + y² + z² + R² - r²)² - 4R²(x² + z²), halved.
+        let r2 = self.major_radius * self.major_radius;
+        let s = point.x * point.x + point.y * point.y + point.z * point.z + r2
+            - self.minor_radius * self.minor_radius;
+
+        Tuple::vector(
+            point.x * (s - 2. * r2),
+            point.y * s,
+            point.z * (s - 2. * r2),
+        )
+    }
+
+    fn local_contains(&self, point: Tuple) -> bool {
+        let ring = (point.x * point.x + point.z * point.z).sqrt() - self.major_radius;
+        ring * ring + point.y * point.y < self.minor_radius * self.minor_radius
+    }
+
+    fn local_bounds(&self) -> Bounds {
+        let outer = self.major_radius + self.minor_radius;
+        Bounds::create(
+            Tuple::point(-outer, -self.minor_radius, -outer),
+            Tuple::point(outer, self.minor_radius, outer),
+        )
+    }
+}
+
+#[test]
+fn ray_through_torus_hits_tube_twice() {
+    let torus = Torus::create(1., 0.25);
+    let ray = Ray::create(Tuple::point(0., 0., -5.), Tuple::vector(0., 0., 1.));
+    let xs = torus.local_intersect(&ray);
+
+    assert_eq!(4, xs.len());
+    assert!(approx_eq(3.75, xs[0].t));
+    assert!(approx_eq(4.25, xs[1].t));
+    assert!(approx_eq(5.75, xs[2].t));
+    assert!(approx_eq(6.25, xs[3].t));
+}
+
+#[test]
+fn ray_through_torus_hole_misses() {
+    let torus = Torus::create(1., 0.25);
+    let ray = Ray::create(Tuple::point(0., 5., 0.), Tuple::vector(0., -1., 0.));
+
+    assert!(torus.local_intersect(&ray).is_empty());
+}
+
+#[test]
+fn ray_outside_bounding_sphere_misses() {
+    let torus = Torus::create(1., 0.25);
+    let ray = Ray::create(Tuple::point(0., 2., -5.), Tuple::vector(0., 0., 1.));
+
+    assert!(torus.local_intersect(&ray).is_empty());
+}
+
+#[test]
+fn vertical_ray_through_tube() {
+    let torus = Torus::create(1., 0.25);
+    let ray = Ray::create(Tuple::point(1., 5., 0.), Tuple::vector(0., -1., 0.));
+    let xs = torus.local_intersect(&ray);
+
+    assert_eq!(2, xs.len());
+    assert!(approx_eq(4.75, xs[0].t));
+    assert!(approx_eq(5.25, xs[1].t));
+}
+
+#[test]
+fn distant_ray_keeps_precision() {
+    let torus = Torus::create(3., 0.01);
+    let ray = Ray::create(Tuple::point(3., 0., -1e5), Tuple::vector(0., 0., 1.));
+    let xs = torus.local_intersect(&ray);
+    let half_chord = f64::sqrt(3.01 * 3.01 - 9.);
+
+    assert_eq!(2, xs.len());
+    assert!(approx_eq(1e5 - half_chord, xs[0].t));
+    assert!(approx_eq(1e5 + half_chord, xs[1].t));
+}
+
+#[test]
+fn normal_vector_on_torus() {
+    let torus = Torus::create(1., 0.25);
+    let hit = Intersection::create(0., &torus);
+    let examples = [
+        (Tuple::point(1.25, 0., 0.), Tuple::vector(1., 0., 0.)),
+        (Tuple::point(0.75, 0., 0.), Tuple::vector(-1., 0., 0.)),
+        (Tuple::point(1., 0.25, 0.), Tuple::vector(0., 1., 0.)),
+        (Tuple::point(0., -0.25, 1.), Tuple::vector(0., -1., 0.)),
+        (Tuple::point(0., 0., -0.75), Tuple::vector(0., 0., 1.)),
+    ];
+
+    for (point, normal) in examples.iter() {
+        assert_eq!(*normal, torus.normal_at(*point, &hit));
+    }
+}
+
+#[test]
+fn point_inside_torus() {
+    let mut torus = Torus::create(1., 0.25);
+
+    assert!(torus.contains(Tuple::point(1., 0.2, 0.)));
+    assert!(torus.contains(Tuple::point(0., 0., -0.8)));
+    assert!(!torus.contains(Tuple::point(0., 0., 0.)));
+
+    torus.set_transform(scaling(2., 2., 2.));
+    assert!(torus.contains(Tuple::point(1.6, 0., 0.)));
+}
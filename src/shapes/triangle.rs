@@ -0,0 +1,184 @@
+use crate::intersection::intersection::Intersection;
+use crate::material::material::Material;
+use crate::math::bounds::Bounds;
+use crate::math::matrix::{Matrix, IDENTITY_MATRIX};
+use crate::math::tuple::Tuple;
+use crate::ray::ray::Ray;
+use crate::shapes::shape::Shape;
+#[cfg(test)]
+use ray_tracer::approx_eq;
+use ray_tracer::EPSILON;
+
+#[derive(Debug)]
+pub struct Triangle {
+    pub p1: Tuple,
+    pub p2: Tuple,
+    pub p3: Tuple,
+    pub e1: Tuple,
+    pub e2: Tuple,
+    pub normal: Tuple,
+    transform: Matrix,
+    inverse: Matrix,
+    material: Material,
+}
+
+impl Triangle {
+    pub fn create(p1: Tuple, p2: Tuple, p3: Tuple) -> Triangle {
+        let e1 = p2 - p1;
+        let e2 = p3 - p1;
+
+        Triangle {
+            p1,
+            p2,
+            p3,
+            e1,
+            e2,
+            normal: e2.cross(e1).normalize(),
+            transform: IDENTITY_MATRIX,
+            inverse: IDENTITY_MATRIX,
+            material: Material::create(),
+        }
+    }
+}
+
+/// Möller–Trumbore intersection, returning `(t, u, v)` for a hit inside the triangle.
+pub fn moller_trumbore(ray: &Ray, p1: Tuple, e1: Tuple, e2: Tuple) -> Option<(f64, f64, f64)> {
+    let dir_cross_e2 = ray.direction.cross(e2);
+    let det = e1.dot(dir_cross_e2);
+    if det.abs() < EPSILON {
+        return None;
+    }
+
+    let f = 1. / det;
+    let p1_to_origin = ray.origin - p1;
+    let u = f * p1_to_origin.dot(dir_cross_e2);
+    if !(0. ..=1.).contains(&u) {
+        return None;
+    }
+
+    let origin_cross_e1 = p1_to_origin.cross(e1);
+    let v = f * ray.direction.dot(origin_cross_e1);
+    if v < 0. || u + v > 1. {
+        return None;
+    }
+
+    Some((f * e2.dot(origin_cross_e1), u, v))
+}
+
+impl Shape for Triangle {
+    fn transform(&self) -> &Matrix {
+        &self.transform
+    }
+
+    fn inverse_transform(&self) -> &Matrix {
+        &self.inverse
+    }
+
+    fn set_transform(&mut self, transform: Matrix) {
+        self.transform = transform;
+        self.inverse = transform.inverse();
+    }
+
+    fn material(&self) -> &Material {
+        &self.material
+    }
+
+    fn set_material(&mut self, material: Material) {
+        self.material = material;
+    }
+
+    fn local_intersect(&self, ray: &Ray) -> Vec<Intersection<'_>> {
+        match moller_trumbore(ray, self.p1, self.e1, self.e2) {
+            Some((t, u, v)) => vec![Intersection::create_with_uv(t, self, u, v)],
+            None => vec![],
+        }
+    }
+
+    fn local_normal_at(&self, _point: Tuple, _hit: &Intersection) -> Tuple {
+        self.normal
+    }
+
+    fn local_bounds(&self) -> Bounds {
+        Bounds::from_points(&[self.p1, self.p2, self.p3])
+    }
+}
+
+#[cfg(test)]
+fn test_triangle() -> Triangle {
+    Triangle::create(
+        Tuple::point(0., 1., 0.),
+        Tuple::point(-1., 0., 0.),
+        Tuple::point(1., 0., 0.),
+    )
+}
+
+#[test]
+fn constructing_triangle() {
+    let t = test_triangle();
+
+    assert_eq!(Tuple::point(0., 1., 0.), t.p1);
+    assert_eq!(Tuple::point(-1., 0., 0.), t.p2);
+    assert_eq!(Tuple::point(1., 0., 0.), t.p3);
+    assert_eq!(Tuple::vector(-1., -1., 0.), t.e1);
+    assert_eq!(Tuple::vector(1., -1., 0.), t.e2);
+    assert_eq!(Tuple::vector(0., 0., -1.), t.normal);
+}
+
+#[test]
+fn normal_on_triangle_is_constant() {
+    let t = test_triangle();
+    let hit = Intersection::create(0., &t);
+
+    assert_eq!(t.normal, t.local_normal_at(Tuple::point(0., 0.5, 0.), &hit));
+    assert_eq!(
+        t.normal,
+        t.local_normal_at(Tuple::point(-0.5, 0.75, 0.), &hit)
+    );
+    assert_eq!(
+        t.normal,
+        t.local_normal_at(Tuple::point(0.5, 0.25, 0.), &hit)
+    );
+}
+
+#[test]
+fn ray_parallel_to_triangle_misses() {
+    let t = test_triangle();
+    let ray = Ray::create(Tuple::point(0., -1., -2.), Tuple::vector(0., 1., 0.));
+
+    assert!(t.local_intersect(&ray).is_empty());
+}
+
+#[test]
+fn ray_misses_triangle_edges() {
+    let t = test_triangle();
+    let origins = [
+        Tuple::point(1., 1., -2.),
+        Tuple::point(-1., 1., -2.),
+        Tuple::point(0., -1., -2.),
+    ];
+
+    for origin in origins.iter() {
+        let ray = Ray::create(*origin, Tuple::vector(0., 0., 1.));
+        assert!(t.local_intersect(&ray).is_empty());
+    }
+}
+
+#[test]
+fn ray_strikes_triangle() {
+    let t = test_triangle();
+    let ray = Ray::create(Tuple::point(0., 0.5, -2.), Tuple::vector(0., 0., 1.));
+    let xs = t.local_intersect(&ray);
+
+    assert_eq!(1, xs.len());
+    assert!(approx_eq(2., xs[0].t));
+}
+
+#[test]
+fn intersection_records_barycentric_coordinates() {
+    let t = test_triangle();
+    let ray = Ray::create(Tuple::point(-0.2, 0.3, -2.), Tuple::vector(0., 0., 1.));
+    let xs = t.local_intersect(&ray);
+
+    assert!(approx_eq(0.45, xs[0].u));
+    assert!(approx_eq(0.25, xs[0].v));
+}
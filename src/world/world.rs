@@ -0,0 +1,149 @@
+use crate::acceleration::accelerator::{shape_bounds, Accelerator};
+#[cfg(test)]
+use crate::acceleration::bvh::Bvh;
+#[cfg(test)]
+use crate::acceleration::grid::Grid;
+#[cfg(test)]
+use crate::acceleration::kd_tree::KdTree;
+use crate::intersection::intersection::Intersection;
+use crate::math::bounds::Bounds;
+#[cfg(test)]
+use crate::math::transformation::translation;
+#[cfg(test)]
+use crate::math::tuple::Tuple;
+use crate::ray::ray::Ray;
+#[cfg(test)]
+use crate::shapes::quadric::Quadric;
+use crate::shapes::shape::Shape;
+#[cfg(test)]
+use ray_tracer::approx_eq;
+
+/// Everything in a scene. Rays are tested against each object in turn until an
+/// accelerator is built over them with `accelerate`.
+#[derive(Debug)]
+pub struct World {
+    objects: Vec<Box<dyn Shape>>,
+    accelerator: Option<Box<dyn Accelerator>>,
+}
+
+impl World {
+    pub fn create() -> World {
+        World {
+            objects: vec![],
+            accelerator: None,
+        }
+    }
+
+    /// Drops the accelerator, which would not know about the new object.
+    pub fn add_object(&mut self, object: Box<dyn Shape>) {
+        self.objects.push(object);
+        self.accelerator = None;
+    }
+
+    pub fn objects(&self) -> &[Box<dyn Shape>] {
+        &self.objects
+    }
+
+    /// Builds an accelerator over the objects from their bounds, e.g. with
+    /// `|bounds| Box::new(Bvh::create(bounds, 4))`.
+    pub fn accelerate(&mut self, build: impl FnOnce(&[Bounds]) -> Box<dyn Accelerator>) {
+        self.accelerator = Some(build(&shape_bounds(&self.objects)));
+    }
+
+    /// Closest hit in front of the ray.
+    pub fn hit(&self, ray: &Ray) -> Option<Intersection<'_>> {
+        match &self.accelerator {
+            Some(accelerator) => accelerator.intersect(&self.objects, ray),
+            None => self
+                .objects
+                .iter()
+                .filter_map(|object| object.intersect(ray).hit().cloned())
+                .min_by(|a, b| a.t.total_cmp(&b.t)),
+        }
+    }
+
+    /// Whether anything is in the way of the ray before `max_t`, as between a point
+    /// and a light. Stops at the first object found, which need not be the closest.
+    pub fn occluded(&self, ray: &Ray, max_t: f64) -> bool {
+        match &self.accelerator {
+            Some(accelerator) => accelerator.occluded(&self.objects, ray, max_t),
+            None => self
+                .objects
+                .iter()
+                .any(|object| object.occludes(ray, max_t)),
+        }
+    }
+}
+
+#[cfg(test)]
+fn sphere_at(x: f64, y: f64, z: f64) -> Box<dyn Shape> {
+    let mut sphere = Quadric::create([1., 1., 1., 0., 0., 0., 0., 0., 0., -1.]);
+    sphere.set_transform(translation(x, y, z));
+    Box::new(sphere)
+}
+
+#[test]
+fn occluded_only_before_max_t() {
+    let mut world = World::create();
+    world.add_object(sphere_at(0., 0., 0.));
+    let ray = Ray::create(Tuple::point(0., 0., -5.), Tuple::vector(0., 0., 1.));
+
+    assert!(world.occluded(&ray, 10.));
+    assert!(world.occluded(&ray, 4.5));
+    assert!(!world.occluded(&ray, 4.));
+    let away = Ray::create(Tuple::point(0., 0., -5.), Tuple::vector(0., 0., -1.));
+    assert!(!world.occluded(&away, 10.));
+}
+
+#[test]
+fn adding_objects_drops_accelerator() {
+    let mut world = World::create();
+    world.add_object(sphere_at(0., 0., 10.));
+    world.accelerate(|bounds| Box::new(Bvh::create(bounds, 4)));
+    world.add_object(sphere_at(0., 0., 0.));
+    assert_eq!(2, world.objects().len());
+
+    let ray = Ray::create(Tuple::point(0., 0., -5.), Tuple::vector(0., 0., 1.));
+    assert!(world.occluded(&ray, 5.));
+    assert!(approx_eq(4., world.hit(&ray).unwrap().t));
+}
+
+#[test]
+fn accelerated_worlds_agree_with_testing_every_object() {
+    let mut world = World::create();
+    for n in 0..200 {
+        world.add_object(sphere_at(
+            (n % 15) as f64 * 2.5,
+            (n % 7) as f64 * 0.4,
+            (n / 15) as f64 * 2.5,
+        ));
+    }
+    let rays: Vec<Ray> = (0..200)
+        .map(|n| {
+            let origin = Tuple::point((n % 13) as f64 * 3., 6., (n % 11) as f64 * 3. - 3.);
+            let target = Tuple::point((n % 17) as f64 * 2., 0., (n % 19) as f64 * 1.8);
+            Ray::create(origin, (target - origin).normalize())
+        })
+        .collect();
+    let expected: Vec<(Option<f64>, bool)> = rays
+        .iter()
+        .map(|ray| (world.hit(ray).map(|hit| hit.t), world.occluded(ray, 6.5)))
+        .collect();
+
+    type Build = fn(&[Bounds]) -> Box<dyn Accelerator>;
+    let builds: [Build; 4] = [
+        |bounds| Box::new(Bvh::create(bounds, 2)),
+        |bounds| Box::new(Bvh::create(bounds, 2).flatten()),
+        |bounds| Box::new(KdTree::create(bounds, 2)),
+        |bounds| Box::new(Grid::create(bounds)),
+    ];
+    for build in builds {
+        world.accelerate(build);
+        for (ray, (hit, occluded)) in rays.iter().zip(&expected) {
+            assert_eq!(*occluded, world.occluded(ray, 6.5));
+            let t = world.hit(ray).map(|hit| hit.t);
+            assert_eq!(hit.is_some(), t.is_some());
+            assert!(approx_eq(hit.unwrap_or(0.), t.unwrap_or(0.)));
+        }
+    }
+}